@@ -121,6 +121,105 @@ pub fn normalize_tool_name(identifier: &FunctionIdentifier) -> String {
     normalized
 }
 
+/// Prefix of the interface exported by components targeting the `wasi:cli/command` world.
+const WASI_CLI_RUN_PREFIX: &str = "wasi:cli/run@";
+
+/// Returns the name of the `wasi:cli/run` export if the component is a WASI CLI command, i.e.
+/// its only export is the `wasi:cli/run` interface and that interface only exports `run`.
+fn command_run_export(component: &Component, engine: &Engine) -> Option<String> {
+    let component_type = component.component_type();
+    let mut exports = component_type.exports(engine);
+    let (export_name, export_item) = exports.next()?;
+    if exports.next().is_some() || !export_name.starts_with(WASI_CLI_RUN_PREFIX) {
+        return None;
+    }
+
+    let ComponentItem::ComponentInstance(instance) = export_item else {
+        return None;
+    };
+
+    let mut funcs = instance.exports(engine);
+    match (funcs.next(), funcs.next()) {
+        (Some(("run", ComponentItem::ComponentFunc(_))), None) => Some(export_name.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the component targets the `wasi:cli/command` world and only exports `run`.
+///
+/// Such components are surfaced as a single tool taking `args`, `stdin` and an optional `env`,
+/// and returning the exit code together with the captured stdout and stderr.
+pub fn is_command_component(component: &Component, engine: &Engine) -> bool {
+    command_run_export(component, engine).is_some()
+}
+
+/// Returns the name of the single tool a WASI CLI command component is surfaced as, or `None`
+/// if the component is not a command. See [`is_command_component`].
+pub fn command_tool_name(component: &Component, engine: &Engine) -> Option<String> {
+    command_run_export(component, engine)
+        .map(|run_export| command_tool_metadata(run_export, false).normalized_name)
+}
+
+fn command_tool_metadata(run_export: String, output: bool) -> ToolMetadata {
+    let identifier = FunctionIdentifier {
+        package_name: None,
+        interface_name: Some(run_export),
+        function_name: "run".to_string(),
+    };
+    let normalized_name = normalize_tool_name(&identifier);
+
+    let mut tool_obj = Map::new();
+    tool_obj.insert("name".to_string(), json!(normalized_name));
+    tool_obj.insert(
+        "description".to_string(),
+        json!("Run the WASI command with the given arguments, standard input and environment"),
+    );
+    tool_obj.insert(
+        "inputSchema".to_string(),
+        json!({
+            "type": "object",
+            "properties": {
+                "args": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Command line arguments, excluding the program name"
+                },
+                "stdin": {
+                    "type": "string",
+                    "description": "Data written to the command's standard input"
+                },
+                "env": {
+                    "type": "object",
+                    "additionalProperties": {"type": "string"},
+                    "description": "Additional environment variables for this invocation. Only keys allowed by the component's policy may be set, and values provided by the policy or secrets cannot be replaced"
+                }
+            },
+            "required": ["args", "stdin"]
+        }),
+    );
+
+    if output {
+        tool_obj.insert(
+            "outputSchema".to_string(),
+            build_result_wrapper(json!({
+                "type": "object",
+                "properties": {
+                    "exit_code": {"type": "integer"},
+                    "stdout": {"type": "string"},
+                    "stderr": {"type": "string"}
+                },
+                "required": ["exit_code", "stdout", "stderr"]
+            })),
+        );
+    }
+
+    ToolMetadata {
+        identifier,
+        normalized_name,
+        schema: Value::Object(tool_obj),
+    }
+}
+
 /// Given a component and a wasmtime engine, return structured tool metadata with normalized names.
 ///
 /// The `output` parameter determines whether to include the output schema for functions.
@@ -129,6 +228,10 @@ pub fn component_exports_to_tools(
     engine: &Engine,
     output: bool,
 ) -> Vec<ToolMetadata> {
+    if let Some(run_export) = command_run_export(component, engine) {
+        return vec![command_tool_metadata(run_export, output)];
+    }
+
     let mut tools = Vec::new();
 
     for (export_name, export_item) in component.component_type().exports(engine) {
@@ -215,6 +318,10 @@ pub fn component_exports_to_tools_with_docs(
     output: bool,
     package_docs: &Value,
 ) -> Vec<ToolMetadata> {
+    if let Some(run_export) = command_run_export(component, engine) {
+        return vec![command_tool_metadata(run_export, output)];
+    }
+
     let mut tools = Vec::new();
 
     let context = GatherMetadataContext {
@@ -1242,6 +1349,56 @@ mod tests {
         assert_eq!(tools.len(), 0);
     }
 
+    #[test]
+    fn test_command_component_exports_single_tool() {
+        let engine = Engine::default();
+        let wat = r#"(component
+            (core module $m
+                (func (export "run") (result i32) i32.const 0)
+            )
+            (core instance $i (instantiate $m))
+            (type $run-ty (func (result (result))))
+            (func $run (type $run-ty) (canon lift (core func $i "run")))
+            (instance $run-inst (export "run" (func $run)))
+            (export "wasi:cli/run@0.2.0" (instance $run-inst))
+        )"#;
+        let component = Component::new(&engine, wat).unwrap();
+        assert!(is_command_component(&component, &engine));
+        assert_eq!(
+            command_tool_name(&component, &engine).as_deref(),
+            Some("wasi_cli_run_0_2_0_run")
+        );
+
+        let tools = component_exports_to_tools(&component, &engine, true);
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].normalized_name, "wasi_cli_run_0_2_0_run");
+        assert_eq!(
+            tools[0].identifier.interface_name.as_deref(),
+            Some("wasi:cli/run@0.2.0")
+        );
+
+        let input_schema = tools[0].schema.get("inputSchema").unwrap();
+        let properties = input_schema.get("properties").unwrap();
+        assert_eq!(properties["args"]["type"], "array");
+        assert_eq!(properties["stdin"]["type"], "string");
+        assert_eq!(properties["env"]["type"], "object");
+        assert_eq!(input_schema["required"], json!(["args", "stdin"]));
+
+        let output = result_schema(tools[0].schema.get("outputSchema").unwrap());
+        assert_eq!(output["required"], json!(["exit_code", "stdout", "stderr"]));
+    }
+
+    #[test]
+    fn test_non_command_component_is_not_detected() {
+        let mut config = wasmtime::Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+        let engine = Engine::new(&config).unwrap();
+        let component = Component::from_file(&engine, "testdata/filesystem.wasm").unwrap();
+        assert!(!is_command_component(&component, &engine));
+        assert!(command_tool_name(&component, &engine).is_none());
+    }
+
     #[test]
     fn test_root_component_exports() {
         let mut config = wasmtime::Config::new();
@@ -10,8 +10,8 @@ use rmcp::model::{CallToolRequestParam, CallToolResult, Content, Tool};
 use rmcp::{Peer, RoleServer};
 use serde_json::{json, Value};
 use tracing::{debug, error, info, instrument};
-use wassette::schema::{canonicalize_output_schema, ensure_structured_result};
-use wassette::{ComponentLoadOutcome, LifecycleManager, LoadResult};
+use wassette::schema::{canonicalize_output_schema, ensure_structured_result, is_error_result};
+use wassette::{ComponentCallError, ComponentLoadOutcome, LifecycleManager, LoadResult};
 
 #[instrument(skip(lifecycle_manager))]
 pub(crate) async fn get_component_tools(lifecycle_manager: &LifecycleManager) -> Result<Vec<Tool>> {
@@ -146,6 +146,10 @@ pub async fn handle_component_call(
     let tool_schema = lifecycle_manager
         .get_tool_schema_for_component(&component_id, &req.name)
         .await;
+    let normalized_schema = tool_schema
+        .as_ref()
+        .and_then(|schema| schema.get("outputSchema"))
+        .and_then(normalize_output_schema);
 
     let result = lifecycle_manager
         .execute_component_call(&component_id, &req.name, &serde_json::to_string(&args)?)
@@ -163,10 +167,8 @@ pub async fn handle_component_call(
             let display_value = unwrap_result_wrapper(&parsed_value);
             let response_text = value_to_text(&display_value)?;
 
-            let normalized_schema = tool_schema
-                .as_ref()
-                .and_then(|schema| schema.get("outputSchema"))
-                .and_then(normalize_output_schema);
+            // A WIT `result::err` is a tool-level failure; keep its payload as structured content
+            let is_error = is_error_result(normalized_schema.as_ref(), &parsed_value);
 
             let structured_content = normalized_schema.as_ref().map(|schema| {
                 align_structured_result_with_schema(Some(schema), parsed_value.clone())
@@ -177,7 +179,7 @@ pub async fn handle_component_call(
             Ok(CallToolResult {
                 content: Some(contents),
                 structured_content,
-                is_error: Some(false),
+                is_error: Some(is_error),
             })
         }
         Err(e) => {
@@ -187,11 +189,44 @@ pub async fn handle_component_call(
                 error = %e,
                 "Component function invocation failed"
             );
-            Err(anyhow::anyhow!(e.to_string()))
+            match e.downcast_ref::<ComponentCallError>() {
+                Some(call_error) => Ok(create_call_error_result(
+                    call_error,
+                    normalized_schema.is_some(),
+                )),
+                None => Err(anyhow::anyhow!(e.to_string())),
+            }
         }
     }
 }
 
+/// Create an error result carrying the failure's error code. Tools that declare an output schema
+/// get the code in the text content only, since an error object would not match their schema.
+fn create_call_error_result(error: &ComponentCallError, has_output_schema: bool) -> CallToolResult {
+    if has_output_schema {
+        return CallToolResult {
+            content: Some(vec![Content::text(format!(
+                "Error ({}): {}",
+                error.kind.code(),
+                error.message
+            ))]),
+            structured_content: None,
+            is_error: Some(true),
+        };
+    }
+
+    CallToolResult {
+        content: Some(vec![Content::text(format!("Error: {}", error.message))]),
+        structured_content: Some(json!({
+            "error": {
+                "code": error.kind.code(),
+                "message": error.message,
+            }
+        })),
+        is_error: Some(true),
+    }
+}
+
 fn parse_structured_result(result: &str) -> Value {
     serde_json::from_str(result).unwrap_or_else(|_| Value::String(result.to_string()))
 }
@@ -395,9 +430,21 @@ pub async fn handle_load_component_cli(
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing required argument: 'path'"))?;
 
-    info!(path, "Loading component (CLI mode)");
+    // Optional pinned digest the component must match
+    let digest = args.get("digest").and_then(|v| v.as_str());
 
-    match lifecycle_manager.load_component(path).await {
+    info!(path, digest, "Loading component (CLI mode)");
+
+    let result = match digest {
+        Some(digest) => {
+            lifecycle_manager
+                .load_component_with_digest(path, digest)
+                .await
+        }
+        None => lifecycle_manager.load_component(path).await,
+    };
+
+    match result {
         Ok(outcome) => {
             handle_tool_list_notification(None, &outcome.component_id, "load").await;
             create_load_component_success_result(&outcome)
@@ -685,6 +732,42 @@ mod tests {
         assert_eq!(tool.description, Some("Test description".into()));
     }
 
+    #[test]
+    fn test_create_call_error_result_includes_code() {
+        let error = ComponentCallError {
+            kind: wassette::CallErrorKind::PermissionDenied,
+            message: "Network permission denied".to_string(),
+        };
+
+        let result = create_call_error_result(&error, false);
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({
+                "error": {
+                    "code": "permission_denied",
+                    "message": "Network permission denied"
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn test_create_call_error_result_with_output_schema_is_text_only() {
+        let error = ComponentCallError {
+            kind: wassette::CallErrorKind::Trap,
+            message: "wasm trap: unreachable".to_string(),
+        };
+
+        let result = create_call_error_result(&error, true);
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.structured_content, None);
+        let text = serde_json::to_value(&result.content.unwrap()[0]).unwrap();
+        assert_eq!(text["text"], "Error (trap): wasm trap: unreachable");
+    }
+
     #[test]
     fn test_parse_tool_schema_with_output_schema() {
         let tool_json = json!({
@@ -5,9 +5,12 @@ pub use wassette::LifecycleManager;
 
 pub mod components;
 pub mod prompts;
+pub mod registry;
 pub mod resources;
+pub mod search;
 pub mod tools;
 
 pub use prompts::{handle_prompts_get, handle_prompts_list};
+pub use registry::{ComponentRegistry, RegistrySource};
 pub use resources::handle_resources_list;
 pub use tools::{handle_tools_call, handle_tools_list};
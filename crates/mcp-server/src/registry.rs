@@ -0,0 +1,838 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Component registry: loading index files from configured sources and searching them
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use policy::{NetworkPermission, PolicyDocument};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::search::{self, Document, Field};
+
+/// The registry index shipped with Wassette, used after every configured source
+const BUILTIN_REGISTRY: &str = include_str!("../../../component-registry.json");
+
+/// How long a loaded index is reused before the sources are read again
+const DEFAULT_INDEX_TTL: Duration = Duration::from_secs(300);
+
+/// Time allowed to connect to a remote index
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for a whole request for a remote index
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed before fetching an index fails, the same limit reqwest applies by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Represents a component in the registry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryComponent {
+    pub name: String,
+    pub description: String,
+    pub uri: String,
+    /// Version of the component that `uri` points to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Pinned digest (`sha256:<hex>`) of the component bytes or, for `oci://` references, of
+    /// the OCI manifest. Loading fails when the fetched component does not match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Tags or categories used for discovery
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// SPDX license expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// URL of the component's source repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Permissions the component needs, in policy file format. `None` means the entry does not
+    /// declare them, which is different from declaring that no permissions are needed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<policy::Permissions>,
+    /// The configured source the entry was loaded from, `None` for the built-in registry
+    #[serde(skip)]
+    pub source: Option<RegistrySource>,
+}
+
+impl RegistryComponent {
+    /// The policy granting the declared permissions, if the entry declares any
+    pub fn declared_policy(&self) -> Option<PolicyDocument> {
+        self.permissions.as_ref().map(|permissions| PolicyDocument {
+            version: "1.0".to_string(),
+            description: Some(format!(
+                "Permissions declared by the '{}' registry entry",
+                self.name
+            )),
+            permissions: permissions.clone(),
+            args: None,
+        })
+    }
+
+    /// One human-readable line per declared permission, e.g. `network: api.example.com`
+    pub fn permission_summary(&self) -> Vec<String> {
+        let Some(permissions) = &self.permissions else {
+            return Vec::new();
+        };
+
+        let mut lines = Vec::new();
+        for rule in permissions
+            .storage
+            .iter()
+            .flat_map(|s| s.allow.iter().flatten())
+        {
+            let access: Vec<String> = rule
+                .access
+                .iter()
+                .map(|a| format!("{a:?}").to_lowercase())
+                .collect();
+            lines.push(format!("storage: {} ({})", rule.uri, access.join(", ")));
+        }
+        for rule in permissions
+            .network
+            .iter()
+            .flat_map(|n| n.allow.iter().flatten())
+        {
+            match rule {
+                NetworkPermission::Host(host) => lines.push(format!("network: {}", host.host)),
+                NetworkPermission::Cidr(cidr) => lines.push(format!("network: {}", cidr.cidr)),
+            }
+        }
+        for rule in permissions
+            .environment
+            .iter()
+            .flat_map(|e| e.allow.iter().flatten())
+        {
+            lines.push(format!("environment: {}", rule.key));
+        }
+
+        // Less common sections are listed in their policy file form
+        let other = [
+            ("resources", serde_json::to_value(&permissions.resources)),
+            ("ipc", serde_json::to_value(&permissions.ipc)),
+            ("runtime", serde_json::to_value(&permissions.runtime)),
+            ("clock", serde_json::to_value(&permissions.clock)),
+            ("random", serde_json::to_value(&permissions.random)),
+        ];
+        for (section, value) in other {
+            if let Ok(value) = value {
+                if !value.is_null() {
+                    lines.push(format!("{section}: {value}"));
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+/// Parse the component registry JSON
+pub fn parse_registry(registry_json: &str) -> Result<Vec<RegistryComponent>> {
+    serde_json::from_str(registry_json).context("Failed to parse component registry JSON")
+}
+
+/// A location a registry index can be loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrySource {
+    /// A JSON index on the local filesystem
+    File(PathBuf),
+    /// A JSON index served over HTTPS
+    Url(String),
+}
+
+impl FromStr for RegistrySource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("https://") {
+            Ok(Self::Url(s.to_string()))
+        } else if s.starts_with("http://") {
+            bail!("Registry URL must use https: {s}")
+        } else if let Some(path) = s.strip_prefix("file://") {
+            Ok(Self::File(PathBuf::from(path)))
+        } else if s.contains("://") {
+            bail!("Unsupported registry source: {s}. Use a file path or an https:// URL")
+        } else {
+            Ok(Self::File(PathBuf::from(s)))
+        }
+    }
+}
+
+impl RegistrySource {
+    /// The source as a `file://` or `https://` URI, as checked by the trust policy
+    pub fn uri(&self) -> String {
+        match self {
+            Self::File(path) => format!("file://{}", path.display()),
+            Self::Url(url) => url.clone(),
+        }
+    }
+}
+
+impl fmt::Display for RegistrySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+/// The set of registry sources Wassette searches, in priority order.
+///
+/// Entries from earlier sources win over entries with the same name or URI from later ones. The
+/// registry shipped with Wassette is always consulted last. The merged index is reused for a
+/// while after it is loaded, and clones of a registry share it.
+#[derive(Debug, Clone)]
+pub struct ComponentRegistry {
+    sources: Vec<RegistrySource>,
+    cache_dir: Option<PathBuf>,
+    http_client: reqwest::Client,
+    index_ttl: Duration,
+    loaded: Arc<Mutex<Option<LoadedIndex>>>,
+}
+
+/// A merged index and when it was loaded
+#[derive(Debug)]
+struct LoadedIndex {
+    loaded_at: Instant,
+    components: Vec<RegistryComponent>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl ComponentRegistry {
+    /// Creates a registry that reads the given sources, highest priority first
+    pub fn new(sources: Vec<RegistrySource>) -> Self {
+        Self {
+            sources,
+            cache_dir: None,
+            http_client: default_http_client(),
+            index_ttl: DEFAULT_INDEX_TTL,
+            loaded: Arc::default(),
+        }
+    }
+
+    /// Sets the directory where indexes fetched over HTTPS are cached for offline use
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Sets the HTTP client used to fetch remote indexes. The client is used as given, so it
+    /// should carry [`https_only_redirects`] unless redirects to plain HTTP are acceptable.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Sets how long a loaded index is reused before the sources are read again
+    pub fn with_index_ttl(mut self, index_ttl: Duration) -> Self {
+        self.index_ttl = index_ttl;
+        self
+    }
+
+    /// Returns the configured sources, highest priority first
+    pub fn sources(&self) -> &[RegistrySource] {
+        &self.sources
+    }
+
+    /// Loads and merges the entries of every source, reusing the last result while it is newer
+    /// than the index TTL.
+    ///
+    /// A local index that does not exist is skipped with a warning, and one that cannot be read
+    /// or parsed is an error. A remote index that cannot be fetched falls back to its cached copy,
+    /// and is skipped with a warning when no copy was cached yet.
+    pub async fn load(&self) -> Result<Vec<RegistryComponent>> {
+        let fresh = self
+            .loaded
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|index| index.loaded_at.elapsed() < self.index_ttl)
+            .map(|index| index.components.clone());
+        if let Some(components) = fresh {
+            return Ok(components);
+        }
+
+        let components = self.load_sources().await?;
+        *self.loaded.lock().unwrap() = Some(LoadedIndex {
+            loaded_at: Instant::now(),
+            components: components.clone(),
+        });
+        Ok(components)
+    }
+
+    async fn load_sources(&self) -> Result<Vec<RegistryComponent>> {
+        let mut merged = Vec::new();
+        let mut seen_names = HashSet::new();
+        let mut seen_uris = HashSet::new();
+
+        let builtin =
+            parse_registry(BUILTIN_REGISTRY).context("Failed to parse built-in registry")?;
+        let mut indexes = Vec::with_capacity(self.sources.len() + 1);
+        for source in &self.sources {
+            if let Some(mut components) = self.load_source(source).await? {
+                for component in &mut components {
+                    component.source = Some(source.clone());
+                }
+                indexes.push(components);
+            }
+        }
+        indexes.push(builtin);
+
+        for component in indexes.into_iter().flatten() {
+            let name = component.name.to_lowercase();
+            if seen_names.contains(&name) || seen_uris.contains(&component.uri) {
+                debug!(name = %component.name, "Skipping registry entry shadowed by a higher-priority source");
+                continue;
+            }
+            seen_names.insert(name);
+            seen_uris.insert(component.uri.clone());
+            merged.push(component);
+        }
+
+        Ok(merged)
+    }
+
+    async fn load_source(&self, source: &RegistrySource) -> Result<Option<Vec<RegistryComponent>>> {
+        match source {
+            RegistrySource::File(path) => {
+                let content = match tokio::fs::read_to_string(path).await {
+                    Ok(content) => content,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        warn!(path = %path.display(), "Registry file does not exist, skipping");
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        return Err(e).with_context(|| {
+                            format!("Failed to read registry file: {}", path.display())
+                        })
+                    }
+                };
+                let components = parse_registry(&content)
+                    .with_context(|| format!("Invalid registry file: {}", path.display()))?;
+                Ok(Some(components))
+            }
+            RegistrySource::Url(url) => {
+                let cache_path = self.cache_dir.as_deref().map(|dir| cache_file(dir, url));
+
+                match self.fetch(url).await {
+                    Ok(content) => {
+                        let components = parse_registry(&content)
+                            .with_context(|| format!("Invalid registry index at {url}"))?;
+                        if let Some(cache_path) = &cache_path {
+                            if let Err(e) = write_cache(cache_path, &content).await {
+                                warn!(url, error = %e, "Failed to cache registry index");
+                            }
+                        }
+                        Ok(Some(components))
+                    }
+                    Err(e) => {
+                        let cached = match &cache_path {
+                            Some(path) => tokio::fs::read_to_string(path).await.ok(),
+                            None => None,
+                        };
+                        match cached {
+                            Some(content) => {
+                                warn!(url, error = %e, "Failed to fetch registry index, using cached copy");
+                                let components = parse_registry(&content).with_context(|| {
+                                    format!("Invalid cached registry index for {url}")
+                                })?;
+                                Ok(Some(components))
+                            }
+                            None => {
+                                warn!(url, error = %e, "Failed to fetch registry index and no cached copy exists, skipping");
+                                Ok(None)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> Result<String> {
+        let response = self.http_client.get(url).send().await?.error_for_status()?;
+        Ok(response.text().await?)
+    }
+}
+
+/// The HTTP client used to fetch remote indexes unless another one is supplied
+fn default_http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(https_only_redirects())
+        .build()
+        .unwrap_or_default()
+}
+
+/// A redirect policy that only follows redirects to `https` URLs, so an index that has to be
+/// fetched over https cannot be moved to plain HTTP by its server
+pub fn https_only_redirects() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error(format!("Too many redirects (more than {MAX_REDIRECTS})"))
+        } else if attempt.url().scheme() == "https" {
+            attempt.follow()
+        } else {
+            let message = format!(
+                "Refusing to follow redirect to {}: registry indexes must be fetched over https",
+                attempt.url()
+            );
+            attempt.error(message)
+        }
+    })
+}
+
+/// The file a remote index is cached in, named after the hash of its URL so that distinct URLs
+/// never share a file
+fn cache_file(cache_dir: &Path, url: &str) -> PathBuf {
+    let digest = wassette::sha256_digest(url.as_bytes());
+    let name = digest.trim_start_matches("sha256:");
+    cache_dir.join(format!("{name}.json"))
+}
+
+async fn write_cache(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, content).await?;
+    Ok(())
+}
+
+/// Search for components matching a query string, best match first.
+///
+/// Without a query, or with one that has no searchable words, every component is returned in
+/// registry order.
+pub fn search_components(
+    components: &[RegistryComponent],
+    query: Option<&str>,
+) -> Vec<RegistryComponent> {
+    let Some(query) = query.filter(|q| !search::tokenize(q).is_empty()) else {
+        return components.to_vec();
+    };
+
+    let documents: Vec<Document> = components
+        .iter()
+        .map(|component| {
+            component.tags.iter().fold(
+                Document::new()
+                    .with_field(Field::Name, &component.name)
+                    .with_field(Field::Description, &component.description)
+                    .with_field(Field::Uri, &component.uri),
+                |document, tag| document.with_field(Field::Tags, tag),
+            )
+        })
+        .collect();
+
+    search::rank(&documents, query)
+        .into_iter()
+        .map(|(index, _)| components[index].clone())
+        .collect()
+}
+
+/// Find a component by name or URI
+pub fn find_component_by_name_or_uri(
+    components: &[RegistryComponent],
+    name_or_uri: &str,
+) -> Option<RegistryComponent> {
+    components
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name_or_uri) || c.uri == name_or_uri)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_registry() {
+        let json = r#"[
+            {
+                "name": "Weather Server",
+                "description": "A weather component",
+                "uri": "oci://ghcr.io/microsoft/get-weather-js:latest"
+            }
+        ]"#;
+
+        let components = parse_registry(json).unwrap();
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].name, "Weather Server");
+    }
+
+    #[test]
+    fn test_parse_registry_rich_entry() {
+        let json = r#"[
+            {
+                "name": "Weather Server",
+                "description": "A weather component",
+                "uri": "oci://ghcr.io/microsoft/get-weather-js:1.2.0",
+                "version": "1.2.0",
+                "digest": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+                "tags": ["weather", "javascript"],
+                "license": "MIT",
+                "repository": "https://github.com/microsoft/wassette",
+                "permissions": {
+                    "network": {"allow": [{"host": "api.openweathermap.org"}]},
+                    "environment": {"allow": [{"key": "OPENWEATHER_API_KEY"}]},
+                    "storage": {"allow": [{"uri": "fs:///tmp", "access": ["read", "write"]}]}
+                }
+            }
+        ]"#;
+
+        let components = parse_registry(json).unwrap();
+        let component = &components[0];
+        assert_eq!(component.version.as_deref(), Some("1.2.0"));
+        assert_eq!(component.tags, vec!["weather", "javascript"]);
+        assert_eq!(component.license.as_deref(), Some("MIT"));
+        assert_eq!(
+            component.permission_summary(),
+            vec![
+                "storage: fs:///tmp (read, write)",
+                "network: api.openweathermap.org",
+                "environment: OPENWEATHER_API_KEY",
+            ]
+        );
+
+        let policy = component.declared_policy().unwrap();
+        assert_eq!(policy.permissions, component.permissions.clone().unwrap());
+        policy.validate().unwrap();
+    }
+
+    #[test]
+    fn test_undeclared_permissions() {
+        let component = RegistryComponent {
+            name: "Component".to_string(),
+            description: "Description".to_string(),
+            uri: "oci://example.com/comp".to_string(),
+            ..Default::default()
+        };
+        assert!(component.declared_policy().is_none());
+        assert!(component.permission_summary().is_empty());
+
+        // Optional fields are left out when serializing
+        let value = serde_json::to_value(&component).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_registry_source_from_str() {
+        assert_eq!(
+            "https://example.com/index.json"
+                .parse::<RegistrySource>()
+                .unwrap(),
+            RegistrySource::Url("https://example.com/index.json".to_string())
+        );
+        assert_eq!(
+            "file:///etc/wassette/index.json"
+                .parse::<RegistrySource>()
+                .unwrap(),
+            RegistrySource::File(PathBuf::from("/etc/wassette/index.json"))
+        );
+        assert_eq!(
+            "./index.json".parse::<RegistrySource>().unwrap(),
+            RegistrySource::File(PathBuf::from("./index.json"))
+        );
+        assert!("http://example.com/index.json"
+            .parse::<RegistrySource>()
+            .is_err());
+        assert!("oci://example.com/index".parse::<RegistrySource>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_builtin_registry() {
+        let components = ComponentRegistry::default().load().await.unwrap();
+        assert_eq!(
+            components.len(),
+            parse_registry(BUILTIN_REGISTRY).unwrap().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_merges_sources_in_priority_order() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let first = tempdir.path().join("first.json");
+        let second = tempdir.path().join("second.json");
+        std::fs::write(
+            &first,
+            r#"[{"name": "Weather Server", "description": "Internal mirror", "uri": "oci://internal.example.com/weather:1"}]"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &second,
+            r#"[
+                {"name": "weather server", "description": "Shadowed", "uri": "oci://other.example.com/weather:1"},
+                {"name": "Internal Tool", "description": "Only here", "uri": "oci://internal.example.com/tool:1"}
+            ]"#,
+        )
+        .unwrap();
+
+        let registry = ComponentRegistry::new(vec![
+            RegistrySource::File(first.clone()),
+            RegistrySource::File(second),
+        ]);
+        let components = registry.load().await.unwrap();
+
+        let weather: Vec<_> = components
+            .iter()
+            .filter(|c| c.name.eq_ignore_ascii_case("Weather Server"))
+            .collect();
+        assert_eq!(weather.len(), 1);
+        assert_eq!(weather[0].uri, "oci://internal.example.com/weather:1");
+        assert_eq!(components[0].name, "Weather Server");
+        assert_eq!(components[1].name, "Internal Tool");
+        assert!(find_component_by_name_or_uri(&components, "Time Server").is_some());
+
+        // Entries remember the configured source they came from
+        assert_eq!(
+            components[0].source.as_ref().map(RegistrySource::uri),
+            Some(format!("file://{}", first.display()))
+        );
+        assert!(find_component_by_name_or_uri(&components, "Time Server")
+            .unwrap()
+            .source
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_missing_file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let registry = ComponentRegistry::new(vec![RegistrySource::File(
+            tempdir.path().join("missing.json"),
+        )]);
+        let components = registry.load().await.unwrap();
+        assert_eq!(
+            components.len(),
+            parse_registry(BUILTIN_REGISTRY).unwrap().len()
+        );
+
+        // A file that exists but is not an index is still an error
+        let invalid = tempdir.path().join("invalid.json");
+        std::fs::write(&invalid, "not json").unwrap();
+        let registry = ComponentRegistry::new(vec![RegistrySource::File(invalid)]);
+        assert!(registry.load().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_reuses_index_until_ttl_expires() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let index = tempdir.path().join("index.json");
+        std::fs::write(
+            &index,
+            r#"[{"name": "First Tool", "description": "First", "uri": "oci://example.com/first:1"}]"#,
+        )
+        .unwrap();
+
+        let registry = ComponentRegistry::new(vec![RegistrySource::File(index.clone())]);
+        let clone = registry.clone();
+        assert_eq!(registry.load().await.unwrap()[0].name, "First Tool");
+
+        std::fs::write(
+            &index,
+            r#"[{"name": "Second Tool", "description": "Second", "uri": "oci://example.com/second:1"}]"#,
+        )
+        .unwrap();
+        assert_eq!(clone.load().await.unwrap()[0].name, "First Tool");
+
+        let registry = registry.with_index_ttl(Duration::ZERO);
+        assert_eq!(registry.load().await.unwrap()[0].name, "Second Tool");
+    }
+
+    #[test]
+    fn test_cache_file_is_unique_per_url() {
+        let dir = Path::new("/cache");
+        let first = cache_file(dir, "https://example.com/a/index.json");
+        let second = cache_file(dir, "https://example.com/a_index.json");
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), Some(dir));
+        assert_eq!(first, cache_file(dir, "https://example.com/a/index.json"));
+    }
+
+    #[tokio::test]
+    async fn test_load_unreachable_url_uses_cache() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let url = "https://127.0.0.1:1/index.json";
+        std::fs::write(
+            cache_file(tempdir.path(), url),
+            r#"[{"name": "Cached Tool", "description": "From cache", "uri": "oci://example.com/cached:1"}]"#,
+        )
+        .unwrap();
+
+        let registry = ComponentRegistry::new(vec![RegistrySource::Url(url.to_string())])
+            .with_cache_dir(tempdir.path());
+        let components = registry.load().await.unwrap();
+        assert_eq!(components[0].name, "Cached Tool");
+
+        // Without a cached copy the source is skipped and the built-in entries remain
+        let registry = ComponentRegistry::new(vec![RegistrySource::Url(url.to_string())]);
+        let components = registry.load().await.unwrap();
+        assert!(find_component_by_name_or_uri(&components, "Cached Tool").is_none());
+        assert!(!components.is_empty());
+    }
+
+    #[test]
+    fn test_search_components_no_query() {
+        let components = vec![
+            RegistryComponent {
+                name: "Component A".to_string(),
+                description: "Description A".to_string(),
+                uri: "oci://example.com/a".to_string(),
+                ..Default::default()
+            },
+            RegistryComponent {
+                name: "Component B".to_string(),
+                description: "Description B".to_string(),
+                uri: "oci://example.com/b".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let results = search_components(&components, None);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_search_components_with_query() {
+        let components = vec![
+            RegistryComponent {
+                name: "Weather Server".to_string(),
+                description: "A weather component".to_string(),
+                uri: "oci://example.com/weather".to_string(),
+                ..Default::default()
+            },
+            RegistryComponent {
+                name: "Time Server".to_string(),
+                description: "A time component".to_string(),
+                uri: "oci://example.com/time".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let results = search_components(&components, Some("weather"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Weather Server");
+    }
+
+    #[test]
+    fn test_search_components_case_insensitive() {
+        let components = vec![RegistryComponent {
+            name: "Weather Server".to_string(),
+            description: "A weather component".to_string(),
+            uri: "oci://example.com/weather".to_string(),
+            ..Default::default()
+        }];
+
+        let results = search_components(&components, Some("WEATHER"));
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_find_component_by_name() {
+        let components = vec![RegistryComponent {
+            name: "Weather Server".to_string(),
+            description: "A weather component".to_string(),
+            uri: "oci://example.com/weather".to_string(),
+            ..Default::default()
+        }];
+
+        let result = find_component_by_name_or_uri(&components, "Weather Server");
+        assert!(result.is_some());
+        assert_eq!(result.unwrap().name, "Weather Server");
+    }
+
+    #[test]
+    fn test_find_component_by_uri() {
+        let components = vec![RegistryComponent {
+            name: "Weather Server".to_string(),
+            description: "A weather component".to_string(),
+            uri: "oci://example.com/weather".to_string(),
+            ..Default::default()
+        }];
+
+        let result = find_component_by_name_or_uri(&components, "oci://example.com/weather");
+        assert!(result.is_some());
+        assert_eq!(result.unwrap().name, "Weather Server");
+    }
+
+    #[test]
+    fn test_search_components_multi_term() {
+        let components = vec![
+            RegistryComponent {
+                name: "Weather Server".to_string(),
+                description: "JavaScript weather component".to_string(),
+                uri: "oci://example.com/weather-js".to_string(),
+                ..Default::default()
+            },
+            RegistryComponent {
+                name: "Time Server".to_string(),
+                description: "Rust time component".to_string(),
+                uri: "oci://example.com/time-rs".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        // Multi-term search should match any term
+        let results = search_components(&components, Some("weather rust"));
+        assert_eq!(results.len(), 2); // Both match (weather matches first, rust matches second)
+
+        // Components matching more of the query rank first
+        let results = search_components(&components, Some("rust time"));
+        assert_eq!(results[0].name, "Time Server");
+    }
+
+    #[test]
+    fn test_search_components_matches_uri() {
+        let components = vec![RegistryComponent {
+            name: "Component".to_string(),
+            description: "A test component".to_string(),
+            uri: "oci://ghcr.io/microsoft/weather".to_string(),
+            ..Default::default()
+        }];
+
+        // Should match URI as well
+        let results = search_components(&components, Some("microsoft"));
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_search_components_matches_tags() {
+        let components = vec![RegistryComponent {
+            name: "Component".to_string(),
+            description: "A test component".to_string(),
+            uri: "oci://example.com/comp".to_string(),
+            tags: vec!["Forecast".to_string()],
+            ..Default::default()
+        }];
+
+        let results = search_components(&components, Some("forecast"));
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_search_components_empty_query() {
+        let components = vec![RegistryComponent {
+            name: "Component".to_string(),
+            description: "Description".to_string(),
+            uri: "oci://example.com/comp".to_string(),
+            ..Default::default()
+        }];
+
+        // Empty string query should return all components
+        let results = search_components(&components, Some("   "));
+        assert_eq!(results.len(), 1);
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Ranked full-text search over registry entries and installed tools
+//!
+//! Documents are split into weighted fields and scored with BM25F: per-field term frequencies
+//! are length-normalized and weighted, then combined before BM25 saturation is applied. A query
+//! term also matches tokens it is a prefix of, at a reduced weight, so partial words still find
+//! results.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use wassette::LifecycleManager;
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 length normalization
+const B: f64 = 0.75;
+/// Weight of a prefix match relative to an exact token match
+const PREFIX_MATCH_WEIGHT: f64 = 0.5;
+
+/// A searchable part of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Name,
+    Tags,
+    Description,
+    Uri,
+}
+
+impl Field {
+    fn weight(self) -> f64 {
+        match self {
+            Field::Name => 3.0,
+            Field::Tags => 2.0,
+            Field::Description => 1.0,
+            Field::Uri => 0.5,
+        }
+    }
+}
+
+/// Split text into lowercase alphanumeric tokens
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A tokenized document made of weighted fields
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    fields: Vec<(Field, Vec<String>)>,
+}
+
+impl Document {
+    /// Creates an empty document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `text` to `field`. A field may be added several times, e.g. once per tag.
+    pub fn with_field(mut self, field: Field, text: &str) -> Self {
+        let tokens = tokenize(text);
+        match self.fields.iter_mut().find(|(f, _)| *f == field) {
+            Some((_, existing)) => existing.extend(tokens),
+            None => self.fields.push((field, tokens)),
+        }
+        self
+    }
+
+    fn field_len(&self, field: Field) -> usize {
+        self.fields
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map_or(0, |(_, tokens)| tokens.len())
+    }
+
+    /// How often `term` occurs in each field, counting prefix matches at a reduced weight
+    fn term_frequencies(&self, term: &str) -> impl Iterator<Item = (Field, f64)> + '_ {
+        let term = term.to_string();
+        self.fields.iter().filter_map(move |(field, tokens)| {
+            let tf: f64 = tokens
+                .iter()
+                .map(|token| {
+                    if *token == term {
+                        1.0
+                    } else if token.starts_with(term.as_str()) {
+                        PREFIX_MATCH_WEIGHT
+                    } else {
+                        0.0
+                    }
+                })
+                .sum();
+            (tf > 0.0).then_some((*field, tf))
+        })
+    }
+}
+
+/// Scores `documents` against `query` and returns the index and score of every matching
+/// document, best match first. Documents with equal scores keep their input order.
+pub fn rank(documents: &[Document], query: &str) -> Vec<(usize, f64)> {
+    let terms = tokenize(query);
+    if terms.is_empty() || documents.is_empty() {
+        return Vec::new();
+    }
+
+    // Average length of each field across the documents that have it
+    let mut totals: HashMap<Field, (usize, usize)> = HashMap::new();
+    for document in documents {
+        for (field, tokens) in &document.fields {
+            let entry = totals.entry(*field).or_default();
+            entry.0 += tokens.len();
+            entry.1 += 1;
+        }
+    }
+    let avg_len = |field: Field| {
+        totals
+            .get(&field)
+            .filter(|(_, count)| *count > 0)
+            .map_or(1.0, |(len, count)| (*len as f64 / *count as f64).max(1.0))
+    };
+
+    let n = documents.len() as f64;
+    let mut scores = vec![0.0; documents.len()];
+    for term in &terms {
+        let weighted_tfs: Vec<f64> = documents
+            .iter()
+            .map(|document| {
+                document
+                    .term_frequencies(term)
+                    .map(|(field, tf)| {
+                        let norm = 1.0 - B + B * document.field_len(field) as f64 / avg_len(field);
+                        field.weight() * tf / norm
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let df = weighted_tfs.iter().filter(|tf| **tf > 0.0).count() as f64;
+        if df == 0.0 {
+            continue;
+        }
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+
+        for (score, tf) in scores.iter_mut().zip(weighted_tfs) {
+            if tf > 0.0 {
+                *score += idf * tf * (K1 + 1.0) / (tf + K1);
+            }
+        }
+    }
+
+    let mut ranked: Vec<(usize, f64)> = scores
+        .into_iter()
+        .enumerate()
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+/// A tool of an installed component that matched a search
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolMatch {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub component_id: String,
+    pub score: f64,
+}
+
+/// Search the tools of every installed component, whether loaded or only known from metadata
+pub async fn search_tools(
+    lifecycle_manager: &LifecycleManager,
+    query: &str,
+) -> Result<Vec<ToolMatch>> {
+    let mut candidates = Vec::new();
+    for component_id in lifecycle_manager.list_components_known().await {
+        let Some(schema) = lifecycle_manager.get_component_schema(&component_id).await else {
+            continue;
+        };
+        let tools = schema
+            .get("tools")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for tool in tools {
+            let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let description = tool
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            candidates.push((name.to_string(), description, component_id.clone()));
+        }
+    }
+
+    let documents: Vec<Document> = candidates
+        .iter()
+        .map(|(name, description, component_id)| {
+            Document::new()
+                .with_field(Field::Name, name)
+                .with_field(Field::Description, description.as_deref().unwrap_or(""))
+                .with_field(Field::Uri, component_id)
+        })
+        .collect();
+
+    Ok(rank(&documents, query)
+        .into_iter()
+        .map(|(index, score)| {
+            let (name, description, component_id) = candidates[index].clone();
+            ToolMatch {
+                name,
+                description,
+                component_id,
+                score,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Get-Weather_JS: fetch the forecast!"),
+            vec!["get", "weather", "js", "fetch", "the", "forecast"]
+        );
+        assert!(tokenize("  -- ").is_empty());
+    }
+
+    #[test]
+    fn test_rank_prefers_name_matches() {
+        let documents = vec![
+            Document::new()
+                .with_field(Field::Name, "Time Server")
+                .with_field(
+                    Field::Description,
+                    "Reports the weather of a given time zone",
+                ),
+            Document::new()
+                .with_field(Field::Name, "Weather Server")
+                .with_field(Field::Description, "A forecast component"),
+            Document::new()
+                .with_field(Field::Name, "Filesystem")
+                .with_field(Field::Description, "Reads files"),
+        ];
+
+        let ranked = rank(&documents, "weather");
+        let order: Vec<usize> = ranked.iter().map(|(index, _)| *index).collect();
+        assert_eq!(order, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_rank_rare_terms_weigh_more() {
+        let documents = vec![
+            Document::new().with_field(Field::Description, "component written in rust"),
+            Document::new().with_field(Field::Description, "component using the brave api"),
+            Document::new().with_field(Field::Description, "component written in rust"),
+        ];
+
+        // "brave" only occurs once, so it outweighs the common "rust"
+        let ranked = rank(&documents, "rust brave");
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].0, 1);
+    }
+
+    #[test]
+    fn test_rank_prefix_and_tags() {
+        let documents = vec![
+            Document::new()
+                .with_field(Field::Name, "Fetch")
+                .with_field(Field::Tags, "http")
+                .with_field(Field::Tags, "web"),
+            Document::new().with_field(Field::Name, "Weather"),
+        ];
+
+        assert_eq!(rank(&documents, "weath")[0].0, 1);
+        assert_eq!(rank(&documents, "http")[0].0, 0);
+        assert!(rank(&documents, "").is_empty());
+        assert!(rank(&documents, "nothing").is_empty());
+    }
+}
@@ -16,9 +16,8 @@ use crate::components::{
     extract_args_from_request, get_component_tools, handle_component_call, handle_list_components,
     handle_load_component, handle_unload_component,
 };
-
-/// The list of components that Wassette knows about
-const COMPONENT_LIST: &str = include_str!("../../../component-registry.json");
+use crate::registry::{search_components, ComponentRegistry};
+use crate::search::search_tools;
 
 /// Handles a request to list available tools.
 #[instrument(skip(lifecycle_manager))]
@@ -112,6 +111,7 @@ fn sanitize_args_for_logging(args: &Option<serde_json::Map<String, Value>>) -> S
 pub async fn handle_tools_call(
     req: CallToolRequestParam,
     lifecycle_manager: &LifecycleManager,
+    registry: &ComponentRegistry,
     server_peer: Peer<RoleServer>,
     disable_builtin_tools: bool,
 ) -> Result<Value> {
@@ -166,7 +166,7 @@ pub async fn handle_tools_call(
                 handle_revoke_environment_variable_permission(&req, lifecycle_manager).await
             }
             "search-components" if !disable_builtin_tools => {
-                handle_search_component(&req, lifecycle_manager).await
+                handle_search_component(&req, lifecycle_manager, registry).await
             }
             "reset-permission" if !disable_builtin_tools => {
                 handle_reset_permission(&req, lifecycle_manager).await
@@ -316,6 +316,10 @@ fn get_builtin_tools() -> Vec<Tool> {
                               "enum": ["read", "write"]
                             },
                             "description": "Access type for the storage resource, this must be an array of strings with values 'read' or 'write'"
+                          },
+                          "mount": {
+                            "type": "string",
+                            "description": "Path to mount the directory at inside the component. Required when the URI uses '~' or a variable, e.g. fs://~/data"
                           }
                         },
                         "required": ["uri", "access"],
@@ -513,7 +517,7 @@ fn get_builtin_tools() -> Vec<Tool> {
         Tool {
             name: Cow::Borrowed("search-components"),
             description: Some(Cow::Borrowed(
-                "Lists all known components that can be fetched and loaded. Optionally filter by a search query.",
+                "Lists all known components that can be fetched and loaded, including the permissions each one declares it needs. Optionally rank them against a search query, which also returns matching tools of installed components.",
             )),
             input_schema: Arc::new(
                 serde_json::from_value(json!({
@@ -521,7 +525,7 @@ fn get_builtin_tools() -> Vec<Tool> {
                     "properties": {
                         "query": {
                             "type": "string",
-                            "description": "Optional search query to filter components by name, description, or URI"
+                            "description": "Optional search query matched against component names, descriptions, tags and URIs, and against installed tool names and descriptions"
                         }
                     },
                     "required": []
@@ -534,98 +538,31 @@ fn get_builtin_tools() -> Vec<Tool> {
     ]
 }
 
-/// Calculate a relevance score for a component based on query terms
-/// Higher scores indicate better matches
-fn calculate_relevance_score(component: &Value, query_terms: &[String]) -> u32 {
-    let name = component["name"].as_str().unwrap_or("").to_lowercase();
-    let description = component["description"]
-        .as_str()
-        .unwrap_or("")
-        .to_lowercase();
-    let uri = component["uri"].as_str().unwrap_or("").to_lowercase();
-
-    let mut score = 0u32;
-
-    for term in query_terms {
-        // Exact name match gets highest score
-        if name == term.as_str() {
-            score += 100;
-        } else if name.starts_with(term) {
-            score += 50;
-        } else if name.contains(term) {
-            score += 20;
-        }
-
-        // Description matches get medium score
-        if description.starts_with(term) {
-            score += 15;
-        } else if description.contains(term) {
-            score += 10;
-        }
-
-        // URI matches get lower score
-        if uri.contains(term) {
-            score += 5;
-        }
-    }
-
-    score
-}
-
-#[instrument(skip(_lifecycle_manager))]
+#[instrument(skip(lifecycle_manager, registry))]
 pub(crate) async fn handle_search_component(
     req: &CallToolRequestParam,
-    _lifecycle_manager: &LifecycleManager,
+    lifecycle_manager: &LifecycleManager,
+    registry: &ComponentRegistry,
 ) -> Result<CallToolResult> {
     let args = extract_args_from_request(req)?;
 
     // Extract the optional query parameter
     let query = args.get("query").and_then(|v| v.as_str());
 
-    // Parse the component list
-    let components_value: Value = serde_json::from_str(COMPONENT_LIST)?;
-    let all_components = components_value
-        .as_array()
-        .ok_or_else(|| anyhow::anyhow!("Component registry is not an array"))?;
-
-    // Filter and rank components based on query
-    let filtered_components: Vec<Value> = if let Some(q) = query {
-        // Split query into words for multi-term matching
-        let query_terms: Vec<String> = q
-            .split_whitespace()
-            .map(|term| term.to_lowercase())
-            .collect();
-
-        if query_terms.is_empty() {
-            all_components.to_vec()
-        } else {
-            // Calculate relevance scores and filter out non-matches
-            let mut scored_components: Vec<(u32, &Value)> = all_components
-                .iter()
-                .map(|component| {
-                    let score = calculate_relevance_score(component, &query_terms);
-                    (score, component)
-                })
-                .filter(|(score, _)| *score > 0)
-                .collect();
-
-            // Sort by relevance score (descending)
-            scored_components.sort_by(|a, b| b.0.cmp(&a.0));
-
-            // Extract components in ranked order
-            scored_components
-                .into_iter()
-                .map(|(_, component)| (*component).clone())
-                .collect()
-        }
-    } else {
-        all_components.to_vec()
-    };
+    // Rank the components from the configured registry sources
+    let all_components = registry.load().await?;
+    let components = search_components(&all_components, query);
 
-    let status_text = serde_json::to_string(&json!({
+    // With a query, also point at installed tools that already match it
+    let mut response = json!({
         "status": "Component list found",
-        "components": filtered_components,
-    }))?;
+        "components": components,
+    });
+    if let Some(q) = query.filter(|q| !q.trim().is_empty()) {
+        response["tools"] = serde_json::to_value(search_tools(lifecycle_manager, q).await?)?;
+    }
+
+    let status_text = serde_json::to_string(&response)?;
 
     let contents = vec![Content::text(status_text)];
 
@@ -1379,6 +1316,7 @@ mod tests {
     async fn test_search_component_without_query() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test without query - should return all components
         let args = serde_json::Map::new();
@@ -1387,7 +1325,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         // Parse the result
         let content = result
@@ -1410,6 +1348,9 @@ mod tests {
         // Should return all 9 components from component-registry.json
         assert_eq!(components.len(), 9);
 
+        // Installed tools are only searched when there is a query
+        assert!(response.get("tools").is_none());
+
         Ok(())
     }
 
@@ -1417,6 +1358,7 @@ mod tests {
     async fn test_search_component_with_query() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test with query - search for "weather"
         let mut args = serde_json::Map::new();
@@ -1426,7 +1368,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         // Parse the result
         let content = result
@@ -1474,6 +1416,7 @@ mod tests {
     async fn test_search_component_case_insensitive() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test case insensitivity - search with uppercase
         let mut args = serde_json::Map::new();
@@ -1483,7 +1426,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         let content = result
             .content
@@ -1510,6 +1453,7 @@ mod tests {
     async fn test_search_component_no_results() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test with query that matches nothing
         let mut args = serde_json::Map::new();
@@ -1519,7 +1463,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         let content = result
             .content
@@ -1546,6 +1490,7 @@ mod tests {
     async fn test_search_component_multi_term() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test multi-term search
         let mut args = serde_json::Map::new();
@@ -1555,7 +1500,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         let content = result
             .content
@@ -1583,6 +1528,7 @@ mod tests {
     async fn test_search_component_relevance_ranking() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test relevance ranking - search for "server"
         // "Weather Server" and "Time Server" have "server" in the name
@@ -1594,7 +1540,7 @@ mod tests {
             arguments: Some(args),
         };
 
-        let result = handle_search_component(&req, &lifecycle_manager).await?;
+        let result = handle_search_component(&req, &lifecycle_manager, &registry).await?;
 
         let content = result
             .content
@@ -1636,13 +1582,14 @@ mod tests {
     async fn test_search_component_integration_end_to_end() -> Result<()> {
         let tempdir = tempfile::tempdir()?;
         let lifecycle_manager = wassette::LifecycleManager::new(&tempdir).await?;
+        let registry = ComponentRegistry::default();
 
         // Test 1: No query returns all components
         let req1 = CallToolRequestParam {
             name: "search-components".into(),
             arguments: Some(serde_json::Map::new()),
         };
-        let result1 = handle_search_component(&req1, &lifecycle_manager).await?;
+        let result1 = handle_search_component(&req1, &lifecycle_manager, &registry).await?;
         let content1_json = serde_json::to_value(&result1.content)?;
         let text1 = content1_json[0]["text"].as_str().unwrap();
         let response1: Value = serde_json::from_str(text1)?;
@@ -1655,7 +1602,7 @@ mod tests {
             name: "search-components".into(),
             arguments: Some(args2),
         };
-        let result2 = handle_search_component(&req2, &lifecycle_manager).await?;
+        let result2 = handle_search_component(&req2, &lifecycle_manager, &registry).await?;
         let content2_json = serde_json::to_value(&result2.content)?;
         let text2 = content2_json[0]["text"].as_str().unwrap();
         let response2: Value = serde_json::from_str(text2)?;
@@ -1670,7 +1617,7 @@ mod tests {
             name: "search-components".into(),
             arguments: Some(args3),
         };
-        let result3 = handle_search_component(&req3, &lifecycle_manager).await?;
+        let result3 = handle_search_component(&req3, &lifecycle_manager, &registry).await?;
         let content3_json = serde_json::to_value(&result3.content)?;
         let text3 = content3_json[0]["text"].as_str().unwrap();
         let response3: Value = serde_json::from_str(text3)?;
@@ -1683,7 +1630,7 @@ mod tests {
             name: "search-components".into(),
             arguments: Some(args4),
         };
-        let result4 = handle_search_component(&req4, &lifecycle_manager).await?;
+        let result4 = handle_search_component(&req4, &lifecycle_manager, &registry).await?;
         let content4_json = serde_json::to_value(&result4.content)?;
         let text4 = content4_json[0]["text"].as_str().unwrap();
         let response4: Value = serde_json::from_str(text4)?;
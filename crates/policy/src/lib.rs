@@ -26,6 +26,11 @@ pub struct PolicyDocument {
 
     /// Permission definitions
     pub permissions: Permissions,
+
+    /// Command line arguments passed to the component, program name first.
+    /// Components receive no arguments unless they are listed here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
 }
 
 impl PolicyDocument {
@@ -63,6 +68,7 @@ mod tests {
             version: "1.0".to_string(),
             description: Some("Test policy".to_string()),
             permissions: Permissions::default(),
+            args: None,
         };
 
         assert!(policy.validate().is_ok());
@@ -86,6 +92,7 @@ mod tests {
             version: "2.0".to_string(),
             description: None,
             permissions: Permissions::default(),
+            args: None,
         };
 
         let result = policy.validate();
@@ -78,6 +78,7 @@ impl PolicyParser {
     ///     version: "1.0".to_string(),
     ///     description: Some("Test policy".to_string()),
     ///     permissions: Permissions::default(),
+    ///     args: None,
     /// };
     ///
     /// let yaml = PolicyParser::to_yaml(&policy).unwrap();
@@ -176,6 +177,8 @@ invalid: yaml: content
                 allow: Some(vec![StoragePermission {
                     uri: "fs://work/agent/**".to_string(),
                     access: vec![AccessType::Read, AccessType::Write],
+                    mount: None,
+                    memory: None,
                 }]),
                 deny: None,
             }),
@@ -185,6 +188,7 @@ invalid: yaml: content
             version: "1.0".to_string(),
             description: Some("Test policy".to_string()),
             permissions,
+            args: None,
         };
 
         let yaml = PolicyParser::to_yaml(&original).unwrap();
@@ -227,6 +231,7 @@ permissions:
             version: "1.0".to_string(),
             description: Some("Write test policy".to_string()),
             permissions,
+            args: None,
         };
 
         let temp_file = NamedTempFile::new().unwrap();
@@ -29,6 +29,47 @@ pub struct StoragePermission {
     pub uri: String,
     /// Access types allowed
     pub access: Vec<AccessType>,
+    /// Path the directory is mounted at inside the guest. Defaults to the path in `uri`, and is
+    /// required when that path starts with `~` or references a variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount: Option<String>,
+    /// Options for in-memory (`mem://`) mounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryMountOptions>,
+}
+
+impl StoragePermission {
+    /// Whether the host path in `uri` depends on the host's home directory or environment, so
+    /// it can't double as the guest path and `mount` has to be set
+    pub fn requires_mount(&self) -> bool {
+        self.mount.is_none()
+            && self
+                .uri
+                .strip_prefix("fs://")
+                .is_some_and(|path| path.contains('~') || path.contains('$'))
+    }
+}
+
+/// How long the contents of an in-memory mount live
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// A fresh, empty directory for every tool call
+    #[default]
+    Invocation,
+    /// Shared by all calls until the component or its policy is reloaded
+    Session,
+}
+
+/// Options for an in-memory (`mem://`) storage mount
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct MemoryMountOptions {
+    /// Maximum amount of data the mount may hold ("64Mi", or a number in MB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<MemoryLimit>,
+    /// Lifetime of the mount's contents
+    #[serde(default)]
+    pub scope: MemoryScope,
 }
 
 /// Network host permission
@@ -147,6 +188,11 @@ pub struct ResourceLimitValues {
     pub cpu: Option<CpuLimit>,
     /// Memory limit in k8s format ("512Mi", "1Gi", "256Ki")
     pub memory: Option<MemoryLimit>,
+    /// Maximum wall-clock time in milliseconds a single call may run before it is interrupted.
+    /// Time the call spends waiting on host calls counts as well, so this is not a measure of
+    /// CPU time despite its name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
     /// Cached parsed CPU value in cores (not serialized)
     #[serde(skip)]
     cpu_cores_cache: OnceLock<f64>,
@@ -175,6 +221,44 @@ pub struct IpcPermission {
     pub uri: String,
 }
 
+/// Wall clock behaviour exposed to a component
+///
+/// real: the host's clock
+/// fixed: frozen at `time`, with a monotonic clock that does not advance
+/// offset: the host's clock shifted by `offset` seconds
+/// deny: frozen at the UNIX epoch, hiding the host's time entirely
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockMode {
+    #[default]
+    Real,
+    Fixed,
+    Offset,
+    Deny,
+}
+
+/// Clock virtualization settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ClockPermissions {
+    /// How the wall clock is presented to the component
+    #[serde(default)]
+    pub mode: ClockMode,
+    /// Seconds since the UNIX epoch reported by a `fixed` clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+    /// Seconds added to the host time by an `offset` clock (may be negative)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// Random number generation settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct RandomPermissions {
+    /// Seed for a deterministic generator. When unset the host's entropy is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
 /// Runtime configuration
 ///
 /// TODO: add more sandboxing runtimes
@@ -215,6 +299,10 @@ pub struct Permissions {
     pub runtime: Option<Runtime>,
     pub resources: Option<ResourceLimits>,
     pub ipc: Option<PermissionList<IpcPermission>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockPermissions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random: Option<RandomPermissions>,
 }
 
 impl CpuLimit {
@@ -315,6 +403,7 @@ impl ResourceLimitValues {
         Self {
             cpu,
             memory,
+            cpu_time_ms: None,
             cpu_cores_cache: OnceLock::new(),
             memory_bytes_cache: OnceLock::new(),
         }
@@ -359,6 +448,9 @@ impl ResourceLimitValues {
         // Validation now uses the cached getters, which will parse and cache the values
         self.cpu_cores()?;
         self.memory_bytes()?;
+        if self.cpu_time_ms == Some(0) {
+            bail!("CPU time limit cannot be zero");
+        }
         Ok(())
     }
 }
@@ -418,6 +510,96 @@ impl Permissions {
         Ok(())
     }
 
+    fn validate_memory_mount(perm: &StoragePermission) -> PolicyResult<()> {
+        match perm.uri.strip_prefix("mem://") {
+            Some(path) => {
+                if path.is_empty() {
+                    bail!("In-memory mount needs a path: {}", perm.uri);
+                }
+                if path.contains('*') {
+                    bail!("No wildcards allowed in in-memory mounts: {}", perm.uri);
+                }
+            }
+            None => {
+                if perm.memory.is_some() {
+                    bail!(
+                        "Memory options are only valid for mem:// URIs: {}",
+                        perm.uri
+                    );
+                }
+            }
+        }
+
+        if let Some(quota) = perm.memory.as_ref().and_then(|m| m.quota.as_ref()) {
+            quota.to_bytes()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the guest path of a concrete mount, or `None` for wildcard access patterns
+    fn guest_mount_path(perm: &StoragePermission) -> Option<&str> {
+        if let Some(mount) = &perm.mount {
+            return Some(mount);
+        }
+        let path = perm
+            .uri
+            .strip_prefix("fs://")
+            .or_else(|| perm.uri.strip_prefix("mem://"))?;
+        (!path.contains('*')).then_some(path)
+    }
+
+    fn validate_mount_path(mount: &str) -> PolicyResult<()> {
+        if mount.is_empty() {
+            bail!("Mount path can't be empty");
+        }
+        if mount.contains('*') {
+            bail!("No wildcards allowed in mount paths: {}", mount);
+        }
+        if mount.split('/').any(|part| part == "..") {
+            bail!("Mount path can't contain '..': {}", mount);
+        }
+        Ok(())
+    }
+
+    /// Rejects allow rules whose guest mount paths are equal or nested inside one another.
+    /// Paths are normalized first, so `data/../data/x` is compared as `data/x`.
+    fn validate_mount_overlaps(allow_list: &[StoragePermission]) -> PolicyResult<()> {
+        fn segments(path: &str) -> (bool, Vec<&str>) {
+            let mut parts: Vec<&str> = Vec::new();
+            for part in path.split('/') {
+                match part {
+                    "" | "." => {}
+                    ".." if parts.last().is_some_and(|last| *last != "..") => {
+                        parts.pop();
+                    }
+                    _ => parts.push(part),
+                }
+            }
+            (path.starts_with('/'), parts)
+        }
+
+        let mounts: Vec<(&str, (bool, Vec<&str>))> = allow_list
+            .iter()
+            .filter_map(Self::guest_mount_path)
+            .map(|path| (path, segments(path)))
+            .collect();
+
+        for (i, (path_a, (abs_a, parts_a))) in mounts.iter().enumerate() {
+            for (path_b, (abs_b, parts_b)) in &mounts[i + 1..] {
+                if abs_a != abs_b {
+                    continue;
+                }
+                let shared = parts_a.len().min(parts_b.len());
+                if parts_a[..shared] == parts_b[..shared] {
+                    bail!("Storage mounts overlap: '{}' and '{}'", path_a, path_b);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_network_host(host: &str) -> PolicyResult<()> {
         if host.is_empty() {
             bail!("Host can't be empty");
@@ -459,10 +641,21 @@ impl Permissions {
             if let Some(allow_list) = &storage.allow {
                 for perm in allow_list {
                     Self::validate_storage_uri(&perm.uri)?;
+                    Self::validate_memory_mount(perm)?;
+                    if let Some(mount) = &perm.mount {
+                        Self::validate_mount_path(mount)?;
+                    }
+                    if perm.requires_mount() {
+                        bail!(
+                            "Storage URI '{}' uses '~' or a variable, so it needs a 'mount' path for the guest",
+                            perm.uri
+                        );
+                    }
                     if perm.access.is_empty() {
                         bail!("Storage needs some access permissions");
                     }
                 }
+                Self::validate_mount_overlaps(allow_list)?;
             }
             if let Some(deny_list) = &storage.deny {
                 for perm in deny_list {
@@ -523,6 +716,34 @@ impl Permissions {
             resources.validate()?;
         }
 
+        if let Some(clock) = &self.clock {
+            clock.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ClockPermissions {
+    /// Validate that the fields required by the selected mode are present
+    pub fn validate(&self) -> PolicyResult<()> {
+        match self.mode {
+            ClockMode::Fixed if self.time.is_none() => {
+                bail!("Fixed clock needs a time (seconds since the UNIX epoch)")
+            }
+            ClockMode::Offset if self.offset.is_none() => {
+                bail!("Offset clock needs an offset in seconds")
+            }
+            _ => {}
+        }
+
+        if self.time.is_some() && self.mode != ClockMode::Fixed {
+            bail!("Clock time is only valid with mode 'fixed'");
+        }
+        if self.offset.is_some() && self.mode != ClockMode::Offset {
+            bail!("Clock offset is only valid with mode 'offset'");
+        }
+
         Ok(())
     }
 }
@@ -531,6 +752,220 @@ impl Permissions {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_mount_overlap_validation() {
+        let rule = |uri: &str, mount: Option<&str>| StoragePermission {
+            uri: uri.to_string(),
+            access: vec![AccessType::Read],
+            mount: mount.map(str::to_string),
+            memory: None,
+        };
+        let permissions = |allow: Vec<StoragePermission>| Permissions {
+            storage: Some(PermissionList {
+                allow: Some(allow),
+                deny: None,
+            }),
+            ..Default::default()
+        };
+
+        assert!(permissions(vec![
+            rule("fs://~/projects/app", Some("/workspace")),
+            rule("fs://$XDG_DATA_HOME", Some("/data")),
+            rule("mem://scratch", None),
+        ])
+        .validate()
+        .is_ok());
+
+        // Same guest path from different hosts
+        assert!(permissions(vec![
+            rule("fs:///srv/a", Some("/data")),
+            rule("fs:///srv/b", Some("/data/")),
+        ])
+        .validate()
+        .is_err());
+
+        // Nested guest paths
+        assert!(permissions(vec![
+            rule("fs:///srv/a", Some("/data")),
+            rule("fs:///tmp", Some("/data/tmp")),
+        ])
+        .validate()
+        .is_err());
+        assert!(
+            permissions(vec![rule("fs://work", None), rule("mem://work/tmp", None)])
+                .validate()
+                .is_err()
+        );
+
+        // Paths are normalized before comparing
+        assert!(permissions(vec![
+            rule("fs://data/../data/cache", None),
+            rule("fs:///srv/cache", Some("data")),
+        ])
+        .validate()
+        .is_err());
+        assert!(permissions(vec![
+            rule("fs:///srv/a", Some("/data//")),
+            rule("fs:///srv/b", Some("/./data/x")),
+        ])
+        .validate()
+        .is_err());
+
+        // Sibling paths that share a prefix string are fine
+        assert!(
+            permissions(vec![rule("fs://data", None), rule("fs://database", None)])
+                .validate()
+                .is_ok()
+        );
+
+        // Wildcard patterns are access rules rather than mounts
+        assert!(permissions(vec![
+            rule("fs://work/agent/**", None),
+            rule("fs://work/agent/config.yaml", None),
+        ])
+        .validate()
+        .is_ok());
+
+        assert!(permissions(vec![rule("fs:///srv", Some(""))])
+            .validate()
+            .is_err());
+        assert!(permissions(vec![rule("fs:///srv", Some("/data/../etc"))])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_host_dependent_uri_requires_mount() {
+        let rule = |uri: &str, mount: Option<&str>| Permissions {
+            storage: Some(PermissionList {
+                allow: Some(vec![StoragePermission {
+                    uri: uri.to_string(),
+                    access: vec![AccessType::Read],
+                    mount: mount.map(str::to_string),
+                    memory: None,
+                }]),
+                deny: None,
+            }),
+            ..Default::default()
+        };
+
+        assert!(rule("fs://~/projects/app", None).validate().is_err());
+        assert!(rule("fs://$HOME/data", None).validate().is_err());
+        assert!(rule("fs://${XDG_CACHE_HOME}/app", None).validate().is_err());
+
+        assert!(rule("fs://~/projects/app", Some("/workspace"))
+            .validate()
+            .is_ok());
+        assert!(rule("fs:///srv/data", None).validate().is_ok());
+        assert!(rule("fs://work/data", None).validate().is_ok());
+    }
+
+    #[test]
+    fn test_memory_mount_validation() {
+        let mount = |uri: &str, memory: Option<MemoryMountOptions>| Permissions {
+            storage: Some(PermissionList {
+                allow: Some(vec![StoragePermission {
+                    uri: uri.to_string(),
+                    access: vec![AccessType::Read, AccessType::Write],
+                    mount: None,
+                    memory,
+                }]),
+                deny: None,
+            }),
+            ..Default::default()
+        };
+
+        let options = MemoryMountOptions {
+            quota: Some(MemoryLimit::String("16Mi".to_string())),
+            scope: MemoryScope::Session,
+        };
+        assert!(mount("mem://scratch", Some(options.clone()))
+            .validate()
+            .is_ok());
+        assert!(mount("mem://scratch", None).validate().is_ok());
+
+        assert!(mount("mem://", None).validate().is_err());
+        assert!(mount("mem://scratch/**", None).validate().is_err());
+        assert!(mount("fs://work", Some(options)).validate().is_err());
+
+        let bad_quota = MemoryMountOptions {
+            quota: Some(MemoryLimit::String("lots".to_string())),
+            ..Default::default()
+        };
+        assert!(mount("mem://scratch", Some(bad_quota)).validate().is_err());
+
+        let yaml = r#"
+storage:
+  allow:
+    - uri: "mem://scratch"
+      access: ["read", "write"]
+      memory:
+        quota: "1Mi"
+        scope: session
+"#;
+        let permissions: Permissions = serde_yaml::from_str(yaml).unwrap();
+        permissions.validate().unwrap();
+        let memory = permissions.storage.unwrap().allow.unwrap()[0]
+            .memory
+            .clone()
+            .unwrap();
+        assert_eq!(memory.scope, MemoryScope::Session);
+        assert_eq!(memory.quota.unwrap().to_bytes().unwrap(), 1024 * 1024);
+    }
+
+    #[test]
+    fn test_clock_permission_validation() {
+        let fixed = ClockPermissions {
+            mode: ClockMode::Fixed,
+            time: Some(1_700_000_000),
+            offset: None,
+        };
+        assert!(fixed.validate().is_ok());
+
+        let fixed_without_time = ClockPermissions {
+            mode: ClockMode::Fixed,
+            ..Default::default()
+        };
+        assert!(fixed_without_time.validate().is_err());
+
+        let offset_without_value = ClockPermissions {
+            mode: ClockMode::Offset,
+            ..Default::default()
+        };
+        assert!(offset_without_value.validate().is_err());
+
+        let deny_with_time = ClockPermissions {
+            mode: ClockMode::Deny,
+            time: Some(0),
+            offset: None,
+        };
+        assert!(deny_with_time.validate().is_err());
+
+        let permissions = Permissions {
+            clock: Some(fixed_without_time),
+            ..Default::default()
+        };
+        assert!(permissions.validate().is_err());
+    }
+
+    #[test]
+    fn test_clock_and_random_yaml() {
+        let yaml = r#"
+clock:
+  mode: offset
+  offset: -3600
+random:
+  seed: 42
+"#;
+        let permissions: Permissions = serde_yaml::from_str(yaml).unwrap();
+        permissions.validate().unwrap();
+
+        let clock = permissions.clock.unwrap();
+        assert_eq!(clock.mode, ClockMode::Offset);
+        assert_eq!(clock.offset, Some(-3600));
+        assert_eq!(permissions.random.unwrap().seed, Some(42));
+    }
+
     #[test]
     fn test_storage_permission_validation() {
         let permissions = Permissions {
@@ -538,6 +973,8 @@ mod tests {
                 allow: Some(vec![StoragePermission {
                     uri: "".to_string(),
                     access: vec![AccessType::Read],
+                    mount: None,
+                    memory: None,
                 }]),
                 deny: None,
             }),
@@ -569,6 +1006,8 @@ mod tests {
                 allow: Some(vec![StoragePermission {
                     uri: "fs://work/agent/**".to_string(),
                     access: vec![AccessType::Read, AccessType::Write],
+                    mount: None,
+                    memory: None,
                 }]),
                 deny: None,
             }),
@@ -632,15 +1071,21 @@ mod tests {
                     StoragePermission {
                         uri: "fs://work/agent/**".to_string(),
                         access: vec![AccessType::Read, AccessType::Write],
+                        mount: None,
+                        memory: None,
                     },
                     StoragePermission {
                         uri: "fs://work/*/temp".to_string(),
                         access: vec![AccessType::Read],
+                        mount: None,
+                        memory: None,
                     },
                 ]),
                 deny: Some(vec![StoragePermission {
                     uri: "fs://work/agent/secret/*".to_string(),
                     access: vec![AccessType::Write],
+                    mount: None,
+                    memory: None,
                 }]),
             }),
             network: Some(PermissionList {
@@ -776,6 +1221,13 @@ mod tests {
         let invalid_memory =
             ResourceLimitValues::new(None, Some(MemoryLimit::String("invalidMi".to_string())));
         assert!(invalid_memory.validate().is_err());
+
+        // CPU time limit
+        let mut cpu_time = ResourceLimitValues::new(None, None);
+        cpu_time.cpu_time_ms = Some(250);
+        assert!(cpu_time.validate().is_ok());
+        cpu_time.cpu_time_ms = Some(0);
+        assert!(cpu_time.validate().is_err());
     }
 
     #[test]
@@ -860,6 +1312,8 @@ mod tests {
                 allow: Some(vec![StoragePermission {
                     uri: "fs://workspace/**".to_string(),
                     access: vec![AccessType::Read, AccessType::Write],
+                    mount: None,
+                    memory: None,
                 }]),
                 deny: None,
             }),
@@ -876,6 +1330,8 @@ mod tests {
                 io: None,
             }),
             ipc: None,
+            clock: None,
+            random: None,
         };
 
         assert!(permissions.validate().is_ok());
@@ -888,6 +1344,8 @@ mod tests {
                 allow: Some(vec![StoragePermission {
                     uri: "fs://work/agent/**file".to_string(),
                     access: vec![AccessType::Read],
+                    mount: None,
+                    memory: None,
                 }]),
                 deny: None,
             }),
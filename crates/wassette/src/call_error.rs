@@ -0,0 +1,78 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Classified failures of component calls
+
+use std::fmt;
+
+/// Why a component call failed before producing a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallErrorKind {
+    /// The arguments could not be converted into the function's parameter types
+    InvalidArguments,
+    /// The component attempted an operation its policy does not allow
+    PermissionDenied,
+    /// The component trapped while executing
+    Trap,
+}
+
+impl CallErrorKind {
+    /// Stable machine-readable code reported to clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            CallErrorKind::InvalidArguments => "invalid_arguments",
+            CallErrorKind::PermissionDenied => "permission_denied",
+            CallErrorKind::Trap => "trap",
+        }
+    }
+}
+
+/// Error returned by [`crate::LifecycleManager::execute_component_call`] for failures that
+/// callers may want to tell apart. Retrieve it with `anyhow::Error::downcast_ref`.
+#[derive(Debug)]
+pub struct ComponentCallError {
+    /// The category of the failure
+    pub kind: CallErrorKind,
+    /// Human-readable description of the failure
+    pub message: String,
+}
+
+impl ComponentCallError {
+    pub(crate) fn new(kind: CallErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn invalid_arguments(error: impl fmt::Display) -> anyhow::Error {
+        Self::new(
+            CallErrorKind::InvalidArguments,
+            format!("Invalid arguments: {error:#}"),
+        )
+        .into()
+    }
+
+    pub(crate) fn permission_denied(message: String) -> anyhow::Error {
+        Self::new(CallErrorKind::PermissionDenied, message).into()
+    }
+
+    pub(crate) fn trap(error: &anyhow::Error) -> anyhow::Error {
+        if error.downcast_ref::<wasmtime::Trap>() == Some(&wasmtime::Trap::Interrupt) {
+            return Self::new(
+                CallErrorKind::Trap,
+                "Component exceeded its time limit (cpu_time_ms, measured in wall-clock time) and was interrupted",
+            )
+            .into();
+        }
+        Self::new(CallErrorKind::Trap, format!("{error:#}")).into()
+    }
+}
+
+impl fmt::Display for ComponentCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ComponentCallError {}
@@ -0,0 +1,146 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Virtualized clocks handed to components whose policy restricts access to host time
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use wasmtime_wasi::{HostMonotonicClock, HostWallClock};
+
+const NANOS_RESOLUTION: Duration = Duration::from_nanos(1);
+
+/// A wall clock that always reports the same instant
+pub(crate) struct FixedWallClock {
+    since_epoch: Duration,
+}
+
+impl FixedWallClock {
+    pub(crate) fn new(secs_since_epoch: u64) -> Self {
+        Self {
+            since_epoch: Duration::from_secs(secs_since_epoch),
+        }
+    }
+}
+
+impl HostWallClock for FixedWallClock {
+    fn resolution(&self) -> Duration {
+        NANOS_RESOLUTION
+    }
+
+    fn now(&self) -> Duration {
+        self.since_epoch
+    }
+}
+
+/// A wall clock that follows the host clock shifted by a fixed number of seconds
+pub(crate) struct OffsetWallClock {
+    offset_secs: i64,
+}
+
+impl OffsetWallClock {
+    pub(crate) fn new(offset_secs: i64) -> Self {
+        Self { offset_secs }
+    }
+}
+
+impl HostWallClock for OffsetWallClock {
+    fn resolution(&self) -> Duration {
+        NANOS_RESOLUTION
+    }
+
+    fn now(&self) -> Duration {
+        let host = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let offset = Duration::from_secs(self.offset_secs.unsigned_abs());
+        if self.offset_secs >= 0 {
+            host.saturating_add(offset)
+        } else {
+            host.saturating_sub(offset)
+        }
+    }
+}
+
+/// A monotonic clock that never advances, so repeated runs observe identical timings
+pub(crate) struct FrozenMonotonicClock;
+
+impl HostMonotonicClock for FrozenMonotonicClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
+
+/// A monotonic clock that starts at zero when the component is instantiated, so the guest
+/// cannot learn anything about the host's uptime.
+pub(crate) struct VirtualMonotonicClock {
+    start: Instant,
+}
+
+impl VirtualMonotonicClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl HostMonotonicClock for VirtualMonotonicClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.start
+            .elapsed()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_wall_clock() {
+        let clock = FixedWallClock::new(1_700_000_000);
+        assert_eq!(clock.now(), Duration::from_secs(1_700_000_000));
+        assert_eq!(clock.now(), clock.now());
+    }
+
+    #[test]
+    fn test_offset_wall_clock() {
+        let host = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let ahead = OffsetWallClock::new(3600).now();
+        assert!(ahead >= host + Duration::from_secs(3600));
+
+        let behind = OffsetWallClock::new(-3600).now();
+        assert!(behind < host);
+        assert!(behind >= host - Duration::from_secs(3601));
+
+        // Offsets larger than the current time saturate at the epoch instead of panicking
+        assert_eq!(OffsetWallClock::new(i64::MIN).now(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_frozen_monotonic_clock() {
+        let clock = FrozenMonotonicClock;
+        let first = clock.now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), first);
+    }
+
+    #[test]
+    fn test_virtual_monotonic_clock_starts_near_zero() {
+        let clock = VirtualMonotonicClock::new();
+        let first = clock.now();
+        assert!(first < Duration::from_secs(1).as_nanos() as u64);
+        assert!(clock.now() >= first);
+    }
+}
@@ -13,8 +13,14 @@ use sha2::{Digest, Sha256};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio::task::spawn_blocking;
 
+use crate::digest::{digests_match, file_sha256_digest};
 use crate::loader::DownloadedResource;
-use crate::{ComponentMetadata, ValidationStamp};
+use crate::precompiled::PrecompiledCache;
+use crate::state_index::StateIndex;
+use crate::{ComponentMetadata, ComponentVersion, ValidationStamp};
+
+/// Index of the retained versions of a component, newest first
+const VERSIONS_INDEX: &str = "versions.json";
 
 /// Handles filesystem layout and metadata persistence for components.
 #[derive(Clone)]
@@ -22,12 +28,25 @@ pub struct ComponentStorage {
     root: PathBuf,
     downloads_dir: PathBuf,
     downloads_semaphore: Arc<Semaphore>,
+    retained_versions: usize,
+    precompiled: PrecompiledCache,
+    state: StateIndex,
 }
 
 impl ComponentStorage {
-    /// Create a new storage manager rooted at the component directory.
-    pub async fn new(root: impl Into<PathBuf>, max_concurrent_downloads: usize) -> Result<Self> {
+    /// Create a new storage manager rooted at the component directory. Precompiled components
+    /// for `engine` are cached in `precompiled_dir`, or in a directory below the component
+    /// directory when it is not set. The keys signing the cache and the state index are kept in
+    /// `key_dir`.
+    pub async fn new(
+        root: impl Into<PathBuf>,
+        key_dir: impl Into<PathBuf>,
+        max_concurrent_downloads: usize,
+        precompiled_dir: Option<PathBuf>,
+        engine: &wasmtime::Engine,
+    ) -> Result<Self> {
         let root = root.into();
+        let key_dir = key_dir.into();
         let downloads_dir = root.join(crate::DOWNLOADS_DIR);
 
         tokio::fs::create_dir_all(&root).await.with_context(|| {
@@ -43,24 +62,54 @@ impl ComponentStorage {
                 )
             })?;
 
+        let precompiled = PrecompiledCache::open(
+            precompiled_dir.unwrap_or_else(|| root.join(crate::PRECOMPILED_DIR)),
+            &key_dir.join(crate::PRECOMPILED_KEY),
+            engine,
+        )
+        .await?;
+        let state = StateIndex::open(
+            root.join(crate::STATE_INDEX),
+            StateIndex::key_path(&key_dir, &root)?,
+        );
+
         Ok(Self {
             root,
             downloads_dir,
             downloads_semaphore: Arc::new(Semaphore::new(max_concurrent_downloads.max(1))),
+            retained_versions: crate::DEFAULT_RETAINED_VERSIONS,
+            precompiled,
+            state,
         })
     }
 
+    /// Keep up to `retained_versions` previous versions of each component when it is replaced.
+    /// Zero disables the version history.
+    pub fn with_retained_versions(mut self, retained_versions: usize) -> Self {
+        self.retained_versions = retained_versions;
+        self
+    }
+
     /// Root component directory containing components.
     pub fn root(&self) -> &Path {
         &self.root
     }
 
     /// Directory used for staging downloaded artifacts.
-    #[allow(dead_code)]
     pub fn downloads_dir(&self) -> &Path {
         &self.downloads_dir
     }
 
+    /// Cache of precompiled components.
+    pub(crate) fn precompiled(&self) -> &PrecompiledCache {
+        &self.precompiled
+    }
+
+    /// Index of the component and policy files written by wassette.
+    pub(crate) fn state(&self) -> &StateIndex {
+        &self.state
+    }
+
     async fn acquire_download_permit(&self) -> OwnedSemaphorePermit {
         self.downloads_semaphore
             .clone()
@@ -85,8 +134,9 @@ impl ComponentStorage {
             .join(format!("{component_id}.{}", crate::METADATA_EXT))
     }
 
-    /// Absolute path to the precompiled component cache file.
-    pub fn precompiled_path(&self, component_id: &str) -> PathBuf {
+    /// Absolute path to the precompiled component file written next to the component by
+    /// earlier releases. It is never loaded and only removed.
+    pub fn legacy_precompiled_path(&self, component_id: &str) -> PathBuf {
         self.root
             .join(format!("{component_id}.{}", crate::PRECOMPILED_EXT))
     }
@@ -96,7 +146,28 @@ impl ComponentStorage {
         self.root.join(format!("{component_id}.policy.meta.json"))
     }
 
-    /// Stage a downloaded component artifact into storage, replacing any existing files.
+    /// Directory holding the previous versions of a component.
+    pub fn versions_dir(&self, component_id: &str) -> PathBuf {
+        self.root.join(crate::VERSIONS_DIR).join(component_id)
+    }
+
+    fn version_path(&self, component_id: &str, digest: &str, extension: &str) -> PathBuf {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        self.versions_dir(component_id)
+            .join(format!("{}.{extension}", hex.to_ascii_lowercase()))
+    }
+
+    /// Absolute path to the blob holding the component bytes with the given digest.
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        self.root
+            .join(crate::BLOBS_DIR)
+            .join(hex.to_ascii_lowercase())
+    }
+
+    /// Stage a downloaded component artifact into storage. The artifact it replaces is moved
+    /// into the version history of the component. When the incoming bytes are already the
+    /// active version, its metadata is kept.
     pub async fn install_component_artifact(
         &self,
         component_id: &str,
@@ -104,7 +175,29 @@ impl ComponentStorage {
     ) -> Result<PathBuf> {
         let _permit = self.acquire_download_permit().await;
 
-        self.remove_component_artifacts(component_id).await?;
+        let incoming_digest = file_sha256_digest(resource.as_ref()).await?;
+        let component_path = self.component_path(component_id);
+        let previous_digest = if component_path.exists() {
+            Some(file_sha256_digest(&component_path).await?)
+        } else {
+            None
+        };
+        let unchanged = previous_digest
+            .as_deref()
+            .is_some_and(|digest| digests_match(digest, &incoming_digest));
+        let policy_path = self.policy_path(component_id);
+        let previous_policy = tokio::fs::read(&policy_path).await.ok();
+        self.archive_active_version(component_id, &incoming_digest)
+            .await?;
+        self.prune_versions(component_id).await?;
+        if unchanged {
+            // The component file is a link to the blob, so it must be removed rather than
+            // overwritten in place
+            self.remove_if_exists(&component_path, "component file", component_id)
+                .await?;
+        } else {
+            self.remove_component_artifacts(component_id).await?;
+        }
 
         resource.copy_to(self.root()).await.with_context(|| {
             format!(
@@ -112,8 +205,94 @@ impl ComponentStorage {
                 self.root.display()
             )
         })?;
+        self.store_blob(component_id, &incoming_digest).await?;
+
+        // OCI artifacts may bring a policy that is copied next to the component
+        self.record_component(component_id, &incoming_digest)
+            .await?;
+        if tokio::fs::read(&policy_path).await.ok() != previous_policy {
+            self.record_policy(component_id).await?;
+        }
+
+        // Drop the replaced bytes unless the version history or another component links to them
+        if let Some(previous_digest) = previous_digest.filter(|_| !unchanged) {
+            self.release_blob(&previous_digest).await?;
+        }
+
+        Ok(component_path)
+    }
+
+    /// Move the component file into the blob store and replace it with a hard link to the blob.
+    /// A blob that already holds the same bytes is reused. Where hard links are not supported the
+    /// component keeps its own copy.
+    async fn store_blob(&self, component_id: &str, digest: &str) -> Result<()> {
+        let component_path = self.component_path(component_id);
+        let blob_path = self.blob_path(digest);
+        let blobs_dir = self.root.join(crate::BLOBS_DIR);
+        tokio::fs::create_dir_all(&blobs_dir)
+            .await
+            .with_context(|| {
+                format!("Failed to create blob directory at {}", blobs_dir.display())
+            })?;
+
+        let reusable = blob_path.exists()
+            && file_sha256_digest(&blob_path)
+                .await
+                .is_ok_and(|blob_digest| digests_match(&blob_digest, digest));
+        if reusable {
+            tracing::debug!(component_id = %component_id, %digest, "Reusing stored component blob");
+            tokio::fs::remove_file(&component_path).await?;
+        } else {
+            tokio::fs::rename(&component_path, &blob_path)
+                .await
+                .with_context(|| {
+                    format!("Failed to store component blob at {}", blob_path.display())
+                })?;
+        }
+
+        if let Err(e) = tokio::fs::hard_link(&blob_path, &component_path).await {
+            tracing::debug!(component_id = %component_id, error = %e, "Hard link failed, copying component blob");
+            tokio::fs::copy(&blob_path, &component_path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to copy component blob to {}",
+                        component_path.display()
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Digests of the active and retained versions of a component.
+    pub async fn component_digests(&self, component_id: &str) -> Result<Vec<String>> {
+        let mut digests = Vec::new();
+        let component_path = self.component_path(component_id);
+        if component_path.exists() {
+            digests.push(file_sha256_digest(&component_path).await?);
+        }
+        for version in self.read_versions(component_id).await? {
+            digests.push(version.digest);
+        }
+        Ok(digests)
+    }
 
-        Ok(self.component_path(component_id))
+    /// Remove the blob and precompiled cache entry of a digest once no component file links to
+    /// the blob any more. Without link counts the blob is left to garbage collection.
+    pub async fn release_blob(&self, digest: &str) -> Result<()> {
+        let blob_path = self.blob_path(digest);
+        let Ok(metadata) = tokio::fs::metadata(&blob_path).await else {
+            // Components stored before the blob store only have a precompiled cache entry
+            self.precompiled.remove(digest).await;
+            return Ok(());
+        };
+        if link_count(&metadata).is_none_or(|count| count > 1) {
+            return Ok(());
+        }
+        self.remove_if_exists(&blob_path, "component blob", digest)
+            .await?;
+        self.precompiled.remove(digest).await;
+        Ok(())
     }
 
     /// Remove persisted component artifacts (wasm, metadata, cache) if they exist.
@@ -131,14 +310,202 @@ impl ComponentStorage {
         )
         .await?;
         self.remove_if_exists(
-            &self.precompiled_path(component_id),
-            "precompiled component file",
+            &self.legacy_precompiled_path(component_id),
+            "legacy precompiled component file",
             component_id,
         )
         .await?;
         Ok(())
     }
 
+    /// Load the retained previous versions of a component, newest first.
+    pub async fn read_versions(&self, component_id: &str) -> Result<Vec<ComponentVersion>> {
+        let path = self.versions_dir(component_id).join(VERSIONS_INDEX);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Failed to parse version index at {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to read version index at {}", path.display())),
+        }
+    }
+
+    async fn write_versions(
+        &self,
+        component_id: &str,
+        versions: &[ComponentVersion],
+    ) -> Result<()> {
+        let dir = self.versions_dir(component_id);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed to create versions directory at {}", dir.display()))?;
+        let path = dir.join(VERSIONS_INDEX);
+        let json =
+            serde_json::to_string_pretty(versions).context("Failed to serialize version index")?;
+        tokio::fs::write(&path, json)
+            .await
+            .with_context(|| format!("Failed to write version index to {}", path.display()))
+    }
+
+    /// Move the active artifact of a component into the version history, unless it is the same
+    /// artifact as `incoming_digest`. Its precompiled cache entry is kept for a rollback. The
+    /// entry for `incoming_digest` is dropped from the history since that version becomes active.
+    async fn archive_active_version(
+        &self,
+        component_id: &str,
+        incoming_digest: &str,
+    ) -> Result<()> {
+        let mut versions = self.read_versions(component_id).await?;
+        versions.retain(|version| !digests_match(&version.digest, incoming_digest));
+
+        let active_path = self.component_path(component_id);
+        if self.retained_versions > 0 && active_path.exists() {
+            let digest = file_sha256_digest(&active_path).await?;
+            if !digests_match(&digest, incoming_digest) {
+                let installed_at = tokio::fs::metadata(&active_path)
+                    .await
+                    .ok()
+                    .and_then(|metadata| modified_secs(&metadata).ok())
+                    .unwrap_or_default();
+                let (source_uri, manifest_digest) = self
+                    .read_metadata(component_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|metadata| (metadata.source_uri, metadata.manifest_digest))
+                    .unwrap_or_default();
+
+                tokio::fs::create_dir_all(self.versions_dir(component_id)).await?;
+                tokio::fs::rename(
+                    &active_path,
+                    self.version_path(component_id, &digest, "wasm"),
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to archive component file {}", active_path.display())
+                })?;
+
+                versions.retain(|version| !digests_match(&version.digest, &digest));
+                versions.insert(
+                    0,
+                    ComponentVersion {
+                        digest,
+                        source_uri,
+                        manifest_digest,
+                        installed_at,
+                        active: false,
+                    },
+                );
+                tracing::debug!(component_id = %component_id, "Archived previous component version");
+            }
+        }
+
+        self.write_versions(component_id, &versions).await
+    }
+
+    /// Trim the version history to the retained count and delete artifacts that are no longer
+    /// referenced by it, including the precompiled cache entries of dropped versions.
+    async fn prune_versions(&self, component_id: &str) -> Result<()> {
+        let mut versions = self.read_versions(component_id).await?;
+        if versions.is_empty() {
+            return self.remove_versions(component_id).await;
+        }
+
+        let dropped = versions.split_off(versions.len().min(self.retained_versions));
+        if versions.is_empty() {
+            self.remove_versions(component_id).await?;
+            return self.release_blobs(&dropped).await;
+        }
+        self.write_versions(component_id, &versions).await?;
+
+        let dir = self.versions_dir(component_id);
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("Failed to read versions directory {}", dir.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let referenced = path.file_name().is_some_and(|name| name == VERSIONS_INDEX)
+                || versions.iter().any(|version| {
+                    digests_match(
+                        version
+                            .digest
+                            .strip_prefix("sha256:")
+                            .unwrap_or(&version.digest),
+                        stem,
+                    )
+                });
+            if !referenced {
+                self.remove_if_exists(&path, "archived component version", component_id)
+                    .await?;
+            }
+        }
+        self.release_blobs(&dropped).await
+    }
+
+    /// Make a retained version the active artifact again. The current artifact takes its place
+    /// in the version history.
+    pub async fn restore_version(
+        &self,
+        component_id: &str,
+        digest: &str,
+    ) -> Result<ComponentVersion> {
+        let _permit = self.acquire_download_permit().await;
+
+        let version = self
+            .read_versions(component_id)
+            .await?
+            .into_iter()
+            .find(|version| digests_match(&version.digest, digest))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Version {} of component '{}' is not retained",
+                    digest,
+                    component_id
+                )
+            })?;
+
+        self.archive_active_version(component_id, &version.digest)
+            .await?;
+        self.remove_component_artifacts(component_id).await?;
+
+        let archived_path = self.version_path(component_id, &version.digest, "wasm");
+        tokio::fs::rename(&archived_path, self.component_path(component_id))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to restore component version from {}",
+                    archived_path.display()
+                )
+            })?;
+        self.record_component(component_id, &version.digest).await?;
+        self.prune_versions(component_id).await?;
+        Ok(version)
+    }
+
+    async fn release_blobs(&self, versions: &[ComponentVersion]) -> Result<()> {
+        for version in versions {
+            self.release_blob(&version.digest).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the version history of a component.
+    pub async fn remove_versions(&self, component_id: &str) -> Result<()> {
+        let dir = self.versions_dir(component_id);
+        match tokio::fs::remove_dir_all(&dir).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(anyhow!(
+                "Failed to remove versions directory at {}: {}",
+                dir.display(),
+                e
+            )),
+        }
+    }
+
     /// Persist component metadata to disk.
     pub async fn write_metadata(&self, metadata: &ComponentMetadata) -> Result<()> {
         let path = self.metadata_path(&metadata.component_id);
@@ -170,15 +537,55 @@ impl ComponentStorage {
         Ok(Some(metadata))
     }
 
-    /// Write precompiled component bytes to disk.
-    pub async fn write_precompiled(&self, component_id: &str, bytes: &[u8]) -> Result<()> {
-        let path = self.precompiled_path(component_id);
-        tokio::fs::write(&path, bytes).await.with_context(|| {
-            format!(
-                "Failed to write precompiled component to {}",
-                path.display()
-            )
-        })
+    /// Find metadata describing the component bytes with the given digest. The component's own
+    /// metadata is preferred; otherwise the metadata of another component with the same bytes is
+    /// returned, carrying this component's id and source.
+    pub async fn find_metadata(
+        &self,
+        component_id: &str,
+        digest: &str,
+    ) -> Result<Option<ComponentMetadata>> {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        let describes = |metadata: &ComponentMetadata| {
+            metadata
+                .validation_stamp
+                .content_hash
+                .as_deref()
+                .is_some_and(|hash| digests_match(hash, hex))
+        };
+
+        let own = self.read_metadata(component_id).await.ok().flatten();
+        if own.as_ref().is_some_and(describes) {
+            return Ok(own);
+        }
+
+        let suffix = format!(".{}", crate::METADATA_EXT);
+        let mut entries = tokio::fs::read_dir(&self.root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(other_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(&suffix))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            if other_id == component_id {
+                continue;
+            }
+            let Ok(Some(mut metadata)) = self.read_metadata(&other_id).await else {
+                continue;
+            };
+            if describes(&metadata) {
+                tracing::debug!(component_id = %component_id, from = %other_id, "Reusing component metadata");
+                metadata.component_id = component_id.to_string();
+                (metadata.source_uri, metadata.manifest_digest) = own
+                    .map(|own| (own.source_uri, own.manifest_digest))
+                    .unwrap_or_default();
+                return Ok(Some(metadata));
+            }
+        }
+        Ok(None)
     }
 
     /// Remove a file if it exists, translating IO errors into `anyhow`.
@@ -209,33 +616,18 @@ impl ComponentStorage {
 
     /// Create a validation stamp for a component artifact to track stale data on disk.
     ///
-    /// When `include_hash` is `true` the SHA-256 hash of the file is
-    /// recorded in addition to size and modification time so changes can be
-    /// detected even when timestamps are unreliable.
-    pub async fn create_validation_stamp(
-        &self,
-        path: &Path,
-        include_hash: bool,
-    ) -> Result<ValidationStamp> {
+    /// The SHA-256 hash of the file is recorded in addition to size and modification time, so
+    /// changes are detected even when timestamps are unreliable and metadata can be reused for
+    /// any component with the same bytes.
+    pub async fn create_validation_stamp(&self, path: &Path) -> Result<ValidationStamp> {
         let metadata = tokio::fs::metadata(path)
             .await
             .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
 
         let file_size = metadata.len();
-        let mtime = metadata
-            .modified()
-            .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
-            .and_then(|t| {
-                t.duration_since(std::time::UNIX_EPOCH)
-                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
-            })?
-            .as_secs();
-
-        let content_hash = if include_hash {
-            Some(compute_file_hash(path).await?)
-        } else {
-            None
-        };
+        let mtime = modified_secs(&metadata)?;
+
+        let content_hash = Some(compute_file_hash(path).await?);
 
         Ok(ValidationStamp {
             file_size,
@@ -262,15 +654,7 @@ impl ComponentStorage {
             }
         }
 
-        let mtime = match metadata
-            .modified()
-            .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
-            .and_then(|t| {
-                t.duration_since(std::time::UNIX_EPOCH)
-                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
-            })
-            .map(|d| d.as_secs())
-        {
+        let mtime = match modified_secs(&metadata) {
             Ok(mtime) => mtime,
             Err(_) => return false,
         };
@@ -283,6 +667,30 @@ impl ComponentStorage {
     }
 }
 
+/// Number of hard links to a file, where the platform reports it
+#[cfg(unix)]
+fn link_count(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Modification time of a file in seconds since the epoch
+fn modified_secs(metadata: &std::fs::Metadata) -> std::io::Result<u64> {
+    metadata
+        .modified()
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
+        .and_then(|t| {
+            t.duration_since(std::time::UNIX_EPOCH)
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::Other))
+        })
+        .map(|d| d.as_secs())
+}
+
 async fn compute_file_hash(path: &Path) -> Result<String> {
     let file = tokio::fs::File::open(path)
         .await
@@ -11,7 +11,8 @@ use std::time::Duration;
 use anyhow::{Context, Result};
 
 use crate::{
-    get_default_secrets_dir, LifecycleManager, DEFAULT_HTTP_TIMEOUT_SECS, DEFAULT_OCI_TIMEOUT_SECS,
+    get_default_secrets_dir, LifecycleManager, TrustPolicy, DEFAULT_HTTP_TIMEOUT_SECS,
+    DEFAULT_OCI_TIMEOUT_SECS, DEFAULT_RETAINED_VERSIONS,
 };
 
 /// Fully-specified configuration for constructing a [`LifecycleManager`].
@@ -23,6 +24,10 @@ pub struct LifecycleConfig {
     http_client: reqwest::Client,
     oci_client: oci_client::Client,
     eager_load: bool,
+    inherit_env_without_policy: bool,
+    retained_versions: usize,
+    precompiled_cache_dir: Option<PathBuf>,
+    trust_policy: TrustPolicy,
 }
 
 impl LifecycleConfig {
@@ -56,25 +61,53 @@ impl LifecycleConfig {
         self.eager_load
     }
 
-    pub(crate) fn into_parts(
-        self,
-    ) -> (
-        PathBuf,
-        PathBuf,
-        HashMap<String, String>,
-        reqwest::Client,
-        oci_client::Client,
-        bool,
-    ) {
-        (
-            self.component_dir,
-            self.secrets_dir,
-            self.environment_vars,
-            self.http_client,
-            self.oci_client,
-            self.eager_load,
-        )
+    /// Whether components without a policy receive every configured environment variable.
+    pub fn inherit_env_without_policy(&self) -> bool {
+        self.inherit_env_without_policy
     }
+
+    /// How many previous versions of each component are kept for rollback.
+    pub fn retained_versions(&self) -> usize {
+        self.retained_versions
+    }
+
+    /// Directory where precompiled components are cached, if not the default below the
+    /// component directory.
+    pub fn precompiled_cache_dir(&self) -> Option<&Path> {
+        self.precompiled_cache_dir.as_deref()
+    }
+
+    /// Sources components and policies may be loaded from.
+    pub fn trust_policy(&self) -> &TrustPolicy {
+        &self.trust_policy
+    }
+
+    pub(crate) fn into_parts(self) -> LifecycleParts {
+        LifecycleParts {
+            component_dir: self.component_dir,
+            secrets_dir: self.secrets_dir,
+            environment_vars: self.environment_vars,
+            http_client: self.http_client,
+            oci_client: self.oci_client,
+            inherit_env_without_policy: self.inherit_env_without_policy,
+            retained_versions: self.retained_versions,
+            precompiled_cache_dir: self.precompiled_cache_dir,
+            trust_policy: self.trust_policy,
+        }
+    }
+}
+
+/// The settings of a [`LifecycleConfig`] a [`LifecycleManager`] is constructed from.
+pub(crate) struct LifecycleParts {
+    pub(crate) component_dir: PathBuf,
+    pub(crate) secrets_dir: PathBuf,
+    pub(crate) environment_vars: HashMap<String, String>,
+    pub(crate) http_client: reqwest::Client,
+    pub(crate) oci_client: oci_client::Client,
+    pub(crate) inherit_env_without_policy: bool,
+    pub(crate) retained_versions: usize,
+    pub(crate) precompiled_cache_dir: Option<PathBuf>,
+    pub(crate) trust_policy: TrustPolicy,
 }
 
 /// Builder that validates inputs and produces a [`LifecycleConfig`] or [`LifecycleManager`].
@@ -86,6 +119,10 @@ pub struct LifecycleBuilder {
     http_client: Option<reqwest::Client>,
     oci_client: Option<oci_client::Client>,
     eager_load: bool,
+    inherit_env_without_policy: bool,
+    retained_versions: usize,
+    precompiled_cache_dir: Option<PathBuf>,
+    trust_policy: TrustPolicy,
 }
 
 impl LifecycleBuilder {
@@ -99,6 +136,10 @@ impl LifecycleBuilder {
             http_client: None,
             oci_client: None,
             eager_load: true,
+            inherit_env_without_policy: false,
+            retained_versions: DEFAULT_RETAINED_VERSIONS,
+            precompiled_cache_dir: None,
+            trust_policy: TrustPolicy::default(),
         }
     }
 
@@ -124,7 +165,9 @@ impl LifecycleBuilder {
         self
     }
 
-    /// Override the HTTP client.
+    /// Override the HTTP client. The client is used as given, so it does not follow the
+    /// redirect policy of the trust policy: build it with `.redirect(trust.redirect_policy())`
+    /// when `trust.https` is set.
     pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
         self.http_client = Some(client);
         self
@@ -136,6 +179,36 @@ impl LifecycleBuilder {
         self
     }
 
+    /// Expose every configured environment variable to components that have no policy
+    /// attached. By default such components get an empty environment; this restores the
+    /// legacy behavior.
+    pub fn with_env_inheritance_without_policy(mut self, inherit: bool) -> Self {
+        self.inherit_env_without_policy = inherit;
+        self
+    }
+
+    /// Keep up to `retained_versions` previous versions of each component so a replaced
+    /// component can be rolled back. Zero disables the version history.
+    pub fn with_retained_versions(mut self, retained_versions: usize) -> Self {
+        self.retained_versions = retained_versions;
+        self
+    }
+
+    /// Cache precompiled components in `dir` instead of a directory below the component
+    /// directory. Entries are verified with a key kept in the secrets directory, so the cache
+    /// directory should not be shared between installations with different secrets directories.
+    pub fn with_precompiled_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.precompiled_cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Only load components and policies from the sources `policy` allows. By default every
+    /// source is allowed.
+    pub fn with_trust_policy(mut self, policy: TrustPolicy) -> Self {
+        self.trust_policy = policy;
+        self
+    }
+
     /// Control whether the manager eagerly loads components during build.
     pub fn with_eager_loading(mut self, eager: bool) -> Self {
         self.eager_load = eager;
@@ -153,7 +226,7 @@ impl LifecycleBuilder {
 
         let http_client = match self.http_client {
             Some(client) => client,
-            None => default_http_client()?,
+            None => default_http_client(&self.trust_policy)?,
         };
 
         let oci_client = match self.oci_client {
@@ -168,6 +241,10 @@ impl LifecycleBuilder {
             http_client,
             oci_client,
             eager_load: self.eager_load,
+            inherit_env_without_policy: self.inherit_env_without_policy,
+            retained_versions: self.retained_versions,
+            precompiled_cache_dir: self.precompiled_cache_dir,
+            trust_policy: self.trust_policy,
         })
     }
 
@@ -188,8 +265,9 @@ impl LifecycleBuilder {
     }
 }
 
-/// Create the default HTTP client used when none is supplied.
-fn default_http_client() -> Result<reqwest::Client> {
+/// Create the default HTTP client used when none is supplied. Redirects are checked against the
+/// trust policy.
+fn default_http_client(trust: &TrustPolicy) -> Result<reqwest::Client> {
     let http_timeout = std::env::var("HTTP_TIMEOUT_SECS")
         .ok()
         .and_then(|s| s.parse().ok())
@@ -197,6 +275,7 @@ fn default_http_client() -> Result<reqwest::Client> {
 
     reqwest::Client::builder()
         .timeout(Duration::from_secs(http_timeout))
+        .redirect(trust.redirect_policy())
         .build()
         .context("Failed to create default HTTP client")
 }
@@ -0,0 +1,46 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! SHA-256 digests used to pin and verify component artifacts
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Returns the digest of `data` in OCI form (`sha256:<hex>`)
+pub fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Returns the digest of the file at `path` in OCI form (`sha256:<hex>`)
+pub(crate) async fn file_sha256_digest(path: &Path) -> Result<String> {
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {} for digest", path.display()))?;
+    Ok(sha256_digest(&data))
+}
+
+/// Compares two digests, ignoring the case of the hex part
+pub(crate) fn digests_match(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_digest() {
+        assert_eq!(
+            sha256_digest(b"hello world"),
+            "sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn test_digests_match_ignores_case() {
+        assert!(digests_match("sha256:ABCDEF", "sha256:abcdef"));
+        assert!(!digests_match("sha256:abcdef", "sha256:abcde0"));
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Garbage collection of orphaned artifacts in the component directory
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::component_storage::ComponentStorage;
+use crate::digest::file_sha256_digest;
+
+/// Downloads and partially written cache entries younger than this may still be in use by a
+/// running load
+const STALE_DOWNLOAD_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Per-component files that are only meaningful next to the component's `.wasm` file
+const COMPONENT_ARTIFACT_SUFFIXES: &[(&str, &str)] = &[
+    (
+        crate::METADATA_EXT,
+        "component metadata without a component",
+    ),
+    ("policy.meta.json", "policy metadata without a component"),
+    ("policy.yaml", "policy without a component"),
+];
+
+/// A file or directory removed by garbage collection
+#[derive(Debug, Clone, Serialize)]
+pub struct GcEntry {
+    /// Path of the removed file or directory
+    pub path: PathBuf,
+    /// Why the entry is considered garbage
+    pub reason: String,
+    /// Disk space the entry used
+    pub bytes: u64,
+}
+
+/// Result of a garbage collection sweep of the component directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    /// Whether entries were only reported and not removed
+    pub dry_run: bool,
+    /// Entries that were removed, or would be removed in a dry run
+    pub removed: Vec<GcEntry>,
+    /// Total disk space reclaimed, or that would be reclaimed in a dry run
+    pub reclaimed_bytes: u64,
+}
+
+impl GcReport {
+    fn push(&mut self, path: PathBuf, reason: &str, bytes: u64) {
+        self.reclaimed_bytes += bytes;
+        self.removed.push(GcEntry {
+            path,
+            reason: reason.to_string(),
+            bytes,
+        });
+    }
+}
+
+impl ComponentStorage {
+    /// Find artifacts that no longer belong to an installed component, including entries of the
+    /// precompiled cache, and remove them unless `dry_run` is set. Files the component directory
+    /// does not manage are left alone.
+    pub async fn collect_garbage(&self, dry_run: bool) -> Result<GcReport> {
+        let mut report = GcReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        let mut installed = HashSet::new();
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(self.root()).await.with_context(|| {
+            format!(
+                "Failed to read component directory {}",
+                self.root().display()
+            )
+        })?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            match name.strip_suffix(".wasm") {
+                Some(component_id) => {
+                    installed.insert(component_id.to_string());
+                }
+                None => files.push((entry.path(), name)),
+            }
+        }
+
+        for (path, name) in files {
+            let legacy_precompiled = name
+                .strip_suffix(crate::PRECOMPILED_EXT)
+                .is_some_and(|stem| stem.ends_with('.'));
+            let reason = if legacy_precompiled {
+                Some("precompiled component in the location used by earlier releases")
+            } else {
+                COMPONENT_ARTIFACT_SUFFIXES
+                    .iter()
+                    .find_map(|(suffix, reason)| {
+                        let component_id = name.strip_suffix(suffix)?.strip_suffix('.')?;
+                        (!installed.contains(component_id)).then_some(*reason)
+                    })
+            };
+            if let Some(reason) = reason {
+                let bytes = disk_usage(&path).await;
+                report.push(path, reason, bytes);
+            }
+        }
+
+        let mut referenced_digests = HashSet::new();
+        for component_id in &installed {
+            let digest = file_sha256_digest(&self.component_path(component_id)).await?;
+            referenced_digests.insert(digest_hex(&digest));
+            for version in self.read_versions(component_id).await? {
+                referenced_digests.insert(digest_hex(&version.digest));
+            }
+        }
+        self.collect_precompiled(&referenced_digests, &mut report)
+            .await?;
+
+        let now = SystemTime::now();
+        let blobs_dir = self.root().join(crate::BLOBS_DIR);
+        if blobs_dir.is_dir() {
+            let mut entries = tokio::fs::read_dir(&blobs_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let referenced = entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|hex| referenced_digests.contains(hex));
+                if referenced {
+                    continue;
+                }
+                // A running load writes the blob before it links the component to it
+                let modified = entry.metadata().await?.modified()?;
+                if now.duration_since(modified).unwrap_or_default() < STALE_DOWNLOAD_AGE {
+                    continue;
+                }
+                let bytes = disk_usage(&path).await;
+                report.push(path, "component blob no longer referenced", bytes);
+            }
+        }
+
+        let versions_root = self.root().join(crate::VERSIONS_DIR);
+        if versions_root.is_dir() {
+            let mut entries = tokio::fs::read_dir(&versions_root).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let Some(component_id) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                if installed.contains(&component_id) {
+                    self.collect_unreferenced_versions(&component_id, &mut report)
+                        .await?;
+                } else {
+                    let bytes = disk_usage(&path).await;
+                    report.push(path, "version history without a component", bytes);
+                }
+            }
+        }
+
+        let mut entries = tokio::fs::read_dir(self.downloads_dir()).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let modified = entry.metadata().await?.modified()?;
+            if now.duration_since(modified).unwrap_or_default() >= STALE_DOWNLOAD_AGE {
+                let path = entry.path();
+                let bytes = disk_usage(&path).await;
+                report.push(path, "stale download", bytes);
+            }
+        }
+
+        if !dry_run {
+            for entry in &report.removed {
+                let result = if entry.path.is_dir() {
+                    tokio::fs::remove_dir_all(&entry.path).await
+                } else {
+                    tokio::fs::remove_file(&entry.path).await
+                };
+                match result {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(e)
+                            .with_context(|| format!("Failed to remove {}", entry.path.display()))
+                    }
+                }
+                tracing::debug!(path = %entry.path.display(), reason = %entry.reason, "Removed garbage");
+            }
+        }
+
+        tracing::info!(
+            entries = report.removed.len(),
+            bytes = report.reclaimed_bytes,
+            dry_run,
+            "Garbage collection finished"
+        );
+        Ok(report)
+    }
+
+    /// Collect precompiled cache entries for components that are neither installed nor kept in
+    /// a version history, and caches built by other engine configurations. Other engines' caches
+    /// are only removed from the default cache below the component directory, since a cache
+    /// directory configured elsewhere may be shared with other Wassette releases.
+    async fn collect_precompiled(
+        &self,
+        referenced_digests: &HashSet<String>,
+        report: &mut GcReport,
+    ) -> Result<()> {
+        let cache = self.precompiled();
+        let now = SystemTime::now();
+        if cache.root() == self.root().join(crate::PRECOMPILED_DIR) {
+            let mut entries = tokio::fs::read_dir(cache.root()).await.with_context(|| {
+                format!(
+                    "Failed to read precompiled cache directory {}",
+                    cache.root().display()
+                )
+            })?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_name().to_str() != Some(cache.engine_key()) {
+                    let bytes = disk_usage(&path).await;
+                    report.push(
+                        path,
+                        "precompiled cache of another engine configuration",
+                        bytes,
+                    );
+                }
+            }
+        }
+
+        let mut entries = tokio::fs::read_dir(cache.dir()).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let reason = match name
+                .strip_suffix(crate::PRECOMPILED_EXT)
+                .and_then(|stem| stem.strip_suffix('.'))
+            {
+                Some(hex) if referenced_digests.contains(hex) => continue,
+                Some(_) => "precompiled component no longer installed",
+                None => {
+                    let modified = entry.metadata().await?.modified()?;
+                    if now.duration_since(modified).unwrap_or_default() < STALE_DOWNLOAD_AGE {
+                        continue;
+                    }
+                    "incomplete precompiled component"
+                }
+            };
+            let bytes = disk_usage(&path).await;
+            report.push(path, reason, bytes);
+        }
+        Ok(())
+    }
+
+    /// Collect the files in a component's version history that its index no longer lists
+    async fn collect_unreferenced_versions(
+        &self,
+        component_id: &str,
+        report: &mut GcReport,
+    ) -> Result<()> {
+        let referenced: HashSet<String> = self
+            .read_versions(component_id)
+            .await?
+            .iter()
+            .map(|version| digest_hex(&version.digest))
+            .collect();
+
+        let mut entries = tokio::fs::read_dir(self.versions_dir(component_id)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.file_name().is_some_and(|name| name == "versions.json") {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !referenced.contains(stem) {
+                let bytes = disk_usage(&path).await;
+                report.push(path, "version not listed in the version history", bytes);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lowercase hex part of a `sha256:<hex>` digest
+fn digest_hex(digest: &str) -> String {
+    digest
+        .strip_prefix("sha256:")
+        .unwrap_or(digest)
+        .to_ascii_lowercase()
+}
+
+/// Size of a file, or of everything below a directory
+async fn disk_usage(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = tokio::fs::symlink_metadata(&path).await else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(mut entries) = tokio::fs::read_dir(&path).await {
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    pending.push(entry.path());
+                }
+            }
+        } else {
+            total += metadata.len();
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_garbage() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let key_dir = tempfile::tempdir()?;
+        let storage = ComponentStorage::new(
+            tempdir.path(),
+            key_dir.path(),
+            1,
+            None,
+            &wasmtime::Engine::default(),
+        )
+        .await?;
+        let root = tempdir.path();
+
+        for file in [
+            "kept.wasm",
+            "kept.cwasm",
+            "kept.metadata.json",
+            "kept.policy.yaml",
+            "gone.cwasm",
+            "gone.metadata.json",
+            "temp_1234.policy.yaml",
+            "gone.policy.meta.json",
+            "notes.txt",
+            "precompiled.key",
+        ] {
+            std::fs::write(root.join(file), "data")?;
+        }
+        std::fs::create_dir_all(storage.versions_dir("gone"))?;
+        std::fs::write(storage.versions_dir("gone").join("abc.wasm"), "data")?;
+        std::fs::create_dir_all(storage.versions_dir("kept"))?;
+        std::fs::write(storage.versions_dir("kept").join("versions.json"), "[]")?;
+        std::fs::write(storage.versions_dir("kept").join("abc.wasm"), "data")?;
+        let cache = storage.precompiled();
+        std::fs::write(cache.path(&crate::sha256_digest(b"data")), "data")?;
+        std::fs::write(cache.path(&crate::sha256_digest(b"gone")), "data")?;
+        std::fs::create_dir_all(root.join("sha256"))?;
+        std::fs::write(storage.blob_path(&crate::sha256_digest(b"data")), "data")?;
+        std::fs::write(storage.blob_path(&crate::sha256_digest(b"gone")), "data")?;
+        make_stale(&storage.blob_path(&crate::sha256_digest(b"gone")))?;
+        // Written by a load that has not linked it yet
+        std::fs::write(storage.blob_path(&crate::sha256_digest(b"fresh")), "data")?;
+        std::fs::create_dir_all(cache.root().join("other-engine"))?;
+        std::fs::write(cache.root().join("other-engine").join("abc.cwasm"), "data")?;
+        let unreferenced_cache_entry = format!(
+            "precompiled/{}/{}.cwasm",
+            cache.engine_key(),
+            digest_hex(&crate::sha256_digest(b"gone"))
+        );
+
+        let report = storage.collect_garbage(true).await?;
+        let mut removed: Vec<String> = report
+            .removed
+            .iter()
+            .map(|entry| {
+                entry
+                    .path
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        removed.sort();
+        let mut expected = vec![
+            "gone.cwasm".to_string(),
+            "gone.metadata.json".to_string(),
+            "gone.policy.meta.json".to_string(),
+            "kept.cwasm".to_string(),
+            "precompiled/other-engine".to_string(),
+            unreferenced_cache_entry,
+            format!("sha256/{}", digest_hex(&crate::sha256_digest(b"gone"))),
+            "temp_1234.policy.yaml".to_string(),
+            "versions/gone".to_string(),
+            "versions/kept/abc.wasm".to_string(),
+        ];
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert_eq!(report.reclaimed_bytes, 10 * 4);
+        assert!(root.join("gone.cwasm").exists(), "dry run must not delete");
+
+        let report = storage.collect_garbage(false).await?;
+        assert_eq!(report.removed.len(), 10);
+        assert!(!root.join("gone.cwasm").exists());
+        assert!(!storage.versions_dir("gone").exists());
+        for file in [
+            "kept.wasm",
+            "kept.policy.yaml",
+            "notes.txt",
+            "precompiled.key",
+        ] {
+            assert!(root.join(file).exists(), "{file} must be kept");
+        }
+        assert!(cache.path(&crate::sha256_digest(b"data")).exists());
+        assert!(storage.blob_path(&crate::sha256_digest(b"data")).exists());
+        assert!(storage.blob_path(&crate::sha256_digest(b"fresh")).exists());
+
+        assert!(storage.collect_garbage(false).await?.removed.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shared_precompiled_cache_keeps_other_engines() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let key_dir = tempfile::tempdir()?;
+        let cache_dir = tempfile::tempdir()?;
+        let storage = ComponentStorage::new(
+            tempdir.path(),
+            key_dir.path(),
+            1,
+            Some(cache_dir.path().to_path_buf()),
+            &wasmtime::Engine::default(),
+        )
+        .await?;
+        let other_engine = cache_dir.path().join("other-engine");
+        std::fs::create_dir_all(&other_engine)?;
+        std::fs::write(other_engine.join("abc.cwasm"), "data")?;
+
+        let report = storage.collect_garbage(false).await?;
+        assert!(report.removed.is_empty(), "{:?}", report.removed);
+        assert!(other_engine.join("abc.cwasm").exists());
+        Ok(())
+    }
+
+    /// Backdate a file past the age at which garbage collection may remove it
+    fn make_stale(path: &Path) -> Result<()> {
+        let file = std::fs::File::options().write(true).open(path)?;
+        file.set_modified(SystemTime::now() - 2 * STALE_DOWNLOAD_AGE)?;
+        Ok(())
+    }
+}
@@ -128,6 +128,16 @@ impl WassetteWasiState<crate::wasistate::WasiState> {
             .ok()
             .and_then(|guard| guard.clone())
     }
+
+    /// Forget permission errors recorded so far, so only denials from the next call are reported
+    pub fn clear_permission_errors(&self) {
+        if let Ok(mut denial) = self.last_network_denial.lock() {
+            *denial = None;
+        }
+        if let Ok(mut last) = self.inner.last_permission_error.lock() {
+            *last = None;
+        }
+    }
 }
 
 impl<T: WasiView> WasiView for WassetteWasiState<T> {
@@ -13,9 +13,10 @@ use std::time::Instant;
 
 use anyhow::{anyhow, bail, Context, Result};
 use component2json::{
-    component_exports_to_json_schema, component_exports_to_json_schema_with_docs,
-    component_exports_to_tools, component_exports_to_tools_with_docs, create_placeholder_results,
-    extract_package_docs, json_to_vals, vals_to_json, FunctionIdentifier, ToolMetadata,
+    command_tool_name, component_exports_to_json_schema,
+    component_exports_to_json_schema_with_docs, component_exports_to_tools,
+    component_exports_to_tools_with_docs, create_placeholder_results, extract_package_docs,
+    json_to_vals, vals_to_json, FunctionIdentifier, ToolMetadata,
 };
 use etcetera::BaseStrategy;
 use serde::{Deserialize, Serialize};
@@ -26,33 +27,61 @@ use tracing::{debug, info, instrument, warn};
 use wasmtime::component::{Component, InstancePre};
 use wasmtime::Store;
 
+mod call_error;
+mod clocks;
 mod component_storage;
 mod config;
+mod digest;
+mod gc;
 mod http;
 mod loader;
+mod memory_mount;
 pub mod oci_multi_layer;
 mod policy_internal;
+mod precompiled;
+mod retry;
 mod runtime_context;
 pub mod schema;
 mod secrets;
+mod state_index;
+mod storage_guard;
+mod trust;
 mod wasistate;
 
+pub use call_error::{CallErrorKind, ComponentCallError};
 use component_storage::ComponentStorage;
+use config::LifecycleParts;
 pub use config::{LifecycleBuilder, LifecycleConfig};
+pub use digest::sha256_digest;
+pub use gc::{GcEntry, GcReport};
 pub use http::WassetteWasiState;
+pub use loader::component_id_from_uri;
 use loader::{ComponentResource, DownloadedResource};
 use policy_internal::PolicyManager;
 pub use policy_internal::{PermissionGrantRequest, PermissionRule, PolicyInfo};
+pub use retry::{is_transient, Backoff, RetryPolicy};
 use runtime_context::RuntimeContext;
 pub use secrets::SecretsManager;
+pub use state_index::{IntegrityViolation, TamperedArtifact};
+pub use trust::TrustPolicy;
 use wasistate::WasiState;
 pub use wasistate::{
-    create_wasi_state_template_from_policy, CustomResourceLimiter, PermissionError,
+    create_wasi_state_template_from_policy, CommandIo, CustomResourceLimiter, PermissionError,
     WasiStateTemplate,
 };
 
 const DOWNLOADS_DIR: &str = "downloads";
+const VERSIONS_DIR: &str = "versions";
+const BLOBS_DIR: &str = "sha256";
 const PRECOMPILED_EXT: &str = "cwasm";
+const PRECOMPILED_DIR: &str = "precompiled";
+/// Directory below the secrets directory holding the signing keys, out of reach of anything
+/// that can only write to the component directory
+const KEYS_DIR: &str = "keys";
+const PRECOMPILED_KEY: &str = "precompiled.key";
+const STATE_INDEX: &str = "state.json";
+const STATE_KEY_PREFIX: &str = "state";
+const QUARANTINE_DIR: &str = "quarantine";
 const METADATA_EXT: &str = "metadata.json";
 
 // Default timeout configurations
@@ -60,6 +89,9 @@ pub(crate) const DEFAULT_OCI_TIMEOUT_SECS: u64 = 30;
 pub(crate) const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 30;
 pub(crate) const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 8;
 
+/// Number of previous versions kept for each component unless configured otherwise
+pub const DEFAULT_RETAINED_VERSIONS: usize = 3;
+
 /// Get the default secrets directory path based on the OS
 pub(crate) fn get_default_secrets_dir() -> PathBuf {
     let dir_strategy = etcetera::choose_base_strategy();
@@ -94,6 +126,12 @@ pub struct ComponentMetadata {
     pub validation_stamp: ValidationStamp,
     /// Metadata creation timestamp
     pub created_at: u64,
+    /// URI the component was loaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_uri: Option<String>,
+    /// Digest of the OCI manifest the component was pulled from, for `oci://` sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_digest: Option<String>,
 }
 
 /// Validation stamp to check if component has changed
@@ -107,6 +145,72 @@ pub struct ValidationStamp {
     pub content_hash: Option<String>,
 }
 
+/// A version of a component kept in the component directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentVersion {
+    /// SHA-256 digest of the component bytes (`sha256:<hex>`)
+    pub digest: String,
+    /// URI the version was loaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_uri: Option<String>,
+    /// Digest of the OCI manifest the version was pulled from, for `oci://` sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_digest: Option<String>,
+    /// When the version was installed (seconds since epoch)
+    pub installed_at: u64,
+    /// Whether this is the version currently in use
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// Where an installed component was loaded from
+#[derive(Debug, Clone, Copy)]
+struct ComponentSource<'a> {
+    uri: &'a str,
+    manifest_digest: Option<&'a str>,
+}
+
+/// Whether a component installed from an OCI tag has a newer version in the registry
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentUpdateStatus {
+    /// Identifier of the component
+    pub component_id: String,
+    /// OCI reference the component was installed from
+    pub source_uri: String,
+    /// Manifest digest of the installed version, if it was recorded
+    pub current_digest: Option<String>,
+    /// Manifest digest the tag currently points to
+    pub latest_digest: String,
+}
+
+impl ComponentVersion {
+    /// Whether `expected` is the digest of this version's bytes or of the OCI manifest it was
+    /// pulled from
+    pub fn matches_digest(&self, expected: &str) -> bool {
+        digest::digests_match(&self.digest, expected)
+            || self
+                .manifest_digest
+                .as_deref()
+                .is_some_and(|manifest| digest::digests_match(manifest, expected))
+    }
+
+    /// The digest a lockfile should pin: the OCI manifest digest when there is one, otherwise
+    /// the digest of the bytes
+    pub fn pinned_digest(&self) -> &str {
+        self.manifest_digest.as_deref().unwrap_or(&self.digest)
+    }
+}
+
+impl ComponentUpdateStatus {
+    /// Whether the tag points to a different manifest than the installed version. Components
+    /// without a recorded digest are always considered outdated.
+    pub fn is_outdated(&self) -> bool {
+        self.current_digest
+            .as_deref()
+            .is_none_or(|current| !digest::digests_match(current, &self.latest_digest))
+    }
+}
+
 #[derive(Clone, Default)]
 struct ComponentRegistry {
     state: Arc<RwLock<ComponentRegistryState>>,
@@ -138,6 +242,15 @@ pub enum LoadResult {
     New,
 }
 
+/// Arguments accepted by the tool generated for a WASI CLI command component
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CommandRequest {
+    args: Vec<String>,
+    stdin: String,
+    env: HashMap<String, String>,
+}
+
 /// Detailed outcome for a component load operation.
 #[derive(Debug, Clone)]
 pub struct ComponentLoadOutcome {
@@ -147,6 +260,11 @@ pub struct ComponentLoadOutcome {
     pub status: LoadResult,
     /// Normalized tool names exposed by the component after registration.
     pub tool_names: Vec<String>,
+    /// SHA-256 digest of the component bytes (`sha256:<hex>`).
+    pub digest: String,
+    /// Digest of the OCI manifest the component was pulled by, when this load installed it
+    /// from an `oci://` reference.
+    pub manifest_digest: Option<String>,
 }
 
 impl ComponentRegistry {
@@ -304,6 +422,7 @@ pub struct LifecycleManager {
     oci_client: Arc<oci_wasm::WasmClient>,
     http_client: reqwest::Client,
     secrets_manager: Arc<SecretsManager>,
+    trust: Arc<TrustPolicy>,
 }
 
 /// A representation of a loaded component instance. It contains both the base component info and a
@@ -313,6 +432,8 @@ pub struct ComponentInstance {
     component: Arc<Component>,
     instance_pre: Arc<InstancePre<WassetteWasiState<WasiState>>>,
     package_docs: Option<Value>,
+    /// The only tool of a WASI CLI command component, `None` for other components
+    command_tool: Option<String>,
 }
 
 impl LifecycleManager {
@@ -340,19 +461,37 @@ impl LifecycleManager {
     /// Construct a lifecycle manager from an explicit configuration without loading components.
     #[instrument(skip_all, fields(component_dir = %config.component_dir().display()))]
     pub async fn from_config(config: LifecycleConfig) -> Result<Self> {
-        let (component_dir, secrets_dir, environment_vars, http_client, oci_client, _) =
-            config.into_parts();
-
-        let storage =
-            ComponentStorage::new(component_dir.clone(), DEFAULT_DOWNLOAD_CONCURRENCY).await?;
+        let LifecycleParts {
+            component_dir,
+            secrets_dir,
+            environment_vars,
+            http_client,
+            oci_client,
+            inherit_env_without_policy,
+            retained_versions,
+            precompiled_cache_dir,
+            trust_policy,
+        } = config.into_parts();
 
         let runtime = Arc::new(RuntimeContext::initialize()?);
 
+        // The signing keys live in the secrets directory, which has to be locked down first
         let secrets_manager = Arc::new(SecretsManager::new(secrets_dir.clone()));
         secrets_manager.ensure_secrets_dir().await?;
 
+        let storage = ComponentStorage::new(
+            component_dir.clone(),
+            secrets_dir.join(KEYS_DIR),
+            DEFAULT_DOWNLOAD_CONCURRENCY,
+            precompiled_cache_dir,
+            &runtime,
+        )
+        .await?
+        .with_retained_versions(retained_versions);
+
         let environment_vars = Arc::new(environment_vars);
         let oci_client = Arc::new(oci_wasm::WasmClient::new(oci_client));
+        let trust = Arc::new(trust_policy);
 
         let policy_manager = PolicyManager::new(
             storage.clone(),
@@ -360,7 +499,9 @@ impl LifecycleManager {
             Arc::clone(&environment_vars),
             Arc::clone(&oci_client),
             http_client.clone(),
-        );
+            Arc::clone(&trust),
+            inherit_env_without_policy,
+        )?;
 
         Ok(Self {
             runtime,
@@ -370,6 +511,7 @@ impl LifecycleManager {
             oci_client,
             http_client,
             secrets_manager,
+            trust,
         })
     }
 
@@ -431,6 +573,7 @@ impl LifecycleManager {
             uri,
             &self.oci_client,
             &self.http_client,
+            &self.trust,
             show_progress,
         )
         .await?;
@@ -454,11 +597,17 @@ impl LifecycleManager {
         }
     }
 
+    /// Compiles and registers the component at `wasm_path`. `source` is recorded in the
+    /// component metadata; without one, the previously recorded source is kept.
     async fn compile_and_register_component(
         &self,
         component_id: &str,
         wasm_path: &Path,
+        source: Option<ComponentSource<'_>>,
     ) -> Result<ComponentLoadOutcome> {
+        let manifest_digest = source
+            .and_then(|source| source.manifest_digest)
+            .map(str::to_string);
         let (component, wasm_bytes) = self
             .load_component_optimized(wasm_path, component_id)
             .await?;
@@ -470,23 +619,35 @@ impl LifecycleManager {
 
         // Extract package docs from wasm bytes
         let package_docs = extract_package_docs(&wasm_bytes);
+        let digest = digest::sha256_digest(&wasm_bytes);
 
         let component_instance = ComponentInstance {
+            command_tool: command_tool_name(&component, self.runtime.as_ref()),
             component: Arc::new(component),
             instance_pre: Arc::new(instance_pre),
             package_docs: package_docs.clone(),
         };
 
-        // Use package docs if available
-        let tool_metadata = if let Some(ref docs) = package_docs {
-            component_exports_to_tools_with_docs(
-                &component_instance.component,
-                self.runtime.as_ref(),
-                true,
-                docs,
-            )
-        } else {
-            component_exports_to_tools(&component_instance.component, self.runtime.as_ref(), true)
+        // Reuse the tools recorded for the same bytes, otherwise extract them using package docs
+        // if available
+        let tool_metadata = match self.storage.find_metadata(component_id, &digest).await {
+            Ok(Some(metadata)) => tool_metadata_from_cache(metadata),
+            _ => {
+                if let Some(ref docs) = package_docs {
+                    component_exports_to_tools_with_docs(
+                        &component_instance.component,
+                        self.runtime.as_ref(),
+                        true,
+                        docs,
+                    )
+                } else {
+                    component_exports_to_tools(
+                        &component_instance.component,
+                        self.runtime.as_ref(),
+                        true,
+                    )
+                }
+            }
         };
 
         let tool_names: Vec<String> = tool_metadata
@@ -494,9 +655,9 @@ impl LifecycleManager {
             .map(|tool| tool.normalized_name.clone())
             .collect();
 
-        if let Ok(validation_stamp) = self.storage.create_validation_stamp(wasm_path, false).await {
+        if let Ok(validation_stamp) = self.storage.create_validation_stamp(wasm_path).await {
             if let Err(e) = self
-                .save_component_metadata(component_id, &tool_metadata, validation_stamp)
+                .save_component_metadata(component_id, &tool_metadata, validation_stamp, source)
                 .await
             {
                 warn!(%component_id, error = %e, "Failed to save component metadata");
@@ -516,6 +677,8 @@ impl LifecycleManager {
             component_id: component_id.to_string(),
             status: load_result,
             tool_names,
+            digest,
+            manifest_digest,
         })
     }
 
@@ -527,12 +690,203 @@ impl LifecycleManager {
     #[instrument(skip(self))]
     pub async fn load_component(&self, uri: &str) -> Result<ComponentLoadOutcome> {
         debug!(uri, "Loading component");
+        self.trust.check_pinned::<ComponentResource>(uri, None)?;
+
+        // Pull OCI tags by the digest they currently resolve to, so the recorded manifest
+        // digest matches the pulled bytes
+        if let Some((manifest_digest, pinned_uri)) = self.resolve_oci_manifest(uri).await? {
+            let (component_id, resource) = self.resolve_component_resource(&pinned_uri).await?;
+            return self
+                .install_component_resource(
+                    &component_id,
+                    resource,
+                    ComponentSource {
+                        uri,
+                        manifest_digest: Some(&manifest_digest),
+                    },
+                )
+                .await;
+        }
+
+        let (component_id, resource) = self.resolve_component_resource(uri).await?;
+        self.install_component_resource(
+            &component_id,
+            resource,
+            ComponentSource {
+                uri,
+                manifest_digest: None,
+            },
+        )
+        .await
+    }
+
+    /// Loads a component like [`Self::load_component`], but only installs it if it matches
+    /// `expected_digest` (`sha256:<hex>`). The digest may be the SHA-256 of the component bytes
+    /// or, for `oci://` references, the digest of the OCI manifest. Nothing is installed or
+    /// registered when the digest does not match.
+    #[instrument(skip(self))]
+    pub async fn load_component_with_digest(
+        &self,
+        uri: &str,
+        expected_digest: &str,
+    ) -> Result<ComponentLoadOutcome> {
+        debug!(
+            uri,
+            expected_digest, "Loading component with digest verification"
+        );
+        if let Some(pinned_uri) = self.pin_oci_manifest(uri, expected_digest).await? {
+            // The registry response is checked against the pinned manifest digest on pull
+            let (component_id, resource) = self.resolve_component_resource(&pinned_uri).await?;
+            return self
+                .install_component_resource(
+                    &component_id,
+                    resource,
+                    ComponentSource {
+                        uri,
+                        manifest_digest: Some(expected_digest),
+                    },
+                )
+                .await;
+        }
+
         let (component_id, resource) = self.resolve_component_resource(uri).await?;
+        let actual_digest = digest::file_sha256_digest(resource.as_ref()).await?;
+        if !digest::digests_match(&actual_digest, expected_digest) {
+            bail!(
+                "Digest mismatch for component '{}': expected {}, got {}",
+                uri,
+                expected_digest,
+                actual_digest
+            );
+        }
+
+        self.install_component_resource(
+            &component_id,
+            resource,
+            ComponentSource {
+                uri,
+                manifest_digest: None,
+            },
+        )
+        .await
+    }
+
+    /// Parses an `oci://` URI and returns the reference with the digest of the manifest it
+    /// currently points to. References that carry a digest resolve to it without contacting the
+    /// registry. Returns `None` for other URIs.
+    async fn resolve_manifest_digest(
+        &self,
+        uri: &str,
+    ) -> Result<Option<(oci_client::Reference, String)>> {
+        let Some(reference) = uri.trim().strip_prefix("oci://") else {
+            return Ok(None);
+        };
+        let reference: oci_client::Reference =
+            reference.parse().context("Failed to parse OCI reference")?;
+        // The registry is contacted here, before the loader checks the source
+        self.trust.check_source::<ComponentResource>(uri)?;
+        let digest = match reference.digest() {
+            Some(digest) => digest.to_string(),
+            None => self
+                .oci_client
+                .fetch_manifest_digest(&reference, &oci_client::secrets::RegistryAuth::Anonymous)
+                .await
+                .with_context(|| format!("Failed to fetch manifest digest for {}", uri))?,
+        };
+        Ok(Some((reference, digest)))
+    }
+
+    /// Returns the current manifest digest of an `oci://` reference and the reference pinned to
+    /// it. References that already carry a digest are returned as they are. Returns `None` for
+    /// other URIs.
+    async fn resolve_oci_manifest(&self, uri: &str) -> Result<Option<(String, String)>> {
+        let Some((reference, digest)) = self.resolve_manifest_digest(uri).await? else {
+            return Ok(None);
+        };
+        if reference.digest().is_some() {
+            return Ok(Some((digest, uri.to_string())));
+        }
+
+        let pinned = reference.clone_with_digest(digest.clone());
+        Ok(Some((digest, format!("oci://{}", pinned.whole()))))
+    }
+
+    /// Checks whether the OCI tag a component was installed from now points to a different
+    /// manifest. Returns `None` for components that were not installed from an OCI tag.
+    #[instrument(skip(self))]
+    pub async fn check_component_update(
+        &self,
+        component_id: &str,
+    ) -> Result<Option<ComponentUpdateStatus>> {
+        let Some(metadata) = self.load_component_metadata(component_id).await? else {
+            bail!("Component not found: {}", component_id);
+        };
+        let Some(source_uri) = metadata.source_uri else {
+            return Ok(None);
+        };
+        let Some((reference, latest_digest)) = self.resolve_manifest_digest(&source_uri).await?
+        else {
+            return Ok(None);
+        };
+        if reference.digest().is_some() {
+            return Ok(None);
+        }
+
+        Ok(Some(ComponentUpdateStatus {
+            component_id: component_id.to_string(),
+            source_uri,
+            current_digest: metadata.manifest_digest,
+            latest_digest,
+        }))
+    }
+
+    /// Re-pulls and reloads a component when the OCI tag it was installed from points to a new
+    /// manifest. Returns `None` when the component is up to date or was not installed from an
+    /// OCI tag. The replaced version is kept for rollback.
+    #[instrument(skip(self))]
+    pub async fn update_component(
+        &self,
+        component_id: &str,
+    ) -> Result<Option<ComponentLoadOutcome>> {
+        match self.check_component_update(component_id).await? {
+            Some(status) if status.is_outdated() => {
+                info!(
+                    %component_id,
+                    current = ?status.current_digest,
+                    latest = %status.latest_digest,
+                    "Updating component"
+                );
+                self.load_component(&status.source_uri).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the URI pinned to `expected_digest` when `uri` is an OCI reference whose manifest
+    /// currently has that digest
+    async fn pin_oci_manifest(&self, uri: &str, expected_digest: &str) -> Result<Option<String>> {
+        let Some((reference, manifest_digest)) = self.resolve_manifest_digest(uri).await? else {
+            return Ok(None);
+        };
+        if !digest::digests_match(&manifest_digest, expected_digest) {
+            return Ok(None);
+        }
+
+        let pinned = reference.clone_with_digest(manifest_digest);
+        Ok(Some(format!("oci://{}", pinned.whole())))
+    }
+
+    async fn install_component_resource(
+        &self,
+        component_id: &str,
+        resource: DownloadedResource,
+        source: ComponentSource<'_>,
+    ) -> Result<ComponentLoadOutcome> {
         let staged_path = self
-            .stage_component_artifact(&component_id, resource)
+            .stage_component_artifact(component_id, resource)
             .await?;
         let outcome = self
-            .compile_and_register_component(&component_id, &staged_path)
+            .compile_and_register_component(component_id, &staged_path, Some(source))
             .await
             .with_context(|| {
                 format!(
@@ -558,7 +912,12 @@ impl LifecycleManager {
         debug!("Unloading component and removing files from disk");
 
         // Remove files first, then clean up memory on success
+        let digests = self.storage.component_digests(id).await?;
         self.storage.remove_component_artifacts(id).await?;
+        self.storage.remove_versions(id).await?;
+        for digest in &digests {
+            self.storage.release_blob(digest).await?;
+        }
 
         let policy_path = self.get_component_policy_path(id);
         self.storage
@@ -569,6 +928,7 @@ impl LifecycleManager {
         self.storage
             .remove_if_exists(&metadata_path, "policy metadata file", id)
             .await?;
+        self.storage.forget_component(id).await?;
 
         // Only cleanup memory after all files are successfully removed
         self.registry.remove_component(id).await;
@@ -578,6 +938,128 @@ impl LifecycleManager {
         Ok(())
     }
 
+    /// Lists the versions of a component kept on disk: the active version first, followed by
+    /// the retained previous versions, newest first.
+    #[instrument(skip(self))]
+    pub async fn list_component_versions(
+        &self,
+        component_id: &str,
+    ) -> Result<Vec<ComponentVersion>> {
+        let mut versions = Vec::new();
+
+        let active_path = self.component_path(component_id);
+        if let Ok(metadata) = tokio::fs::metadata(&active_path).await {
+            let recorded = self
+                .load_component_metadata(component_id)
+                .await
+                .ok()
+                .flatten();
+            versions.push(ComponentVersion {
+                digest: digest::file_sha256_digest(&active_path).await?,
+                source_uri: recorded
+                    .as_ref()
+                    .and_then(|metadata| metadata.source_uri.clone()),
+                manifest_digest: recorded.and_then(|metadata| metadata.manifest_digest),
+                installed_at: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+                active: true,
+            });
+        }
+
+        versions.extend(self.storage.read_versions(component_id).await?);
+        if versions.is_empty() {
+            bail!("Component not found: {}", component_id);
+        }
+        Ok(versions)
+    }
+
+    /// Switches a component back to a retained previous version and loads it. Without a
+    /// `digest` the most recent previous version is restored. The version being replaced is
+    /// kept in the history, so a rollback can itself be rolled back.
+    #[instrument(skip(self))]
+    pub async fn rollback_component(
+        &self,
+        component_id: &str,
+        digest: Option<&str>,
+    ) -> Result<ComponentLoadOutcome> {
+        let target = match digest {
+            Some(digest) => digest.to_string(),
+            None => self
+                .storage
+                .read_versions(component_id)
+                .await?
+                .into_iter()
+                .next()
+                .map(|version| version.digest)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Component '{}' has no previous version to roll back to",
+                        component_id
+                    )
+                })?,
+        };
+
+        let version = self.storage.restore_version(component_id, &target).await?;
+        let wasm_path = self.component_path(component_id);
+        let outcome = self
+            .compile_and_register_component(
+                component_id,
+                &wasm_path,
+                version.source_uri.as_deref().map(|uri| ComponentSource {
+                    uri,
+                    manifest_digest: version.manifest_digest.as_deref(),
+                }),
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load version {} of component '{}'",
+                    version.digest, component_id
+                )
+            })?;
+
+        info!(%component_id, digest = %outcome.digest, "Rolled back component");
+        Ok(outcome)
+    }
+
+    /// Removes artifacts in the component directory that no longer belong to an installed
+    /// component, such as precompiled files, metadata and policies left behind by failed loads,
+    /// and stale downloads. With `dry_run` nothing is removed.
+    #[instrument(skip(self))]
+    pub async fn collect_garbage(&self, dry_run: bool) -> Result<GcReport> {
+        self.storage.collect_garbage(dry_run).await
+    }
+
+    /// Checks the component and policy files of every installed component against the state
+    /// index written whenever wassette changes them, and returns the files that were changed
+    /// outside wassette. Components installed before the index was first written are recorded
+    /// as they are; after that, components the index does not know are reported as well.
+    #[instrument(skip(self))]
+    pub async fn verify_integrity(&self) -> Result<Vec<IntegrityViolation>> {
+        self.storage.verify_integrity().await
+    }
+
+    /// Records the current files of every installed component as trusted, so changes made
+    /// outside wassette are no longer reported.
+    #[instrument(skip(self))]
+    pub async fn accept_current_state(&self) -> Result<()> {
+        self.storage.accept_current_state().await
+    }
+
+    /// Moves the files of a component into the `quarantine` directory below the component
+    /// directory and unloads it. Returns the directory the files were moved to.
+    #[instrument(skip(self))]
+    pub async fn quarantine_component(&self, component_id: &str) -> Result<PathBuf> {
+        let dir = self.storage.quarantine_component(component_id).await?;
+        self.registry.remove_component(component_id).await;
+        self.policy_manager.cleanup(component_id).await;
+        Ok(dir)
+    }
+
     /// Returns the component ID for a given tool name.
     /// If there are multiple components with the same tool name, returns an error.
     #[instrument(skip(self))]
@@ -678,6 +1160,16 @@ impl LifecycleManager {
         v
     }
 
+    /// Gets the URI a component was loaded from, if it was recorded
+    #[instrument(skip(self))]
+    pub async fn get_component_source_uri(&self, component_id: &str) -> Option<String> {
+        self.load_component_metadata(component_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|metadata| metadata.source_uri)
+    }
+
     /// Gets the schema for a specific component
     #[instrument(skip(self))]
     pub async fn get_component_schema(&self, component_id: &str) -> Option<Value> {
@@ -707,7 +1199,13 @@ impl LifecycleManager {
                 let tools: Vec<Value> = metadata
                     .tool_schemas
                     .into_iter()
-                    .map(|schema| schema::canonicalize_output_schema(&schema))
+                    .map(|mut tool| {
+                        if let Some(output_schema) = tool.get("outputSchema") {
+                            tool["outputSchema"] =
+                                schema::canonicalize_output_schema(output_schema);
+                        }
+                        tool
+                    })
                     .collect();
                 Some(serde_json::json!({
                     "tools": tools
@@ -721,11 +1219,6 @@ impl LifecycleManager {
         self.storage.component_path(component_id)
     }
 
-    /// Get the path to precompiled component file
-    fn component_precompiled_path(&self, component_id: &str) -> PathBuf {
-        self.storage.precompiled_path(component_id)
-    }
-
     pub(crate) fn get_component_policy_path(&self, component_id: &str) -> PathBuf {
         self.policy_manager.policy_path(component_id)
     }
@@ -744,6 +1237,30 @@ impl LifecycleManager {
             .await
     }
 
+    /// Attach a policy document to a component. Use this for policies wassette builds itself;
+    /// policies loaded from a URI go through [`LifecycleManager::attach_policy`]. `source_uri`
+    /// names where the document's permissions were declared, such as a manifest or registry
+    /// index, and must pass the trust policy.
+    pub async fn attach_policy_document(
+        &self,
+        component_id: &str,
+        policy: &policy::PolicyDocument,
+        source_uri: Option<&str>,
+    ) -> Result<()> {
+        if !self.registry.contains_component(component_id).await {
+            return Err(anyhow!("Component not found: {}", component_id));
+        }
+        self.policy_manager
+            .attach_policy_document(component_id, policy, source_uri)
+            .await
+    }
+
+    /// Download and parse the policy at `policy_uri` (`file://`, `oci://` or `https://`)
+    /// without attaching it to any component.
+    pub async fn fetch_policy(&self, policy_uri: &str) -> Result<policy::PolicyDocument> {
+        self.policy_manager.fetch_policy(policy_uri).await
+    }
+
     /// Detach any policy associated with the given component.
     pub async fn detach_policy(&self, component_id: &str) -> Result<()> {
         self.policy_manager.detach_policy(component_id).await
@@ -829,7 +1346,7 @@ impl LifecycleManager {
             bail!("Component not found: {}", component_id);
         }
 
-        self.compile_and_register_component(component_id, &entry_path)
+        self.compile_and_register_component(component_id, &entry_path, None)
             .await
             .with_context(|| {
                 format!(
@@ -847,7 +1364,21 @@ impl LifecycleManager {
         component_id: &str,
         tool_metadata: &[ToolMetadata],
         validation_stamp: ValidationStamp,
+        source: Option<ComponentSource<'_>>,
     ) -> Result<()> {
+        let (source_uri, manifest_digest) = match source {
+            Some(source) => (
+                Some(source.uri.to_string()),
+                source.manifest_digest.map(str::to_string),
+            ),
+            None => self
+                .load_component_metadata(component_id)
+                .await
+                .ok()
+                .flatten()
+                .map(|metadata| (metadata.source_uri, metadata.manifest_digest))
+                .unwrap_or_default(),
+        };
         let metadata = ComponentMetadata {
             component_id: component_id.to_string(),
             tool_schemas: tool_metadata.iter().map(|t| t.schema.clone()).collect(),
@@ -861,6 +1392,8 @@ impl LifecycleManager {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            source_uri,
+            manifest_digest,
         };
 
         self.storage.write_metadata(&metadata).await?;
@@ -877,10 +1410,11 @@ impl LifecycleManager {
         self.storage.read_metadata(component_id).await
     }
 
-    /// Save precompiled component to disk
+    /// Save precompiled component to the cache
     async fn save_precompiled_component(
         &self,
         component_id: &str,
+        digest: &str,
         wasm_bytes: &[u8],
     ) -> Result<()> {
         let precompiled_data = self
@@ -889,49 +1423,44 @@ impl LifecycleManager {
             .context("Failed to precompile component")?;
 
         self.storage
-            .write_precompiled(component_id, &precompiled_data)
+            .precompiled()
+            .store(digest, &precompiled_data)
             .await?;
 
         info!(component_id = %component_id, "Saved precompiled component");
         Ok(())
     }
 
-    /// Load component from precompiled cache or compile fresh
+    /// Load component from precompiled cache or compile fresh. The cache is keyed by the digest
+    /// of the wasm bytes, so an entry built from other bytes is never used.
     async fn load_component_optimized(
         &self,
         wasm_path: &Path,
         component_id: &str,
     ) -> Result<(Component, Vec<u8>)> {
-        let precompiled_path = self.component_precompiled_path(component_id);
+        let wasm_bytes = tokio::fs::read(wasm_path)
+            .await
+            .context("Failed to read wasm file")?;
+        let digest = digest::sha256_digest(&wasm_bytes);
 
         // Try to load from precompiled cache first
-        if precompiled_path.exists() {
-            match unsafe { Component::deserialize_file(self.runtime.as_ref(), &precompiled_path) } {
-                Ok(component) => {
-                    debug!(component_id = %component_id, "Loaded component from precompiled cache");
-                    // Still need the wasm bytes for metadata/validation
-                    let wasm_bytes = tokio::fs::read(wasm_path)
-                        .await
-                        .context("Failed to read wasm file")?;
-                    return Ok((component, wasm_bytes));
-                }
-                Err(e) => {
-                    warn!(%component_id, error = %e, "Failed to load precompiled component, falling back to compilation");
-                }
-            }
+        if let Some(component) = self
+            .storage
+            .precompiled()
+            .load(self.runtime.as_ref(), &digest)
+            .await
+        {
+            debug!(component_id = %component_id, "Loaded component from precompiled cache");
+            return Ok((component, wasm_bytes));
         }
 
         // Fall back to compilation
-        let wasm_bytes = tokio::fs::read(wasm_path)
-            .await
-            .context("Failed to read wasm file")?;
-
         let component = Component::new(self.runtime.as_ref(), &wasm_bytes)
             .context("Failed to compile component")?;
 
-        // Save precompiled version for next time (async, don't block on this)
+        // Save precompiled version for next time
         if let Err(e) = self
-            .save_precompiled_component(component_id, &wasm_bytes)
+            .save_precompiled_component(component_id, &digest, &wasm_bytes)
             .await
         {
             warn!(%component_id, error = %e, "Failed to save precompiled component");
@@ -950,14 +1479,138 @@ impl LifecycleManager {
             .template_for_component(component_id)
             .await;
 
-        let wasi_state = policy_template.build()?;
-        let allowed_hosts = policy_template.allowed_hosts.clone();
+        Self::wasi_state_from_template(&policy_template)
+    }
+
+    fn wasi_state_from_template(
+        template: &WasiStateTemplate,
+    ) -> Result<(WassetteWasiState<WasiState>, Option<CustomResourceLimiter>)> {
+        let wasi_state = template.build()?;
+        let allowed_hosts = template.allowed_hosts.clone();
         let resource_limiter = wasi_state.resource_limiter.clone();
 
         let wassette_wasi_state = WassetteWasiState::new(wasi_state, allowed_hosts)?;
         Ok((wassette_wasi_state, resource_limiter))
     }
 
+    fn new_store(
+        &self,
+        state: WassetteWasiState<WasiState>,
+        resource_limiter: Option<&CustomResourceLimiter>,
+    ) -> Store<WassetteWasiState<WasiState>> {
+        let cpu_time_limit = state.inner.cpu_time_limit;
+        let mut store = Store::new(self.runtime.as_ref(), state);
+
+        // The engine interrupts guests at epoch deadlines, so every store needs one. Without a
+        // CPU time limit the deadline is too far away to ever be reached.
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline(cpu_time_limit.map_or(u64::MAX / 2, runtime_context::epoch_ticks));
+
+        // Apply memory limits if configured in the policy by setting up a limiter closure
+        // that extracts the resource limiter from the WasiState
+        if resource_limiter.is_some() {
+            store.limiter(|state: &mut WassetteWasiState<WasiState>| {
+                // Extract the resource limiter from the inner state
+                state
+                    .inner
+                    .resource_limiter
+                    .as_mut()
+                    .expect("Resource limiter should be present - checked above")
+            });
+        }
+
+        store
+    }
+
+    /// Explains why a call failed: a write an in-memory mount refused because of its quota, a
+    /// recorded permission denial with instructions for granting it, or otherwise the trap itself
+    fn call_failure(
+        store: &Store<WassetteWasiState<WasiState>>,
+        component_id: &str,
+        error: anyhow::Error,
+    ) -> anyhow::Error {
+        if let Some(exceeded) = store.data().inner.memory_quota_error() {
+            return exceeded.into();
+        }
+        if let Some(perm_error) = store.data().get_last_permission_error() {
+            return ComponentCallError::permission_denied(perm_error.to_user_message(component_id));
+        }
+        ComponentCallError::trap(&error)
+    }
+
+    /// Runs a WASI CLI command component with the arguments, stdin and environment described by
+    /// `parameters`, returning the exit code and captured output as a JSON string.
+    async fn execute_command_call(
+        &self,
+        component_id: &str,
+        component: &ComponentInstance,
+        parameters: &str,
+    ) -> Result<String> {
+        let request: CommandRequest =
+            serde_json::from_str(parameters).map_err(ComponentCallError::invalid_arguments)?;
+
+        let mut template = self
+            .policy_manager
+            .template_for_component(component_id)
+            .await
+            .as_ref()
+            .clone();
+        // Arguments from the policy come first; fall back to the component id as program name
+        if template.args.is_empty() {
+            template.args.push(component_id.to_string());
+        }
+        template.args.extend(request.args);
+        // Callers may only set variables the policy allows, and never replace a value that
+        // the policy or the component's secrets provide
+        for (key, value) in request.env {
+            if !template.allowed_env_keys.contains(&key) {
+                return Err(ComponentCallError::permission_denied(format!(
+                    "Environment variable '{key}' is not allowed by the policy of component '{component_id}'. Grant it with: wassette permission grant environment-variable {component_id} {key}"
+                )));
+            }
+            if template.config_vars.contains_key(&key) {
+                return Err(ComponentCallError::invalid_arguments(format!(
+                    "environment variable '{key}' is already set by the component's policy or secrets and cannot be overridden"
+                )));
+            }
+            template.config_vars.insert(key, value);
+        }
+        let io = CommandIo::new(request.stdin);
+        template.command_io = Some(io.clone());
+
+        let (state, resource_limiter) = Self::wasi_state_from_template(&template)?;
+        let mut store = self.new_store(state, resource_limiter.as_ref());
+
+        let command_pre =
+            wasmtime_wasi::p2::bindings::CommandPre::new(component.instance_pre.as_ref().clone())?;
+        let command = command_pre.instantiate_async(&mut store).await?;
+        store.data().clear_permission_errors();
+        let run_result = command.wasi_cli_run().call_run(&mut store).await;
+
+        let exit_code = match run_result {
+            Ok(Ok(())) => 0,
+            Ok(Err(())) => 1,
+            Err(e) => match e.downcast_ref::<wasmtime_wasi::I32Exit>() {
+                Some(exit) => exit.0,
+                None => return Err(Self::call_failure(&store, component_id, e)),
+            },
+        };
+        if exit_code != 0 {
+            if let Some(exceeded) = store.data().inner.memory_quota_error() {
+                return Err(exceeded.into());
+            }
+        }
+
+        let result = serde_json::json!({
+            "result": {
+                "exit_code": exit_code,
+                "stdout": String::from_utf8_lossy(&io.stdout.contents()),
+                "stderr": String::from_utf8_lossy(&io.stderr.contents()),
+            }
+        });
+        Ok(serde_json::to_string(&result)?)
+    }
+
     /// Executes a function call on a WebAssembly component
     #[instrument(skip(self))]
     pub async fn execute_component_call(
@@ -979,22 +1632,30 @@ impl LifecycleManager {
             .await
             .ok_or_else(|| anyhow!("Component not found: {}", component_id))?;
 
-        let (state, resource_limiter) = self.get_wasi_state_for_component(component_id).await?;
+        if let Some(command_tool) = &component.command_tool {
+            if function_name != command_tool {
+                bail!(
+                    "Unknown tool name: {}. Component {} is a WASI command and only provides {}",
+                    function_name,
+                    component_id,
+                    command_tool
+                );
+            }
+            let result = self
+                .execute_command_call(component_id, &component, parameters)
+                .await;
+            debug!(
+                component_id = %component_id,
+                function_name = %function_name,
+                total_duration_ms = %start_time.elapsed().as_millis(),
+                "WASI command execution completed"
+            );
+            return result;
+        }
 
-        let mut store = Store::new(self.runtime.as_ref(), state);
+        let (state, resource_limiter) = self.get_wasi_state_for_component(component_id).await?;
 
-        // Apply memory limits if configured in the policy by setting up a limiter closure
-        // that extracts the resource limiter from the WasiState
-        if resource_limiter.is_some() {
-            store.limiter(|state: &mut WassetteWasiState<WasiState>| {
-                // Extract the resource limiter from the inner state
-                state
-                    .inner
-                    .resource_limiter
-                    .as_mut()
-                    .expect("Resource limiter should be present - checked above")
-            });
-        }
+        let mut store = self.new_store(state, resource_limiter.as_ref());
 
         let instantiation_start = Instant::now();
         let instance = component.instance_pre.instantiate_async(&mut store).await?;
@@ -1051,29 +1712,41 @@ impl LifecycleManager {
                 .ok_or_else(|| anyhow!("Function not found: {}", func_name))?
         };
 
-        let params: serde_json::Value = serde_json::from_str(parameters)?;
-        let argument_vals = json_to_vals(&params, &func.params(&store))?;
+        let params: serde_json::Value =
+            serde_json::from_str(parameters).map_err(ComponentCallError::invalid_arguments)?;
+        let argument_vals = json_to_vals(&params, &func.params(&store))
+            .map_err(ComponentCallError::invalid_arguments)?;
 
         let mut results = create_placeholder_results(&func.results(&store));
 
         let execution_start = Instant::now();
 
         // Execute the WASM function and capture any errors
+        store.data().clear_permission_errors();
         let call_result = func
             .call_async(&mut store, &argument_vals, &mut results)
             .await;
 
         let execution_duration = execution_start.elapsed();
 
-        // If the call failed, check if it was due to a permission denial
         if let Err(e) = call_result {
-            // Check if there was a permission error recorded during execution
+            return Err(Self::call_failure(&store, component_id, e));
+        }
+
+        // Components usually turn a denied host call into an error result rather than a trap,
+        // so surface a recorded quota refusal or denial in that case too
+        if matches!(
+            results.first(),
+            Some(wasmtime::component::Val::Result(Err(_)))
+        ) {
+            if let Some(exceeded) = store.data().inner.memory_quota_error() {
+                return Err(exceeded.into());
+            }
             if let Some(perm_error) = store.data().get_last_permission_error() {
-                // Return a more informative error with instructions
-                return Err(anyhow!(perm_error.to_user_message(component_id)));
+                return Err(ComponentCallError::permission_denied(
+                    perm_error.to_user_message(component_id),
+                ));
             }
-            // Otherwise, return the original WASM execution error
-            return Err(e);
         }
 
         let result_json = vals_to_json(&results);
@@ -1169,46 +1842,31 @@ impl LifecycleManager {
                 continue;
             };
 
-            // Try to load cached metadata
-            if let Ok(Some(metadata)) = self.load_component_metadata(component_id).await {
-                // Validate that the component file hasn't changed
-                if ComponentStorage::validate_stamp(&entry_path, &metadata.validation_stamp).await {
-                    let tool_metadata: Vec<ToolMetadata> = metadata
-                        .function_identifiers
-                        .into_iter()
-                        .zip(metadata.tool_schemas)
-                        .zip(metadata.tool_names)
-                        .map(|((identifier, schema), normalized_name)| {
-                            let canonical = schema::canonicalize_output_schema(&schema);
-                            ToolMetadata {
-                                identifier,
-                                schema: canonical,
-                                normalized_name,
-                            }
-                        })
-                        .collect();
-
-                    match self
-                        .registry
-                        .register_metadata_if_absent(component_id, tool_metadata)
-                        .await
-                    {
-                        Ok(true) => {
-                            loaded_count += 1;
-                            debug!(component_id = %component_id, "Registered tools from cached metadata");
-                            continue;
-                        }
-                        Ok(false) => {
-                            debug!(component_id = %component_id, "Skipping cached metadata; component already registered");
-                            continue;
-                        }
-                        Err(e) => {
-                            warn!(%component_id, error = %e, "Failed to register tools from metadata");
-                            continue;
-                        }
-                    }
-                }
-            }
+            // Try to load cached metadata, which may have been recorded for another component
+            // with the same bytes
+            if let Ok(Some(metadata)) = self.cached_metadata_for(component_id, &entry_path).await {
+                let tool_metadata = tool_metadata_from_cache(metadata);
+
+                match self
+                    .registry
+                    .register_metadata_if_absent(component_id, tool_metadata)
+                    .await
+                {
+                    Ok(true) => {
+                        loaded_count += 1;
+                        debug!(component_id = %component_id, "Registered tools from cached metadata");
+                        continue;
+                    }
+                    Ok(false) => {
+                        debug!(component_id = %component_id, "Skipping cached metadata; component already registered");
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!(%component_id, error = %e, "Failed to register tools from metadata");
+                        continue;
+                    }
+                }
+            }
 
             debug!(component_id = %component_id, "No valid cached metadata found, will load component later");
         }
@@ -1223,6 +1881,25 @@ impl LifecycleManager {
         Ok(())
     }
 
+    /// Cached metadata that still describes the component file at `entry_path`
+    async fn cached_metadata_for(
+        &self,
+        component_id: &str,
+        entry_path: &Path,
+    ) -> Result<Option<ComponentMetadata>> {
+        // Stamps written by earlier releases carry no hash; validate them by size and mtime
+        if let Some(metadata) = self.load_component_metadata(component_id).await? {
+            if metadata.validation_stamp.content_hash.is_none() {
+                let valid =
+                    ComponentStorage::validate_stamp(entry_path, &metadata.validation_stamp).await;
+                return Ok(valid.then_some(metadata));
+            }
+        }
+
+        let digest = digest::file_sha256_digest(entry_path).await?;
+        self.storage.find_metadata(component_id, &digest).await
+    }
+
     /// Load a component from directory entry with optimization
     async fn load_component_from_entry_optimized(&self, entry: DirEntry) -> Result<bool> {
         let entry_path = entry.path();
@@ -1251,7 +1928,7 @@ impl LifecycleManager {
         }
 
         let start_time = Instant::now();
-        self.compile_and_register_component(&component_id, &entry_path)
+        self.compile_and_register_component(&component_id, &entry_path, None)
             .await
             .with_context(|| {
                 format!(
@@ -1266,6 +1943,25 @@ impl LifecycleManager {
 
     // Granular permission system methods
 }
+
+/// Rebuild the tool metadata recorded in a component's cached metadata
+fn tool_metadata_from_cache(metadata: ComponentMetadata) -> Vec<ToolMetadata> {
+    metadata
+        .function_identifiers
+        .into_iter()
+        .zip(metadata.tool_schemas)
+        .zip(metadata.tool_names)
+        .map(|((identifier, schema), normalized_name)| {
+            let canonical = schema::canonicalize_output_schema(&schema);
+            ToolMetadata {
+                identifier,
+                schema: canonical,
+                normalized_name,
+            }
+        })
+        .collect()
+}
+
 // Load components in parallel for improved startup performance
 async fn load_components_parallel(
     component_dir: &Path,
@@ -1398,6 +2094,7 @@ async fn load_component_from_entry(
     let instance_pre = runtime.instantiate_pre(&component)?;
     Ok(Some((
         ComponentInstance {
+            command_tool: command_tool_name(&component, runtime.as_ref()),
             component: Arc::new(component),
             instance_pre: Arc::new(instance_pre),
             package_docs,
@@ -1424,6 +2121,7 @@ mod tests {
     pub(crate) struct TestLifecycleManager {
         pub manager: LifecycleManager,
         _tempdir: tempfile::TempDir,
+        _secrets_dir: tempfile::TempDir,
     }
 
     impl TestLifecycleManager {
@@ -1448,13 +2146,130 @@ mod tests {
 
     pub(crate) async fn create_test_manager() -> Result<TestLifecycleManager> {
         let tempdir = tempfile::tempdir()?;
-        let manager = LifecycleManager::new(&tempdir).await?;
+        let secrets_dir = tempfile::tempdir()?;
+        let manager = LifecycleManager::builder(tempdir.path())
+            .with_secrets_dir(secrets_dir.path())
+            .build()
+            .await?;
         Ok(TestLifecycleManager {
             manager,
             _tempdir: tempdir,
+            _secrets_dir: secrets_dir,
         })
     }
 
+    #[test(tokio::test)]
+    async fn test_component_schema_from_metadata_keeps_tool_names() -> Result<()> {
+        let manager = create_test_manager().await?;
+        manager.load_test_component().await?;
+
+        // A manager that has not compiled the component falls back to the stored metadata
+        let unloaded = LifecycleManager::new_unloaded(manager.component_root()).await?;
+        assert!(unloaded.get_component(TEST_COMPONENT_ID).await.is_none());
+        let schema = unloaded
+            .get_component_schema(TEST_COMPONENT_ID)
+            .await
+            .expect("schema from metadata");
+
+        let tools = schema["tools"].as_array().expect("tools array");
+        assert!(!tools.is_empty());
+        for tool in tools {
+            assert!(tool["name"].is_string(), "tool without a name: {tool}");
+            assert!(
+                tool["inputSchema"].is_object(),
+                "tool without input schema: {tool}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_component_source_uri() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_example_component().await?;
+        let uri = format!("file://{}", component_path.display());
+        manager.load_component(&uri).await?;
+
+        assert_eq!(
+            manager.get_component_source_uri(TEST_COMPONENT_ID).await,
+            Some(uri.clone())
+        );
+
+        // Loading from the component directory keeps the recorded URI
+        let reloaded = LifecycleManager::new(manager.component_root()).await?;
+        assert_eq!(
+            reloaded.get_component_source_uri(TEST_COMPONENT_ID).await,
+            Some(uri)
+        );
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_fetch_policy() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let policy_dir = tempfile::tempdir()?;
+        let policy_path = policy_dir.path().join("shared.policy.yaml");
+        tokio::fs::write(
+            &policy_path,
+            "version: \"1.0\"\npermissions:\n  network:\n    allow:\n      - host: api.example.com\n",
+        )
+        .await?;
+
+        let policy = manager
+            .fetch_policy(&format!("file://{}", policy_path.display()))
+            .await?;
+        assert!(policy.permissions.network.is_some());
+
+        // Fetching does not attach the policy to anything
+        assert!(manager.get_policy_info(TEST_COMPONENT_ID).await.is_none());
+
+        assert!(manager
+            .fetch_policy(&format!(
+                "file://{}",
+                policy_dir.path().join("missing.yaml").display()
+            ))
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_cpu_time_limit_interrupts_guest() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component = Component::new(
+            manager.runtime.as_ref(),
+            r#"(component
+                (core module $m (func (export "spin") (loop $l (br $l))))
+                (core instance $i (instantiate $m))
+                (func (export "spin") (canon lift (core func $i "spin"))))"#,
+        )?;
+
+        let template = WasiStateTemplate {
+            cpu_time_limit: Some(std::time::Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let (state, resource_limiter) = LifecycleManager::wasi_state_from_template(&template)?;
+        let mut store = manager.new_store(state, resource_limiter.as_ref());
+        let instance = manager
+            .runtime
+            .instantiate_pre(&component)?
+            .instantiate_async(&mut store)
+            .await?;
+        let spin = instance.get_typed_func::<(), ()>(&mut store, "spin")?;
+
+        let err = spin
+            .call_async(&mut store, ())
+            .await
+            .expect_err("spinning guest should be interrupted");
+        assert_eq!(
+            err.downcast_ref::<wasmtime::Trap>(),
+            Some(&wasmtime::Trap::Interrupt)
+        );
+        let err = ComponentCallError::trap(&err);
+        assert!(err.to_string().contains("wall-clock time"));
+        Ok(())
+    }
+
     pub(crate) async fn build_example_component() -> Result<PathBuf> {
         let cwd = std::env::current_dir()?;
         println!("CWD: {}", cwd.display());
@@ -1483,6 +2298,26 @@ mod tests {
         Ok(component_path)
     }
 
+    pub(crate) async fn build_echo_cli_component() -> Result<PathBuf> {
+        let cwd = std::env::current_dir()?;
+        let component_path =
+            cwd.join("../../examples/echo-cli-rs/target/wasm32-wasip2/release/echo_cli_rs.wasm");
+
+        if !component_path.exists() {
+            let status = Command::new("cargo")
+                .current_dir(cwd.join("../../examples/echo-cli-rs"))
+                .args(["build", "--release", "--target", "wasm32-wasip2"])
+                .status()
+                .context("Failed to execute cargo build")?;
+
+            if !status.success() {
+                anyhow::bail!("Failed to compile echo-cli-rs component");
+            }
+        }
+
+        Ok(component_path)
+    }
+
     #[test(tokio::test)]
     async fn test_lifecycle_manager_tool_registry() -> Result<()> {
         let manager = create_test_manager().await?;
@@ -1584,6 +2419,127 @@ mod tests {
         Ok(())
     }
 
+    #[test(tokio::test)]
+    async fn test_component_versions_and_rollback() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_example_component().await?;
+        let v1_uri = format!("file://{}", component_path.display());
+
+        // A second version of the same component that only differs by a trailing custom section
+        let v2_dir = tempfile::tempdir()?;
+        let v2_path = v2_dir.path().join(format!("{TEST_COMPONENT_ID}.wasm"));
+        let mut v2_bytes = std::fs::read(&component_path)?;
+        v2_bytes.extend_from_slice(&[0x00, 0x03, 0x02, b'v', b'2']);
+        std::fs::write(&v2_path, &v2_bytes)?;
+        let v2_uri = format!("file://{}", v2_path.display());
+
+        let v1 = manager.load_component(&v1_uri).await?;
+        let v2 = manager.load_component(&v2_uri).await?;
+        assert_eq!(v2.status, LoadResult::Replaced);
+
+        let versions = manager.list_component_versions(TEST_COMPONENT_ID).await?;
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].active);
+        assert_eq!(versions[0].digest, v2.digest);
+        assert_eq!(versions[1].digest, v1.digest);
+        assert_eq!(versions[1].source_uri.as_deref(), Some(v1_uri.as_str()));
+
+        let restored = manager.rollback_component(TEST_COMPONENT_ID, None).await?;
+        assert_eq!(restored.digest, v1.digest);
+        assert_eq!(
+            manager.get_component_source_uri(TEST_COMPONENT_ID).await,
+            Some(v1_uri.clone())
+        );
+        manager.get_component_id_for_tool("fetch").await?;
+
+        // The rolled back version stays available, and reinstalling the active one is a no-op
+        manager.load_component(&v1_uri).await?;
+        let versions = manager.list_component_versions(TEST_COMPONENT_ID).await?;
+        let digests: Vec<&str> = versions.iter().map(|v| v.digest.as_str()).collect();
+        assert_eq!(digests, vec![v1.digest.as_str(), v2.digest.as_str()]);
+
+        manager.unload_component(TEST_COMPONENT_ID).await?;
+        assert!(!manager.storage.versions_dir(TEST_COMPONENT_ID).exists());
+        assert!(manager
+            .rollback_component(TEST_COMPONENT_ID, None)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_identical_components_share_storage() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_example_component().await?;
+        let copy_dir = tempfile::tempdir()?;
+        let copy_path = copy_dir.path().join("fetch-copy.wasm");
+        std::fs::copy(&component_path, &copy_path)?;
+
+        let first = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+        let second = manager
+            .load_component(&format!("file://{}", copy_path.display()))
+            .await?;
+        assert_eq!(first.digest, second.digest);
+
+        let blob_path = manager.storage.blob_path(&first.digest);
+        assert!(blob_path.exists());
+        let blobs = std::fs::read_dir(blob_path.parent().unwrap())?.count();
+        assert_eq!(blobs, 1, "identical bytes are stored once");
+
+        // The copy reuses the metadata recorded for the first component
+        let metadata = manager
+            .storage
+            .find_metadata("fetch-copy", &first.digest)
+            .await?
+            .expect("metadata for the copy");
+        assert_eq!(metadata.component_id, "fetch-copy");
+        assert!(metadata.validation_stamp.content_hash.is_some());
+
+        // The blob is only released once no component refers to it
+        manager.unload_component(TEST_COMPONENT_ID).await?;
+        assert!(blob_path.exists());
+        std::fs::read(manager.storage.component_path("fetch-copy"))?;
+        manager.unload_component("fetch-copy").await?;
+        assert!(!blob_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_component_update_status() {
+        let mut status = ComponentUpdateStatus {
+            component_id: "weather".to_string(),
+            source_uri: "oci://ghcr.io/example/weather:latest".to_string(),
+            current_digest: Some("sha256:ABC".to_string()),
+            latest_digest: "sha256:abc".to_string(),
+        };
+        assert!(!status.is_outdated());
+
+        status.latest_digest = "sha256:def".to_string();
+        assert!(status.is_outdated());
+
+        status.current_digest = None;
+        assert!(status.is_outdated());
+    }
+
+    #[test(tokio::test)]
+    async fn test_update_skips_components_not_from_oci_tags() -> Result<()> {
+        let manager = create_test_manager().await?;
+        manager.load_test_component().await?;
+
+        assert!(manager
+            .check_component_update(TEST_COMPONENT_ID)
+            .await?
+            .is_none());
+        assert!(manager.update_component(TEST_COMPONENT_ID).await?.is_none());
+        assert!(manager.check_component_update("missing").await.is_err());
+
+        Ok(())
+    }
+
     #[test(tokio::test)]
     async fn test_component_path_update() -> Result<()> {
         let manager = create_test_manager().await?;
@@ -1727,6 +2683,317 @@ permissions:
         Ok(())
     }
 
+    #[test(tokio::test)]
+    async fn test_command_component_exposed_as_single_tool() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_echo_cli_component().await?;
+        let outcome = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+
+        assert_eq!(outcome.tool_names.len(), 1);
+        let tool = &outcome.tool_names[0];
+        assert!(tool.starts_with("wasi_cli_run_"));
+
+        let schema = manager
+            .get_tool_schema_for_component(&outcome.component_id, tool)
+            .await
+            .expect("tool schema should be registered");
+        let properties = &schema["inputSchema"]["properties"];
+        assert_eq!(properties["args"]["type"], "array");
+        assert_eq!(properties["stdin"]["type"], "string");
+        assert_eq!(properties["env"]["type"], "object");
+
+        let err = manager
+            .execute_component_call(
+                &outcome.component_id,
+                "not_the_run_tool",
+                r#"{"args": [], "stdin": ""}"#,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown tool name"), "{err}");
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_execute_command_component_captures_output() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_echo_cli_component().await?;
+        let outcome = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+        let tool = &outcome.tool_names[0];
+        let env_details = serde_json::json!({"key": "ECHO_PREFIX"});
+        manager
+            .grant_permission(&outcome.component_id, "environment", &env_details)
+            .await?;
+
+        let output = manager
+            .execute_component_call(
+                &outcome.component_id,
+                tool,
+                r#"{"args": ["hello", "world"], "stdin": "from stdin\n", "env": {"ECHO_PREFIX": "prefix"}}"#,
+            )
+            .await?;
+        let result: Value = serde_json::from_str(&output)?;
+        assert_eq!(result["result"]["exit_code"], 0);
+        assert_eq!(result["result"]["stdout"], "hello world\nfrom stdin\n");
+        assert_eq!(result["result"]["stderr"], "prefix\n");
+
+        let output = manager
+            .execute_component_call(
+                &outcome.component_id,
+                tool,
+                r#"{"args": ["--fail"], "stdin": ""}"#,
+            )
+            .await?;
+        let result: Value = serde_json::from_str(&output)?;
+        assert_eq!(result["result"]["exit_code"], 1);
+        assert_eq!(result["result"]["stdout"], "\n");
+        assert_eq!(result["result"]["stderr"], "");
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_command_env_is_limited_to_policy() -> Result<()> {
+        let component_dir = tempfile::tempdir()?;
+        let secrets_dir = tempfile::tempdir()?;
+        let manager = LifecycleManager::builder(component_dir.path())
+            .with_secrets_dir(secrets_dir.path())
+            .build()
+            .await?;
+        let component_path = build_echo_cli_component().await?;
+        let outcome = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+        let component_id = &outcome.component_id;
+        let tool = &outcome.tool_names[0];
+
+        manager
+            .set_component_secrets(
+                component_id,
+                &[("ECHO_PREFIX".to_string(), "from secret".to_string())],
+            )
+            .await?;
+        let env_details = serde_json::json!({"key": "ECHO_PREFIX"});
+        manager
+            .grant_permission(component_id, "environment", &env_details)
+            .await?;
+
+        // A key the policy does not allow is rejected
+        let err = manager
+            .execute_component_call(
+                component_id,
+                tool,
+                r#"{"args": [], "stdin": "", "env": {"UNGRANTED": "value"}}"#,
+            )
+            .await
+            .unwrap_err();
+        let call_error = err.downcast_ref::<ComponentCallError>().unwrap();
+        assert_eq!(call_error.kind, CallErrorKind::PermissionDenied);
+        assert!(
+            call_error.message.contains("UNGRANTED"),
+            "{}",
+            call_error.message
+        );
+
+        // A value provided by the component's secrets cannot be replaced
+        let err = manager
+            .execute_component_call(
+                component_id,
+                tool,
+                r#"{"args": [], "stdin": "", "env": {"ECHO_PREFIX": "injected"}}"#,
+            )
+            .await
+            .unwrap_err();
+        let call_error = err.downcast_ref::<ComponentCallError>().unwrap();
+        assert_eq!(call_error.kind, CallErrorKind::InvalidArguments);
+        assert!(
+            call_error.message.contains("cannot be overridden"),
+            "{}",
+            call_error.message
+        );
+
+        let output = manager
+            .execute_component_call(component_id, tool, r#"{"args": [], "stdin": ""}"#)
+            .await?;
+        let result: Value = serde_json::from_str(&output)?;
+        assert_eq!(result["result"]["stderr"], "from secret\n");
+
+        Ok(())
+    }
+
+    pub(crate) async fn build_filesystem_component() -> Result<PathBuf> {
+        let cwd = std::env::current_dir()?;
+        let component_path =
+            cwd.join("../../examples/filesystem-rs/target/wasm32-wasip2/release/filesystem.wasm");
+
+        if !component_path.exists() {
+            let status = Command::new("cargo")
+                .current_dir(cwd.join("../../examples/filesystem-rs"))
+                .args(["build", "--release", "--target", "wasm32-wasip2"])
+                .status()
+                .context("Failed to execute cargo build")?;
+
+            if !status.success() {
+                anyhow::bail!("Failed to compile filesystem-rs component");
+            }
+        }
+
+        Ok(component_path)
+    }
+
+    #[test(tokio::test)]
+    async fn test_storage_denial_is_reported() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_filesystem_component().await?;
+        let outcome = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+        let write_tool = outcome
+            .tool_names
+            .iter()
+            .find(|name| name.contains("write"))
+            .expect("filesystem component exports a write tool");
+
+        let data_dir = tempfile::tempdir()?;
+        std::fs::write(data_dir.path().join("existing.txt"), "hello")?;
+        let policy_dir = tempfile::tempdir()?;
+        let policy_path = policy_dir.path().join("policy.yaml");
+        std::fs::write(
+            &policy_path,
+            format!(
+                r#"
+version: "1.0"
+description: "Read-only data directory"
+permissions:
+  storage:
+    allow:
+      - uri: "fs://{}"
+        access: ["read"]
+        mount: "/data"
+"#,
+                data_dir.path().display()
+            ),
+        )?;
+        manager
+            .attach_policy(
+                &outcome.component_id,
+                &format!("file://{}", policy_path.display()),
+            )
+            .await?;
+
+        let err = manager
+            .execute_component_call(
+                &outcome.component_id,
+                write_tool,
+                r#"{"path": "/data/new.txt", "content": "denied"}"#,
+            )
+            .await
+            .expect_err("writing to a read-only mount should be denied");
+        let message = err.to_string();
+        assert!(message.contains("Storage permission denied"), "{message}");
+        assert!(message.contains("/data/new.txt"), "{message}");
+        // The hint names the host directory to grant, not the guest mount
+        assert!(
+            message.contains(&format!(
+                "--uri=\"fs://{}\"",
+                data_dir.path().join("new.txt").display()
+            )),
+            "{message}"
+        );
+        assert!(message.contains("--access=\"write\""), "{message}");
+        assert!(!data_dir.path().join("new.txt").exists());
+        assert_eq!(
+            err.downcast_ref::<ComponentCallError>().map(|e| e.kind),
+            Some(CallErrorKind::PermissionDenied)
+        );
+
+        // Paths outside every granted directory are reported the same way
+        let outside_dir = tempfile::tempdir()?;
+        let outside = outside_dir.path().join("secret.txt");
+        std::fs::write(&outside, "secret")?;
+        let read_tool = outcome
+            .tool_names
+            .iter()
+            .find(|name| name.contains("read"))
+            .expect("filesystem component exports a read tool");
+        let err = manager
+            .execute_component_call(
+                &outcome.component_id,
+                read_tool,
+                &serde_json::json!({ "path": outside.display().to_string() }).to_string(),
+            )
+            .await
+            .expect_err("reading outside the granted directories should be denied");
+        let message = err.to_string();
+        assert!(message.contains("Storage permission denied"), "{message}");
+        assert!(
+            message.contains(&format!("--uri=\"fs://{}\"", outside.display())),
+            "{message}"
+        );
+        assert!(message.contains("--access=\"read\""), "{message}");
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_load_component_with_digest() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_example_component().await?;
+        let uri = format!("file://{}", component_path.display());
+        let expected = sha256_digest(&std::fs::read(&component_path)?);
+
+        let err = manager
+            .load_component_with_digest(&uri, &format!("sha256:{}", "0".repeat(64)))
+            .await
+            .expect_err("a wrong digest must be rejected");
+        assert!(err.to_string().contains("Digest mismatch"), "{err}");
+        assert!(!manager.component_path(TEST_COMPONENT_ID).exists());
+        assert!(manager.get_component(TEST_COMPONENT_ID).await.is_none());
+
+        let outcome = manager
+            .load_component_with_digest(&uri, &expected.to_uppercase().replace("SHA256", "sha256"))
+            .await?;
+        assert_eq!(outcome.component_id, TEST_COMPONENT_ID);
+        assert_eq!(outcome.digest, expected);
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_invalid_arguments_are_classified() -> Result<()> {
+        let manager = create_test_manager().await?;
+        let component_path = build_filesystem_component().await?;
+        let outcome = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await?;
+        let write_tool = outcome
+            .tool_names
+            .iter()
+            .find(|name| name.contains("write"))
+            .expect("filesystem component exports a write tool");
+
+        let err = manager
+            .execute_component_call(
+                &outcome.component_id,
+                write_tool,
+                r#"{"path": 42, "content": "oops"}"#,
+            )
+            .await
+            .expect_err("a number is not a valid path");
+        let call_error = err
+            .downcast_ref::<ComponentCallError>()
+            .expect("argument errors are classified");
+        assert_eq!(call_error.kind, CallErrorKind::InvalidArguments);
+        assert_eq!(call_error.kind.code(), "invalid_arguments");
+
+        Ok(())
+    }
+
     #[test(tokio::test)]
     async fn test_wasi_state_template_allowed_hosts() -> Result<()> {
         // Test that WasiStateTemplate correctly stores allowed hosts from policy
@@ -1947,6 +3214,99 @@ permissions:
         Ok(())
     }
 
+    #[test(tokio::test)]
+    async fn test_integrity_tracks_changes_made_by_wassette() -> Result<()> {
+        let manager = create_test_manager().await?;
+        manager.load_test_component().await?;
+
+        let details = serde_json::json!({"host": "api.example.com"});
+        manager
+            .grant_permission(TEST_COMPONENT_ID, "network", &details)
+            .await?;
+        assert!(manager.verify_integrity().await?.is_empty());
+
+        // A policy edited by hand is reported
+        let policy_path = manager.get_component_policy_path(TEST_COMPONENT_ID);
+        let policy = tokio::fs::read_to_string(&policy_path).await?;
+        tokio::fs::write(&policy_path, policy.replace("api.example.com", "*")).await?;
+        let violations = manager.verify_integrity().await?;
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].artifact, TamperedArtifact::Policy);
+
+        manager.quarantine_component(TEST_COMPONENT_ID).await?;
+        assert!(manager.list_components().await.is_empty());
+        assert!(!policy_path.exists());
+        assert!(manager.verify_integrity().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn test_trust_policy_restricts_sources() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let component_path = build_example_component().await?;
+        let trusted_dir = tempfile::tempdir()?;
+        let manager = LifecycleManager::builder(tempdir.path())
+            .with_trust_policy(TrustPolicy {
+                local: Some(vec![trusted_dir.path().to_path_buf()]),
+                ..Default::default()
+            })
+            .build()
+            .await?;
+
+        let err = manager
+            .load_component(&format!("file://{}", component_path.display()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("trust.local"), "{err}");
+        assert!(manager.list_components().await.is_empty());
+
+        let trusted_path = trusted_dir.path().join("fetch_rs.wasm");
+        std::fs::copy(&component_path, &trusted_path)?;
+        manager
+            .load_component(&format!("file://{}", trusted_path.display()))
+            .await?;
+
+        // Policies are subject to the same allowlist
+        let policy_dir = tempfile::tempdir()?;
+        let policy_path = policy_dir.path().join("policy.yaml");
+        std::fs::write(&policy_path, "version: \"1.0\"\npermissions: {}\n")?;
+        let err = manager
+            .attach_policy(
+                TEST_COMPONENT_ID,
+                &format!("file://{}", policy_path.display()),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Untrusted policy source"), "{err}");
+
+        // Policies wassette builds itself are checked by the source that declared them
+        let policy = PolicyParser::parse_file(&policy_path)?;
+        let err = manager
+            .attach_policy_document(
+                TEST_COMPONENT_ID,
+                &policy,
+                Some(&format!("file://{}", policy_path.display())),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Untrusted policy source"), "{err}");
+        assert!(manager.get_policy_info(TEST_COMPONENT_ID).await.is_none());
+
+        let trusted_source = trusted_dir.path().join("wassette.yaml");
+        std::fs::write(&trusted_source, "components: []\n")?;
+        manager
+            .attach_policy_document(
+                TEST_COMPONENT_ID,
+                &policy,
+                Some(&format!("file://{}", trusted_source.display())),
+            )
+            .await?;
+        assert!(manager.get_policy_info(TEST_COMPONENT_ID).await.is_some());
+
+        Ok(())
+    }
+
     #[test(tokio::test)]
     async fn test_set_secrets_component_not_found() -> Result<()> {
         let manager = create_test_manager().await?;
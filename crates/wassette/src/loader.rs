@@ -10,6 +10,8 @@ use tokio::fs::metadata;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info, warn};
 
+use crate::trust::TrustPolicy;
+
 /// Represents a downloaded resource, either from a local file or a temporary one.
 pub enum DownloadedResource {
     Local(PathBuf),
@@ -286,12 +288,12 @@ impl Loadable for ComponentResource {
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
-            bail!(
-                "Failed to download component from URL: {}. Status code: {}\nBody: {}",
-                url,
-                status,
-                body
-            );
+            return Err(HttpStatusError {
+                url: url.to_string(),
+                status: status.as_u16(),
+                body,
+            }
+            .into());
         }
         let name = resp
             .url()
@@ -313,6 +315,33 @@ impl Loadable for ComponentResource {
     }
 }
 
+/// A component download answered with a non-success HTTP status
+#[derive(Debug)]
+pub struct HttpStatusError {
+    /// URL that was requested
+    pub url: String,
+    /// HTTP status code of the response
+    pub status: u16,
+    /// Response body, for diagnostics
+    pub body: String,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to download component from URL: {}. Status code: {}\nBody: {}",
+            self.url,
+            reqwest::StatusCode::from_u16(self.status)
+                .map(|s| s.to_string())
+                .unwrap_or_else(|_| self.status.to_string()),
+            self.body
+        )
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
 /// Loadable implementation for policies
 pub struct PolicyResource;
 
@@ -334,11 +363,30 @@ impl Loadable for PolicyResource {
     }
 
     async fn from_oci_reference_with_progress(
-        _reference: &str,
-        _oci_client: &oci_client::Client,
-        _show_progress: bool,
+        reference: &str,
+        oci_client: &oci_client::Client,
+        show_progress: bool,
     ) -> Result<DownloadedResource> {
-        bail!("OCI references are not supported for policy resources. Use 'file://' or 'https://' schemes instead.")
+        let reference: oci_client::Reference =
+            reference.parse().context("Failed to parse OCI reference")?;
+
+        if show_progress {
+            eprintln!("Downloading policy from {}...", reference);
+        }
+
+        let policy_data = crate::oci_multi_layer::pull_policy_artifact(&reference, oci_client)
+            .await
+            .with_context(|| format!("Failed to pull policy from {reference}"))?;
+
+        let temp_file_name = format!("policy-{}", reference.repository().replace('/', "_"));
+        let (downloaded_resource, mut temp_file) =
+            DownloadedResource::new_temp_file(&temp_file_name, Self::FILE_EXTENSION).await?;
+        temp_file.write_all(&policy_data).await?;
+        temp_file.flush().await?;
+        temp_file.sync_all().await?;
+        drop(temp_file);
+
+        Ok(downloaded_resource)
     }
 
     async fn from_url(url: &str, http_client: &reqwest::Client) -> Result<DownloadedResource> {
@@ -374,13 +422,50 @@ impl Loadable for PolicyResource {
     }
 }
 
-/// Generic resource loading function
+/// Returns the ID a component loaded from `uri` will be installed under, without downloading it.
+///
+/// For `https://` URIs this is derived from the last path segment of the URI as written; a
+/// download that is redirected elsewhere is named after the final URL instead.
+pub fn component_id_from_uri(uri: &str) -> Result<String> {
+    let uri = uri.trim();
+    let (scheme, reference) = uri
+        .split_once("://")
+        .context("Invalid component reference. Should be of the form scheme://reference")?;
+
+    let id = match scheme {
+        "file" => Path::new(reference)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_string),
+        "oci" => {
+            let reference: oci_client::Reference =
+                reference.parse().context("Failed to parse OCI reference")?;
+            Some(reference.repository().replace('/', "_"))
+        }
+        "https" => reqwest::Url::parse(uri)
+            .context("Failed to parse component URL")?
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                name.trim_end_matches(&format!(".{}", ComponentResource::FILE_EXTENSION))
+                    .to_string()
+            }),
+        _ => bail!("Unsupported component scheme: {}", scheme),
+    };
+
+    id.with_context(|| format!("Failed to derive component ID from URI: {uri}"))
+}
+
+/// Generic resource loading function. Sources the trust policy does not allow are rejected before
+/// anything is fetched.
 pub(crate) async fn load_resource<T: Loadable>(
     uri: &str,
     oci_client: &oci_wasm::WasmClient,
     http_client: &reqwest::Client,
+    trust: &TrustPolicy,
 ) -> Result<DownloadedResource> {
-    load_resource_with_progress::<T>(uri, oci_client, http_client, false).await
+    load_resource_with_progress::<T>(uri, oci_client, http_client, trust, false).await
 }
 
 /// Generic resource loading function with optional progress reporting
@@ -388,9 +473,11 @@ pub(crate) async fn load_resource_with_progress<T: Loadable>(
     uri: &str,
     oci_client: &oci_wasm::WasmClient,
     http_client: &reqwest::Client,
+    trust: &TrustPolicy,
     show_progress: bool,
 ) -> Result<DownloadedResource> {
     let uri = uri.trim();
+    trust.check_source::<T>(uri)?;
     let error_message = format!(
         "Invalid {} reference. Should be of the form scheme://reference",
         T::RESOURCE_TYPE
@@ -409,6 +496,24 @@ pub(crate) async fn load_resource_with_progress<T: Loadable>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_component_id_from_uri() {
+        assert_eq!(
+            component_id_from_uri("file:///opt/components/fetch_rs.wasm").unwrap(),
+            "fetch_rs"
+        );
+        assert_eq!(
+            component_id_from_uri("oci://ghcr.io/microsoft/get-weather-js:1.2.3").unwrap(),
+            "microsoft_get-weather-js"
+        );
+        assert_eq!(
+            component_id_from_uri("https://example.com/releases/time.wasm").unwrap(),
+            "time"
+        );
+        assert!(component_id_from_uri("https://example.com/").is_err());
+        assert!(component_id_from_uri("ftp://example.com/time.wasm").is_err());
+    }
+
     #[test]
     fn test_load_resource_with_progress_api_exists() {
         // Compile-time test to verify the progress-aware API exists
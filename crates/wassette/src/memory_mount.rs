@@ -0,0 +1,407 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Ephemeral scratch directories backing `mem://` storage mounts
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use policy::{MemoryScope, PolicyDocument};
+use tempfile::TempDir;
+use tracing::warn;
+use wasmtime_wasi::p2::{DynOutputStream, OutputStream, Pollable, StreamError, StreamResult};
+
+use crate::wasistate::calculate_permissions;
+
+const SCRATCH_PREFIX: &str = "wassette-mem-";
+
+/// Memory-backed filesystem used for scratch directories when the host provides one
+#[cfg(target_os = "linux")]
+const SHM_DIR: Option<&str> = Some("/dev/shm");
+#[cfg(not(target_os = "linux"))]
+const SHM_DIR: Option<&str> = None;
+
+/// `ENOSPC` on Linux, the only platform with in-memory mounts. Writes refused because of the
+/// quota fail with it so guests see an ordinary "no space left on device" error.
+const ENOSPC: i32 = 28;
+
+/// An in-memory directory mounted into the guest at `guest_path`
+#[derive(Clone)]
+pub struct MemoryMount {
+    pub guest_path: String,
+    pub dir_perms: wasmtime_wasi::DirPerms,
+    pub file_perms: wasmtime_wasi::FilePerms,
+    /// Maximum number of bytes the mount may hold
+    pub quota: Option<u64>,
+    /// Directory shared by every invocation; `None` for per-invocation mounts
+    pub session_dir: Option<Arc<ScratchDir>>,
+}
+
+impl MemoryMount {
+    /// Returns the directory to preopen for a new invocation
+    pub(crate) fn dir_for_invocation(&self) -> Result<Arc<ScratchDir>> {
+        match &self.session_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(Arc::new(ScratchDir::new()?)),
+        }
+    }
+}
+
+/// A scratch directory on the memory-backed filesystem, together with the number of bytes
+/// stored in it
+pub struct ScratchDir {
+    dir: TempDir,
+    /// Bytes stored in the directory, or `None` after something was removed until the
+    /// directory is counted again
+    used: Mutex<Option<u64>>,
+}
+
+impl ScratchDir {
+    /// Creates a scratch directory on the memory-backed filesystem. Fails on hosts without
+    /// one, so a `mem://` mount never ends up on disk.
+    pub(crate) fn new() -> Result<Self> {
+        let Some(shm) = SHM_DIR else {
+            bail!("In-memory mounts need a memory-backed filesystem, which this host does not provide");
+        };
+        let dir = tempfile::Builder::new()
+            .prefix(SCRATCH_PREFIX)
+            .tempdir_in(Path::new(shm))
+            .with_context(|| format!("Failed to create in-memory mount directory in {shm}"))?;
+        Ok(Self {
+            dir,
+            used: Mutex::new(Some(0)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Error recorded when a write to an in-memory mount is refused because of its quota
+#[derive(Debug, Clone)]
+pub struct MemoryQuotaExceeded {
+    /// Guest path of the mount
+    pub guest_path: String,
+    /// Number of bytes the mount would have held after the write
+    pub used: u64,
+    /// Maximum number of bytes the mount may hold
+    pub quota: u64,
+}
+
+impl fmt::Display for MemoryQuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "In-memory mount '{}' would have held {} bytes, exceeding its quota of {} bytes; the write was refused",
+            self.guest_path, self.used, self.quota
+        )
+    }
+}
+
+impl std::error::Error for MemoryQuotaExceeded {}
+
+/// A scratch directory handed to a single store, kept alive for as long as the store is
+#[derive(Clone)]
+pub struct ActiveMemoryMount {
+    pub guest_path: String,
+    pub quota: Option<u64>,
+    pub dir: Arc<ScratchDir>,
+    /// The last write this store had refused because of the quota
+    pub exceeded: Arc<Mutex<Option<MemoryQuotaExceeded>>>,
+}
+
+impl ActiveMemoryMount {
+    /// Accounts for a write that grows the mount by `growth` bytes. Returns `false`, and
+    /// records the refusal, if the mount would exceed its quota.
+    pub(crate) fn reserve(&self, growth: u64) -> bool {
+        let Some(quota) = self.quota else {
+            return true;
+        };
+
+        let mut used = self.dir.used.lock().unwrap_or_else(|e| e.into_inner());
+        let current = match *used {
+            Some(current) => current,
+            None => match dir_usage(self.dir.path()) {
+                Ok(current) => current,
+                Err(e) => {
+                    warn!(mount = %self.guest_path, error = %e, "Failed to measure in-memory mount");
+                    return false;
+                }
+            },
+        };
+
+        let wanted = current.saturating_add(growth);
+        if wanted > quota {
+            *used = Some(current);
+            if let Ok(mut exceeded) = self.exceeded.lock() {
+                *exceeded = Some(MemoryQuotaExceeded {
+                    guest_path: self.guest_path.clone(),
+                    used: wanted,
+                    quota,
+                });
+            }
+            return false;
+        }
+        *used = Some(wanted);
+        true
+    }
+
+    /// Forgets the number of bytes stored after the guest removed or shrank something, or a
+    /// write failed; the mount is counted again before the next write
+    pub(crate) fn release(&self) {
+        if self.quota.is_some() {
+            *self.dir.used.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+
+    /// The last write refused because of the quota, if any
+    pub(crate) fn quota_error(&self) -> Option<MemoryQuotaExceeded> {
+        self.exceeded.lock().ok().and_then(|e| e.clone())
+    }
+}
+
+/// The error guests see when a write is refused because of the quota
+pub(crate) fn no_space() -> std::io::Error {
+    std::io::Error::from_raw_os_error(ENOSPC)
+}
+
+/// An output stream into a file on an in-memory mount that counts the bytes it adds to the file
+/// against the mount's quota
+pub(crate) struct QuotaOutputStream {
+    inner: DynOutputStream,
+    mount: ActiveMemoryMount,
+    /// Offset of the next write, or `None` for a stream that appends
+    position: Option<u64>,
+    /// Size of the file as far as this stream knows
+    end: u64,
+}
+
+impl QuotaOutputStream {
+    /// Wraps `inner`, which writes at `position` (or appends) to a file of `size` bytes
+    pub(crate) fn new(
+        inner: DynOutputStream,
+        mount: ActiveMemoryMount,
+        position: Option<u64>,
+        size: u64,
+    ) -> Self {
+        Self {
+            inner,
+            mount,
+            position,
+            end: size,
+        }
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl Pollable for QuotaOutputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl OutputStream for QuotaOutputStream {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        let len = bytes.len() as u64;
+        let start = self.position.unwrap_or(self.end);
+        let end = self.end.max(start.saturating_add(len));
+        if !self.mount.reserve(end - self.end) {
+            return Err(StreamError::LastOperationFailed(no_space().into()));
+        }
+        if let Err(e) = self.inner.write(bytes) {
+            self.mount.release();
+            return Err(e);
+        }
+        if let Some(position) = &mut self.position {
+            *position = start + len;
+        }
+        self.end = end;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        self.inner.flush()
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        self.inner.check_write()
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await
+    }
+}
+
+/// Builds the `mem://` mounts described by the policy. Session-scoped mounts get their
+/// directory immediately so it can be shared by every store built from the template.
+pub(crate) fn extract_memory_mounts(policy: &PolicyDocument) -> Result<Vec<MemoryMount>> {
+    let mut mounts = Vec::new();
+    let Some(allow) = policy
+        .permissions
+        .storage
+        .as_ref()
+        .and_then(|s| s.allow.as_ref())
+    else {
+        return Ok(mounts);
+    };
+
+    for storage_permission in allow {
+        let Some(path) = storage_permission.uri.strip_prefix("mem://") else {
+            continue;
+        };
+        let (file_perms, dir_perms) = calculate_permissions(&storage_permission.access);
+        let options = storage_permission.memory.clone().unwrap_or_default();
+        let quota = options.quota.as_ref().map(|q| q.to_bytes()).transpose()?;
+        let session_dir = match options.scope {
+            MemoryScope::Session => Some(Arc::new(ScratchDir::new()?)),
+            MemoryScope::Invocation => None,
+        };
+
+        mounts.push(MemoryMount {
+            guest_path: storage_permission
+                .mount
+                .clone()
+                .unwrap_or_else(|| path.to_string()),
+            dir_perms,
+            file_perms,
+            quota,
+            session_dir,
+        });
+    }
+
+    Ok(mounts)
+}
+
+fn dir_usage(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            dir_usage(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use policy::PolicyParser;
+
+    use super::*;
+
+    fn policy_with_mounts() -> PolicyDocument {
+        PolicyParser::parse_str(
+            r#"
+version: "1.0"
+description: "In-memory scratch space"
+permissions:
+  storage:
+    allow:
+      - uri: "mem://tmp"
+        access: ["read", "write"]
+      - uri: "mem://cache"
+        access: ["read", "write"]
+        memory:
+          quota: "1Ki"
+          scope: session
+      - uri: "fs://data"
+        access: ["read"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_memory_mounts() {
+        let mounts = extract_memory_mounts(&policy_with_mounts()).unwrap();
+        assert_eq!(mounts.len(), 2);
+
+        assert_eq!(mounts[0].guest_path, "tmp");
+        assert_eq!(mounts[0].quota, None);
+        assert!(mounts[0].session_dir.is_none());
+
+        assert_eq!(mounts[1].guest_path, "cache");
+        assert_eq!(mounts[1].quota, Some(1024));
+        assert!(mounts[1].session_dir.is_some());
+    }
+
+    #[test]
+    fn test_invocation_and_session_dirs() {
+        let mounts = extract_memory_mounts(&policy_with_mounts()).unwrap();
+
+        let first = mounts[0].dir_for_invocation().unwrap();
+        let second = mounts[0].dir_for_invocation().unwrap();
+        assert_ne!(first.path(), second.path());
+
+        let first = mounts[1].dir_for_invocation().unwrap();
+        let second = mounts[1].dir_for_invocation().unwrap();
+        assert_eq!(first.path(), second.path());
+    }
+
+    fn active_mount(quota: u64) -> ActiveMemoryMount {
+        ActiveMemoryMount {
+            guest_path: "cache".to_string(),
+            quota: Some(quota),
+            dir: Arc::new(ScratchDir::new().unwrap()),
+            exceeded: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_quota_is_checked_when_reserving() {
+        let mount = active_mount(16);
+        assert!(mount.reserve(10));
+        assert!(mount.quota_error().is_none());
+
+        assert!(!mount.reserve(10));
+        let exceeded = mount.quota_error().unwrap();
+        assert_eq!(exceeded.used, 20);
+        assert!(exceeded.to_string().contains("exceeding its quota"));
+
+        // Released space is found by counting the directory again
+        std::fs::create_dir(mount.dir.path().join("nested")).unwrap();
+        std::fs::write(mount.dir.path().join("nested/data"), b"0123").unwrap();
+        mount.release();
+        assert!(mount.reserve(12));
+        assert!(!mount.reserve(1));
+    }
+
+    #[tokio::test]
+    async fn test_quota_output_stream_counts_growth() {
+        let mount = active_mount(16);
+        let pipe = wasmtime_wasi::p2::pipe::MemoryOutputPipe::new(1024);
+
+        // Overwriting the start of an 8 byte file only grows it by what extends past its end
+        assert!(mount.reserve(8));
+        let mut stream = QuotaOutputStream::new(Box::new(pipe.clone()), mount.clone(), Some(0), 8);
+        stream.write(Bytes::from_static(b"01234567")).unwrap();
+        stream.write(Bytes::from_static(b"89abcdef")).unwrap();
+        assert!(stream.write(Bytes::from_static(b"g")).is_err());
+        assert_eq!(mount.quota_error().unwrap().used, 17);
+
+        // Appending always grows the file
+        let mount = active_mount(4);
+        let mut stream = QuotaOutputStream::new(Box::new(pipe.clone()), mount.clone(), None, 0);
+        stream.write(Bytes::from_static(b"0123")).unwrap();
+        assert!(stream.write(Bytes::from_static(b"4")).is_err());
+        assert_eq!(pipe.contents().len(), 20);
+    }
+
+    #[test]
+    fn test_scratch_dir_removed_on_drop() {
+        let mounts = extract_memory_mounts(&policy_with_mounts()).unwrap();
+        let dir = mounts[0].dir_for_invocation().unwrap();
+        let path = dir.path().to_path_buf();
+        assert!(path.is_dir());
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
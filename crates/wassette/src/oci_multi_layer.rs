@@ -274,6 +274,44 @@ pub async fn pull_multi_layer_artifact_with_progress(
     })
 }
 
+/// Pull a policy published as an OCI artifact, returning the first policy layer
+pub async fn pull_policy_artifact(reference: &Reference, client: &Client) -> Result<Vec<u8>> {
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+
+    info!("Pulling OCI policy manifest: {}", reference);
+    let (manifest, _) = client
+        .pull_manifest(reference, &auth)
+        .await
+        .context("Failed to pull OCI manifest")?;
+
+    let image_manifest = match manifest {
+        oci_client::manifest::OciManifest::Image(manifest) => manifest,
+        _ => bail!("Unexpected manifest format - expected OCI Image Manifest"),
+    };
+
+    let layer = find_policy_layer(&image_manifest.layers)
+        .ok_or_else(|| anyhow::anyhow!("No policy layer found in OCI artifact {reference}"))?;
+
+    let mut policy_data = Vec::new();
+    client
+        .pull_blob(reference, layer.digest.as_str(), &mut policy_data)
+        .await
+        .context("Failed to pull policy layer")?;
+    verify_digest(&policy_data, &layer.digest)
+        .context("Policy layer digest verification failed")?;
+
+    Ok(policy_data)
+}
+
+/// The first layer carrying a policy
+fn find_policy_layer(
+    layers: &[oci_client::manifest::OciDescriptor],
+) -> Option<&oci_client::manifest::OciDescriptor> {
+    layers
+        .iter()
+        .find(|layer| POLICY_MEDIA_TYPES.contains(&layer.media_type.as_str()))
+}
+
 /// Pull just the WASM component from a multi-layer OCI artifact
 /// This is a compatibility function that ignores non-WASM layers
 pub async fn pull_wasm_only(reference: &Reference, client: &Client) -> Result<Vec<u8>> {
@@ -348,6 +386,23 @@ mod tests {
         assert_eq!(CONFIG_MEDIA_TYPE, "application/vnd.wasm.config.v0+json");
     }
 
+    #[test]
+    fn test_find_policy_layer() {
+        let layer = |media_type: &str, digest: &str| oci_client::manifest::OciDescriptor {
+            media_type: media_type.to_string(),
+            digest: digest.to_string(),
+            ..Default::default()
+        };
+        let layers = vec![
+            layer("application/wasm", "sha256:wasm"),
+            layer("application/vnd.wasm.policy.v1+yaml", "sha256:policy"),
+            layer("text/yaml", "sha256:other"),
+        ];
+
+        assert_eq!(find_policy_layer(&layers).unwrap().digest, "sha256:policy");
+        assert!(find_policy_layer(&layers[..1]).is_none());
+    }
+
     #[test]
     fn test_wasm_config_serialization() {
         let config = WasmConfig {
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use oci_wasm::WasmClient;
 use policy::{
     AccessType, EnvironmentPermission, NetworkHostPermission, NetworkPermission, PolicyDocument,
@@ -15,12 +15,13 @@ use policy::{
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
 use tokio::sync::RwLock;
 use tracing::{info, instrument, warn};
 
 use crate::component_storage::ComponentStorage;
 use crate::loader::{self, PolicyResource};
-use crate::{SecretsManager, WasiStateTemplate};
+use crate::{SecretsManager, TrustPolicy, WasiStateTemplate};
 
 /// Granular permission rule types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +66,11 @@ pub(crate) struct PolicyManager {
     environment_vars: Arc<HashMap<String, String>>,
     oci_client: Arc<WasmClient>,
     http_client: Client,
+    trust: Arc<TrustPolicy>,
+    /// Whether components without a policy see every configured environment variable
+    inherit_env_without_policy: bool,
+    /// Empty directory every template preopens at `/`, see [`WasiStateTemplate::unmapped_root`]
+    unmapped_root: Arc<TempDir>,
 }
 
 /// Information about a policy attached to a component
@@ -89,15 +95,24 @@ impl PolicyManager {
         environment_vars: Arc<HashMap<String, String>>,
         oci_client: Arc<WasmClient>,
         http_client: Client,
-    ) -> Self {
-        Self {
+        trust: Arc<TrustPolicy>,
+        inherit_env_without_policy: bool,
+    ) -> Result<Self> {
+        let unmapped_root = tempfile::Builder::new()
+            .prefix("wassette-unmapped-")
+            .tempdir()
+            .context("Failed to create the directory for unmapped guest paths")?;
+        Ok(Self {
             registry: Arc::new(RwLock::new(PolicyRegistry::default())),
             storage,
             secrets,
             environment_vars,
             oci_client,
             http_client,
-        }
+            trust,
+            inherit_env_without_policy,
+            unmapped_root: Arc::new(unmapped_root),
+        })
     }
 
     pub(crate) fn policy_path(&self, component_id: &str) -> PathBuf {
@@ -121,11 +136,13 @@ impl PolicyManager {
         component_id: &str,
         template: Arc<WasiStateTemplate>,
     ) {
+        let mut template = Arc::unwrap_or_clone(template);
+        template.unmapped_root = Some(self.unmapped_root.clone());
         self.registry
             .write()
             .await
             .component_policies
-            .insert(component_id.to_string(), template);
+            .insert(component_id.to_string(), Arc::new(template));
     }
 
     pub(crate) async fn template_for_component(
@@ -146,10 +163,15 @@ impl PolicyManager {
         self.build_default_template(component_id).await
     }
 
-    /// Construct a default WASI template enriched with configured environment
-    /// variables and any stored secrets for the component.
+    /// Construct the WASI template for a component without a policy. It only exposes the
+    /// component's own secrets; configured environment variables are withheld unless
+    /// legacy inheritance was enabled.
     async fn build_default_template(&self, component_id: &str) -> Arc<WasiStateTemplate> {
-        let mut config_vars = self.environment_vars.as_ref().clone();
+        let mut config_vars = if self.inherit_env_without_policy {
+            self.environment_vars.as_ref().clone()
+        } else {
+            HashMap::new()
+        };
 
         if let Ok(secrets) = self.secrets.load_component_secrets(component_id).await {
             for (key, value) in secrets {
@@ -159,18 +181,37 @@ impl PolicyManager {
 
         let template = WasiStateTemplate {
             config_vars,
+            unmapped_root: Some(self.unmapped_root.clone()),
             ..WasiStateTemplate::default()
         };
         Arc::new(template)
     }
 
+    pub(crate) async fn fetch_policy(&self, policy_uri: &str) -> Result<PolicyDocument> {
+        self.trust
+            .check_pinned::<PolicyResource>(policy_uri, None)?;
+        let downloaded_policy = loader::load_resource::<PolicyResource>(
+            policy_uri,
+            &self.oci_client,
+            &self.http_client,
+            &self.trust,
+        )
+        .await?;
+
+        PolicyParser::parse_file(downloaded_policy.as_ref())
+            .with_context(|| format!("Failed to parse policy from {policy_uri}"))
+    }
+
     pub(crate) async fn attach_policy(&self, component_id: &str, policy_uri: &str) -> Result<()> {
         info!(component_id, policy_uri, "Attaching policy to component");
 
+        self.trust
+            .check_pinned::<PolicyResource>(policy_uri, None)?;
         let downloaded_policy = loader::load_resource::<PolicyResource>(
             policy_uri,
             &self.oci_client,
             &self.http_client,
+            &self.trust,
         )
         .await?;
 
@@ -188,6 +229,7 @@ impl PolicyManager {
         });
         let metadata_path = self.metadata_path(component_id);
         tokio::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?).await?;
+        self.storage.record_policy(component_id).await?;
 
         let secrets = self.secrets.load_component_secrets(component_id).await.ok();
 
@@ -205,6 +247,28 @@ impl PolicyManager {
         Ok(())
     }
 
+    /// Attach a policy wassette built itself, such as one synthesized from a manifest.
+    /// `source_uri` is where the policy's content was declared, e.g. a manifest or registry
+    /// index, and is checked against the trust policy like any other policy source.
+    pub(crate) async fn attach_policy_document(
+        &self,
+        component_id: &str,
+        policy: &PolicyDocument,
+        source_uri: Option<&str>,
+    ) -> Result<()> {
+        if let Some(source_uri) = source_uri {
+            self.trust.check_source::<PolicyResource>(source_uri)?;
+        }
+        info!(component_id, "Attaching generated policy to component");
+
+        let metadata_path = self.metadata_path(component_id);
+        self.storage
+            .remove_if_exists(&metadata_path, "policy metadata file", component_id)
+            .await?;
+        self.save_component_policy(component_id, policy).await?;
+        self.update_policy_registry(component_id, policy).await
+    }
+
     pub(crate) async fn detach_policy(&self, component_id: &str) -> Result<()> {
         info!(component_id, "Detaching policy from component");
 
@@ -217,6 +281,7 @@ impl PolicyManager {
         self.storage
             .remove_if_exists(&metadata_path, "policy metadata file", component_id)
             .await?;
+        self.storage.record_policy(component_id).await?;
 
         self.cleanup(component_id).await;
 
@@ -403,15 +468,28 @@ impl PolicyManager {
                         })
                         .collect();
 
+                    let mount = details
+                        .get("mount")
+                        .map(|v| {
+                            v.as_str()
+                                .map(str::to_string)
+                                .ok_or_else(|| anyhow!("'mount' field must be a string"))
+                        })
+                        .transpose()?;
+
                     PermissionRule::Storage(StoragePermission {
                         uri: uri.to_string(),
                         access: access_types?,
+                        mount,
+                        memory: None,
                     })
                 } else {
                     // No access field provided - used for revocation, create empty access
                     PermissionRule::Storage(StoragePermission {
                         uri: uri.to_string(),
                         access: Vec::new(),
+                        mount: None,
+                        memory: None,
                     })
                 }
             }
@@ -484,6 +562,7 @@ impl PolicyManager {
                     "Auto-generated policy for component: {component_id}"
                 )),
                 permissions: Default::default(),
+                args: None,
             })
         }
     }
@@ -660,6 +739,7 @@ impl PolicyManager {
         let policy_path = self.policy_path(component_id);
         let policy_yaml = serde_yaml::to_string(policy)?;
         tokio::fs::write(&policy_path, policy_yaml).await?;
+        self.storage.record_policy(component_id).await?;
         Ok(())
     }
 
@@ -676,6 +756,12 @@ impl PolicyManager {
                 if storage.uri.is_empty() {
                     return Err(anyhow!("Storage URI cannot be empty"));
                 }
+                if !storage.access.is_empty() && storage.requires_mount() {
+                    return Err(anyhow!(
+                        "Storage URI '{}' uses '~' or a variable, so it needs a 'mount' path for the guest",
+                        storage.uri
+                    ));
+                }
                 // Note: access can be empty for revocation operations, but not for grant operations
                 // The validation for non-empty access is now done during parsing
             }
@@ -729,6 +815,7 @@ impl PolicyManager {
         self.storage
             .remove_if_exists(&metadata_path, "policy metadata file", component_id)
             .await?;
+        self.storage.record_policy(component_id).await?;
 
         // Remove from policy registry
         self.cleanup(component_id).await;
@@ -849,6 +936,7 @@ impl PolicyManager {
 mod tests {
     use super::*;
     use crate::tests::*;
+    use crate::LifecycleManager;
 
     #[tokio::test]
     async fn test_policy_attachment_and_detachment() -> Result<()> {
@@ -901,6 +989,69 @@ permissions:
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_templates_share_unmapped_root() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let manager = LifecycleManager::builder(tempdir.path())
+            .with_secrets_dir(tempdir.path().join("secrets"))
+            .with_eager_loading(false)
+            .build()
+            .await?;
+        let policy_manager = &manager.policy_manager;
+
+        let policy = PolicyParser::parse_str("version: \"1.0\"\npermissions: {}\n")?;
+        policy_manager
+            .update_policy_registry("with-policy", &policy)
+            .await?;
+        let with_policy = policy_manager.template_for_component("with-policy").await;
+        let without_policy = policy_manager.template_for_component("no-policy").await;
+        let again = policy_manager.template_for_component("no-policy").await;
+
+        let root = with_policy.unmapped_root.as_ref().unwrap();
+        assert!(Arc::ptr_eq(
+            root,
+            without_policy.unmapped_root.as_ref().unwrap()
+        ));
+        assert!(Arc::ptr_eq(root, again.unmapped_root.as_ref().unwrap()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_template_withholds_environment() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let manager = LifecycleManager::builder(tempdir.path())
+            .with_environment_var("SERVER_TOKEN", "hunter2")
+            .with_secrets_dir(tempdir.path().join("secrets"))
+            .with_eager_loading(false)
+            .build()
+            .await?;
+
+        let template = manager
+            .policy_manager
+            .template_for_component("no-policy")
+            .await;
+        assert!(template.config_vars.is_empty());
+
+        let legacy = LifecycleManager::builder(tempdir.path())
+            .with_environment_var("SERVER_TOKEN", "hunter2")
+            .with_secrets_dir(tempdir.path().join("secrets"))
+            .with_eager_loading(false)
+            .with_env_inheritance_without_policy(true)
+            .build()
+            .await?;
+
+        let template = legacy
+            .policy_manager
+            .template_for_component("no-policy")
+            .await;
+        assert_eq!(
+            template.config_vars.get("SERVER_TOKEN").map(String::as_str),
+            Some("hunter2")
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_policy_attachment_component_not_found() -> Result<()> {
         let manager = create_test_manager().await?;
@@ -972,6 +1123,31 @@ permissions: {}
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_grant_home_storage_needs_mount() -> Result<()> {
+        let manager = create_test_manager().await?;
+        manager.load_test_component().await?;
+
+        let details = serde_json::json!({"uri": "fs://~/data", "access": ["read"]});
+        assert!(manager
+            .grant_permission(TEST_COMPONENT_ID, "storage", &details)
+            .await
+            .is_err());
+
+        let details =
+            serde_json::json!({"uri": "fs://~/data", "access": ["read"], "mount": "/data"});
+        manager
+            .grant_permission(TEST_COMPONENT_ID, "storage", &details)
+            .await?;
+
+        let policy_path = manager.get_component_policy_path(TEST_COMPONENT_ID);
+        let policy = PolicyParser::parse_file(&policy_path)?;
+        let allow = policy.permissions.storage.unwrap().allow.unwrap();
+        assert_eq!(allow[0].mount.as_deref(), Some("/data"));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_grant_permission_duplicate_prevention() -> Result<()> {
         let manager = create_test_manager().await?;
@@ -1294,6 +1470,8 @@ permissions:
         let storage_rule = PermissionRule::Storage(StoragePermission {
             uri: "fs:///tmp/test".to_string(),
             access: vec![AccessType::Read, AccessType::Write],
+            mount: None,
+            memory: None,
         });
         let serialized = serde_json::to_string(&storage_rule)?;
         assert!(serialized.contains("fs:///tmp/test"));
@@ -1313,6 +1491,8 @@ permissions:
         let storage_perm = PermissionRule::Storage(StoragePermission {
             uri: "fs:///tmp".to_string(),
             access: vec![AccessType::Read, AccessType::Write],
+            mount: None,
+            memory: None,
         });
         let env_perm = PermissionRule::Environment(EnvironmentPermission {
             key: "API_KEY".to_string(),
@@ -1358,6 +1538,7 @@ permissions:
             version: "1.0".to_string(),
             description: Some("Test policy".to_string()),
             permissions: policy::Permissions::default(),
+            args: None,
         };
 
         // Test adding resource permission
@@ -1415,6 +1596,7 @@ permissions:
             version: "1.0".to_string(),
             description: Some("Test policy with memory limits".to_string()),
             permissions: policy::Permissions::default(),
+            args: None,
         };
 
         // First add memory resource permission
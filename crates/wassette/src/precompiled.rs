@@ -0,0 +1,306 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Cache of precompiled components, keyed by engine configuration and component digest, with
+//! an integrity tag so that only artifacts this installation wrote are deserialized
+
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use cap_rand::RngCore;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use wasmtime::component::Component;
+use wasmtime::Engine;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length of the integrity tag stored in front of every cache entry
+const TAG_LEN: usize = 32;
+
+/// Length of the random key the integrity tags are computed with
+const KEY_LEN: usize = 32;
+
+/// Precompiled components for one engine configuration.
+///
+/// Entries live in `<root>/<engine key>/<wasm sha256>.cwasm`. The engine key changes with the
+/// Wasmtime version and any engine setting that affects code generation, so artifacts built by
+/// another engine are never looked up. Each entry starts with an HMAC-SHA256 tag over the engine
+/// key, the component digest and the precompiled bytes. The HMAC key is kept outside the cache
+/// directory, so a modified, swapped or foreign entry fails verification and the component is
+/// compiled from its `.wasm` file instead.
+#[derive(Clone)]
+pub(crate) struct PrecompiledCache {
+    root: PathBuf,
+    engine_key: String,
+    key: Arc<[u8]>,
+}
+
+impl PrecompiledCache {
+    /// Open the cache below `root` for `engine`, creating the HMAC key at `key_path` on first
+    /// use.
+    pub(crate) async fn open(root: PathBuf, key_path: &Path, engine: &Engine) -> Result<Self> {
+        let engine_key = engine_key(engine);
+        let dir = root.join(&engine_key);
+        tokio::fs::create_dir_all(&dir).await.with_context(|| {
+            format!(
+                "Failed to create precompiled cache directory at {}",
+                dir.display()
+            )
+        })?;
+        let key = load_or_create_key(key_path, "precompiled cache").await?;
+
+        Ok(Self {
+            root,
+            engine_key,
+            key: key.into(),
+        })
+    }
+
+    /// Directory holding the caches of every engine configuration.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory holding the entries usable by the current engine.
+    pub(crate) fn dir(&self) -> PathBuf {
+        self.root.join(&self.engine_key)
+    }
+
+    /// Name of the directory used by the current engine.
+    pub(crate) fn engine_key(&self) -> &str {
+        &self.engine_key
+    }
+
+    /// Path of the entry for the component with the given digest.
+    pub(crate) fn path(&self, digest: &str) -> PathBuf {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        self.dir().join(format!(
+            "{}.{}",
+            hex.to_ascii_lowercase(),
+            crate::PRECOMPILED_EXT
+        ))
+    }
+
+    /// Deserialize the cached component with the given digest. Returns `None` when there is no
+    /// entry or the entry fails verification; a failing entry is removed.
+    pub(crate) async fn load(&self, engine: &Engine, digest: &str) -> Option<Component> {
+        let path = self.path(digest);
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read precompiled component");
+                return None;
+            }
+        };
+
+        let verified = data.len() > TAG_LEN && {
+            let (tag, bytes) = data.split_at(TAG_LEN);
+            self.mac(digest, bytes).verify_slice(tag).is_ok()
+        };
+        if !verified {
+            warn!(path = %path.display(), "Precompiled component failed verification, recompiling");
+            self.remove(digest).await;
+            return None;
+        }
+
+        // SAFETY: the bytes carry a valid tag, so they were produced by `precompile_component`
+        // for this engine configuration and have not been modified since.
+        match unsafe { Component::deserialize(engine, &data[TAG_LEN..]) } {
+            Ok(component) => Some(component),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to load precompiled component, recompiling");
+                None
+            }
+        }
+    }
+
+    /// Store the precompiled bytes of the component with the given digest.
+    pub(crate) async fn store(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(digest);
+        let tag = self.mac(digest, bytes).finalize().into_bytes();
+        let mut data = Vec::with_capacity(TAG_LEN + bytes.len());
+        data.extend_from_slice(&tag);
+        data.extend_from_slice(bytes);
+
+        // Write to a temporary file first so a concurrent load never sees a partial entry
+        let tmp_path = path.with_extension(format!("{}.tmp", crate::PRECOMPILED_EXT));
+        tokio::fs::write(&tmp_path, &data).await.with_context(|| {
+            format!(
+                "Failed to write precompiled component to {}",
+                tmp_path.display()
+            )
+        })?;
+        tokio::fs::rename(&tmp_path, &path).await.with_context(|| {
+            format!(
+                "Failed to write precompiled component to {}",
+                path.display()
+            )
+        })
+    }
+
+    /// Remove the entry for the component with the given digest, if any.
+    pub(crate) async fn remove(&self, digest: &str) {
+        let path = self.path(digest);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => debug!(path = %path.display(), "Removed precompiled component"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to remove precompiled component")
+            }
+        }
+    }
+
+    fn mac(&self, digest: &str, bytes: &[u8]) -> HmacSha256 {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(self.engine_key.as_bytes());
+        mac.update(b"\0");
+        mac.update(hex.to_ascii_lowercase().as_bytes());
+        mac.update(b"\0");
+        mac.update(bytes);
+        mac
+    }
+}
+
+/// Feeds the engine's compatibility hash into SHA-256
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn finish(&self) -> u64 {
+        unreachable!("only the SHA-256 digest is used")
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+/// Key identifying the engine configuration precompiled artifacts must match. Wasmtime's
+/// compatibility hash covers its version and every setting that affects generated code.
+fn engine_key(engine: &Engine) -> String {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hex::encode(&hasher.0.finalize()[..16])
+}
+
+/// Read the HMAC key at `path`, or create a random one if there is none yet. `purpose` names
+/// the key in error messages.
+pub(crate) async fn load_or_create_key(path: &Path, purpose: &str) -> Result<Vec<u8>> {
+    if let Some(key) = read_key(path, purpose).await? {
+        return Ok(key);
+    }
+
+    let mut key = vec![0u8; KEY_LEN];
+    cap_rand::thread_rng(cap_rand::ambient_authority()).fill_bytes(&mut key);
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.with_context(|| {
+            format!(
+                "Failed to create {purpose} key directory at {}",
+                parent.display()
+            )
+        })?;
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    match options.open(path).await {
+        Ok(mut file) => {
+            tokio::io::AsyncWriteExt::write_all(&mut file, &key)
+                .await
+                .with_context(|| format!("Failed to write {purpose} key to {}", path.display()))?;
+            Ok(key)
+        }
+        // Another process created the key first
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => read_key(path, purpose)
+            .await?
+            .with_context(|| format!("The {purpose} key at {} vanished", path.display())),
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to create {purpose} key at {}", path.display()))
+        }
+    }
+}
+
+/// Read the HMAC key at `path`, or `None` if it has not been created yet
+pub(crate) async fn read_key(path: &Path, purpose: &str) -> Result<Option<Vec<u8>>> {
+    match tokio::fs::read(path).await {
+        Ok(key) if key.len() == KEY_LEN => Ok(Some(key)),
+        Ok(_) => bail!(
+            "The {purpose} key at {} is corrupt; delete it to create a new one",
+            path.display()
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to read {purpose} key at {}", path.display()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_precompiled_cache_rejects_tampered_entries() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let engine = Engine::default();
+        let key_path = tempdir.path().join("precompiled.key");
+        let cache =
+            PrecompiledCache::open(tempdir.path().join("cache"), &key_path, &engine).await?;
+
+        let wasm = empty_component();
+        let digest = crate::digest::sha256_digest(&wasm);
+        assert!(cache.load(&engine, &digest).await.is_none());
+
+        let bytes = engine.precompile_component(&wasm)?;
+        cache.store(&digest, &bytes).await?;
+        assert!(cache.load(&engine, &digest).await.is_some());
+
+        // The same entry under another digest does not verify
+        let other = crate::digest::sha256_digest(b"other");
+        std::fs::copy(cache.path(&digest), cache.path(&other))?;
+        assert!(cache.load(&engine, &other).await.is_none());
+        assert!(!cache.path(&other).exists(), "failing entries are removed");
+
+        // Nor does a modified entry
+        let mut data = std::fs::read(cache.path(&digest))?;
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        std::fs::write(cache.path(&digest), data)?;
+        assert!(cache.load(&engine, &digest).await.is_none());
+
+        // A new key invalidates entries written with the old one
+        cache.store(&digest, &bytes).await?;
+        std::fs::remove_file(&key_path)?;
+        let cache =
+            PrecompiledCache::open(tempdir.path().join("cache"), &key_path, &engine).await?;
+        assert!(cache.load(&engine, &digest).await.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_key_depends_on_configuration() -> Result<()> {
+        let default = Engine::default();
+        assert_eq!(engine_key(&default), engine_key(&Engine::default()));
+
+        let mut config = wasmtime::Config::new();
+        config.epoch_interruption(true);
+        assert_ne!(engine_key(&default), engine_key(&Engine::new(&config)?));
+        Ok(())
+    }
+
+    /// Bytes of the smallest valid component
+    fn empty_component() -> Vec<u8> {
+        // `(component)` in binary form
+        vec![0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00]
+    }
+}
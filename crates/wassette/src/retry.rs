@@ -0,0 +1,271 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Retrying component downloads that fail for transient reasons
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing::warn;
+
+use crate::loader::HttpStatusError;
+
+/// How long to wait between download attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Wait `base`, then twice as long after every further failure
+    Exponential {
+        /// Delay before the first retry
+        base: Duration,
+    },
+    /// Wait `increment`, then `increment` longer after every further failure
+    Linear {
+        /// Delay added for every retry
+        increment: Duration,
+    },
+}
+
+/// Retry behaviour for loading a component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub attempts: u32,
+    /// Delay between attempts
+    pub backoff: Backoff,
+}
+
+impl Default for RetryPolicy {
+    /// Never retries
+    fn default() -> Self {
+        Self {
+            attempts: 0,
+            backoff: Backoff::Linear {
+                increment: Duration::ZERO,
+            },
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let retry = retry.max(1);
+        match self.backoff {
+            Backoff::Exponential { base } => base.saturating_mul(2u32.saturating_pow(retry - 1)),
+            Backoff::Linear { increment } => increment.saturating_mul(retry),
+        }
+    }
+
+    /// Runs `operation`, retrying it while it fails with a transient error
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < self.attempts && is_transient(&e) => {
+                    retry += 1;
+                    let delay = self.delay(retry);
+                    warn!(
+                        error = %format!("{e:#}"),
+                        retry,
+                        attempts = self.attempts,
+                        delay_ms = delay.as_millis() as u64,
+                        "Transient failure, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Whether `error` is worth retrying: failures to connect, timeouts, rate limiting (429) and
+/// server errors (5xx). Everything else, including missing resources, rejected credentials,
+/// interrupted transfers and digest mismatches, is not.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<HttpStatusError>() {
+            return is_transient_status(e.status);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return match e.status() {
+                Some(status) => is_transient_status(status.as_u16()),
+                None => e.is_connect() || e.is_timeout(),
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<oci_client::errors::OciDistributionError>() {
+            return match e {
+                oci_client::errors::OciDistributionError::ServerError { code, .. } => {
+                    is_transient_status(*code)
+                }
+                oci_client::errors::OciDistributionError::RequestError(e) => {
+                    e.is_connect() || e.is_timeout()
+                }
+                _ => false,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::TimedOut
+            );
+        }
+        false
+    })
+}
+
+fn is_transient_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::loader::{ComponentResource, Loadable};
+
+    /// Serves `body` over plain HTTP, answering the first `failures` requests with `status`
+    async fn flaky_server(
+        failures: usize,
+        status: u16,
+        body: &'static [u8],
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let (status, body) = if n < failures {
+                    (status, &b"unavailable"[..])
+                } else {
+                    (200, body)
+                };
+                let head = format!(
+                    "HTTP/1.1 {status} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (format!("http://{addr}/component.wasm"), requests)
+    }
+
+    fn policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            backoff: Backoff::Linear {
+                increment: Duration::from_millis(10),
+            },
+        }
+    }
+
+    #[test]
+    fn test_backoff_delays() {
+        let exponential = RetryPolicy {
+            attempts: 4,
+            backoff: Backoff::Exponential {
+                base: Duration::from_millis(100),
+            },
+        };
+        assert_eq!(exponential.delay(1), Duration::from_millis(100));
+        assert_eq!(exponential.delay(3), Duration::from_millis(400));
+
+        let linear = RetryPolicy {
+            attempts: 4,
+            backoff: Backoff::Linear {
+                increment: Duration::from_millis(100),
+            },
+        };
+        assert_eq!(linear.delay(1), Duration::from_millis(100));
+        assert_eq!(linear.delay(3), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let status = |status| {
+            anyhow::Error::new(HttpStatusError {
+                url: "https://example.com/component.wasm".to_string(),
+                status,
+                body: String::new(),
+            })
+        };
+        assert!(is_transient(&status(503)));
+        assert!(is_transient(&status(429)));
+        assert!(!is_transient(&status(408)));
+        assert!(!is_transient(&status(404)));
+
+        let io = |kind| anyhow::Error::new(std::io::Error::from(kind));
+        assert!(is_transient(&io(std::io::ErrorKind::ConnectionRefused)));
+        assert!(is_transient(&io(std::io::ErrorKind::TimedOut)));
+        assert!(!is_transient(&io(std::io::ErrorKind::ConnectionReset)));
+        assert!(!is_transient(&io(std::io::ErrorKind::UnexpectedEof)));
+        assert!(!is_transient(&anyhow::anyhow!("digest mismatch")));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_download_failures() {
+        let (url, requests) = flaky_server(2, 503, b"component bytes").await;
+        let client = reqwest::Client::new();
+
+        let resource = policy(3)
+            .run(|| ComponentResource::from_url(&url, &client))
+            .await
+            .unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let bytes = tokio::fs::read(resource.as_ref()).await.unwrap();
+        assert_eq!(bytes, b"component bytes");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_attempts() {
+        let (url, requests) = flaky_server(5, 503, b"component bytes").await;
+        let client = reqwest::Client::new();
+
+        let err = policy(2)
+            .run(|| ComponentResource::from_url(&url, &client))
+            .await
+            .err()
+            .expect("download should fail");
+
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(err.to_string().contains("503"));
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_permanent_failures() {
+        let (url, requests) = flaky_server(1, 404, b"component bytes").await;
+        let client = reqwest::Client::new();
+
+        let err = policy(3)
+            .run(|| ComponentResource::from_url(&url, &client))
+            .await
+            .err()
+            .expect("download should fail");
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(!is_transient(&err));
+    }
+}
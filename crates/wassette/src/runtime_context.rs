@@ -5,6 +5,7 @@
 //! manager instances.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use wasmtime::component::{Component, InstancePre, Linker};
@@ -13,6 +14,10 @@ use wasmtime_wasi_config::WasiConfig;
 
 use crate::{WasiState, WassetteWasiState};
 
+/// Interval at which the engine epoch advances. Call time limits (`cpu_time_ms`, which counts
+/// wall-clock time) are enforced with this granularity.
+pub(crate) const EPOCH_TICK: Duration = Duration::from_millis(10);
+
 /// Encapsulates Wasmtime engine and linker setup for reuse across the lifecycle manager.
 #[derive(Clone)]
 pub struct RuntimeContext {
@@ -26,11 +31,14 @@ impl RuntimeContext {
         let mut config = wasmtime::Config::new();
         config.wasm_component_model(true);
         config.async_support(true);
+        config.epoch_interruption(true);
 
         let engine = Arc::new(Engine::new(&config)?);
+        spawn_epoch_ticker(&engine);
 
         let mut linker = Linker::new(engine.as_ref());
         wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        crate::storage_guard::add_to_linker(&mut linker)?;
         wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)?;
         wasmtime_wasi_config::add_to_linker(
             &mut linker,
@@ -53,6 +61,27 @@ impl RuntimeContext {
     }
 }
 
+/// Advances the epoch of `engine` every [`EPOCH_TICK`] until the engine is dropped
+fn spawn_epoch_ticker(engine: &Arc<Engine>) {
+    let engine = Arc::downgrade(engine);
+    std::thread::Builder::new()
+        .name("wassette-epoch".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(EPOCH_TICK);
+            match engine.upgrade() {
+                Some(engine) => engine.increment_epoch(),
+                None => break,
+            }
+        })
+        .expect("Failed to spawn epoch ticker thread");
+}
+
+/// Number of epoch ticks that make up `limit`, rounded up
+pub(crate) fn epoch_ticks(limit: Duration) -> u64 {
+    let tick = EPOCH_TICK.as_millis();
+    limit.as_millis().div_ceil(tick).max(1) as u64
+}
+
 impl AsRef<Engine> for RuntimeContext {
     fn as_ref(&self) -> &Engine {
         self.engine.as_ref()
@@ -63,6 +63,34 @@ pub fn ensure_structured_result(schema: &Value, structured_value: Value) -> Valu
     }
 }
 
+/// Returns true when a structured result holds the `err` case of a WIT `result`.
+///
+/// The value must have the `{ "result": { "err": ... } }` shape produced for `Val::Result(Err)`.
+/// When an output schema is available it must also describe a `result` with an `err` variant,
+/// so records that merely have a single field named `err` are not mistaken for errors.
+pub fn is_error_result(schema: Option<&Value>, structured_value: &Value) -> bool {
+    let is_err_shape = structured_value
+        .get("result")
+        .and_then(|v| v.as_object())
+        .is_some_and(|inner| inner.len() == 1 && inner.contains_key("err"));
+    if !is_err_shape {
+        return false;
+    }
+
+    let Some(schema) = schema else {
+        return true;
+    };
+    extract_result_schema(schema)
+        .and_then(|result| result.get("oneOf"))
+        .and_then(|v| v.as_array())
+        .is_some_and(|variants| {
+            variants.iter().any(|variant| {
+                variant.get("required").and_then(|v| v.as_array())
+                    == Some(&vec![Value::String("err".to_string())])
+            })
+        })
+}
+
 /// Wrap an inner schema inside the canonical `{ "result": ... }` envelope.
 pub fn wrap_schema_in_result(schema: Value) -> Value {
     build_result_wrapper(schema)
@@ -223,3 +251,47 @@ fn looks_like_tuple_keys(map: &Map<String, Value>) -> bool {
     }
     idx > 0 && map.len() == idx
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn result_schema() -> Value {
+        canonicalize_output_schema(&json!({
+            "oneOf": [
+                {"type": "object", "properties": {"ok": {"type": "string"}}, "required": ["ok"]},
+                {"type": "object", "properties": {"err": {"type": "string"}}, "required": ["err"]}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_is_error_result() {
+        let schema = result_schema();
+        assert!(is_error_result(
+            Some(&schema),
+            &json!({"result": {"err": "boom"}})
+        ));
+        assert!(!is_error_result(
+            Some(&schema),
+            &json!({"result": {"ok": "fine"}})
+        ));
+        assert!(is_error_result(None, &json!({"result": {"err": null}})));
+        assert!(!is_error_result(None, &json!({"result": "err"})));
+    }
+
+    #[test]
+    fn test_record_with_err_field_is_not_an_error() {
+        let schema = canonicalize_output_schema(&json!({
+            "type": "object",
+            "properties": {"err": {"type": "string"}},
+            "required": ["err"]
+        }));
+        assert!(!is_error_result(
+            Some(&schema),
+            &json!({"result": {"err": "just a field"}})
+        ));
+    }
+}
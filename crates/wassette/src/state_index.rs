@@ -0,0 +1,587 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Signed index of the component and policy files wassette wrote, used to detect files that
+//! were changed outside wassette
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{debug, info, warn};
+
+use crate::component_storage::ComponentStorage;
+use crate::digest::{digests_match, file_sha256_digest};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Digests of the files of one component as wassette last wrote them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ArtifactDigests {
+    /// Digest of `<id>.wasm`
+    pub(crate) wasm: String,
+    /// Digest of `<id>.policy.yaml`, if the component has a policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) policy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    components: BTreeMap<String, ArtifactDigests>,
+    signature: String,
+}
+
+/// Contents of the state index on disk
+pub(crate) enum IndexState {
+    /// No index has been written yet
+    Missing,
+    /// An index was written before but has been removed
+    Removed,
+    /// The index exists but its signature does not match its contents
+    Tampered,
+    /// The recorded digests of every tracked component
+    Valid(BTreeMap<String, ArtifactDigests>),
+}
+
+/// The state index of a component directory.
+///
+/// The index is a JSON file listing the digest of every component and policy file wassette
+/// wrote, signed with an HMAC-SHA256 key kept outside the component directory. The key is
+/// created when the index is first written, so a key without an index means the index was
+/// removed. Updates are serialized within the process and written atomically.
+#[derive(Clone)]
+pub(crate) struct StateIndex {
+    path: PathBuf,
+    key_path: PathBuf,
+    key: Arc<OnceCell<Vec<u8>>>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl StateIndex {
+    /// Open the index at `path`, signed with the key at `key_path`.
+    pub(crate) fn open(path: PathBuf, key_path: PathBuf) -> Self {
+        Self {
+            path,
+            key_path,
+            key: Arc::default(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// The path of the signing key for the index of `component_dir`, in `key_dir`. Each
+    /// component directory has its own key, so an index cannot be copied between them.
+    pub(crate) fn key_path(key_dir: &Path, component_dir: &Path) -> Result<PathBuf> {
+        let component_dir = component_dir.canonicalize().with_context(|| {
+            format!(
+                "Failed to resolve component directory {}",
+                component_dir.display()
+            )
+        })?;
+        let digest = crate::sha256_digest(component_dir.as_os_str().as_encoded_bytes());
+        let name = &digest.trim_start_matches("sha256:")[..16];
+        Ok(key_dir.join(format!("{}-{name}.key", crate::STATE_KEY_PREFIX)))
+    }
+
+    /// The signing key, if the index has been written before
+    async fn existing_key(&self) -> Result<Option<&[u8]>> {
+        if let Some(key) = self.key.get() {
+            return Ok(Some(key));
+        }
+        match crate::precompiled::read_key(&self.key_path, "state index").await? {
+            Some(key) => Ok(Some(self.key.get_or_init(|| async { key }).await)),
+            None => Ok(None),
+        }
+    }
+
+    /// The signing key, created if the index has never been written
+    async fn key(&self) -> Result<&[u8]> {
+        self.key
+            .get_or_try_init(|| {
+                crate::precompiled::load_or_create_key(&self.key_path, "state index")
+            })
+            .await
+            .map(Vec::as_slice)
+    }
+
+    /// Read and verify the index.
+    pub(crate) async fn read(&self) -> Result<IndexState> {
+        let key = self.existing_key().await?;
+        let bytes = match tokio::fs::read(&self.path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(match key {
+                    Some(_) => IndexState::Removed,
+                    None => IndexState::Missing,
+                })
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read state index at {}", self.path.display())
+                })
+            }
+        };
+
+        // Without a key the index was not written by wassette
+        let Some(key) = key else {
+            return Ok(IndexState::Tampered);
+        };
+        let Ok(file) = serde_json::from_slice::<IndexFile>(&bytes) else {
+            return Ok(IndexState::Tampered);
+        };
+        let verified = hex::decode(&file.signature)
+            .is_ok_and(|tag| mac(key, &file.components).verify_slice(&tag).is_ok());
+        if !verified {
+            return Ok(IndexState::Tampered);
+        }
+        Ok(IndexState::Valid(file.components))
+    }
+
+    /// Apply `update` to the recorded digests and write the index back. An index that was never
+    /// written starts out empty. An index that fails verification or was removed is left as it
+    /// is, so this is still reported at the next startup.
+    pub(crate) async fn update(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, ArtifactDigests>),
+    ) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut components = match self.read().await? {
+            IndexState::Missing => BTreeMap::new(),
+            IndexState::Tampered | IndexState::Removed => {
+                warn!(path = %self.path.display(), "State index failed verification or was removed, not updating it");
+                return Ok(());
+            }
+            IndexState::Valid(components) => components,
+        };
+        update(&mut components);
+        self.write(components).await
+    }
+
+    /// Replace the index with `components`, whatever its current state.
+    pub(crate) async fn replace(
+        &self,
+        components: BTreeMap<String, ArtifactDigests>,
+    ) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        self.write(components).await
+    }
+
+    async fn write(&self, components: BTreeMap<String, ArtifactDigests>) -> Result<()> {
+        let key = self.key().await?;
+        let signature = hex::encode(mac(key, &components).finalize().into_bytes());
+        let data = serde_json::to_vec_pretty(&IndexFile {
+            components,
+            signature,
+        })?;
+
+        // Write to a temporary file first so a concurrent read never sees a partial index
+        let tmp_path = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, &data)
+            .await
+            .with_context(|| format!("Failed to write state index to {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .with_context(|| format!("Failed to write state index to {}", self.path.display()))
+    }
+}
+
+fn mac(key: &[u8], components: &BTreeMap<String, ArtifactDigests>) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&serde_json::to_vec(components).expect("digests serialize to JSON"));
+    mac
+}
+
+/// File of a component that differs from what wassette last wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperedArtifact {
+    /// The component's `.wasm` file
+    Component,
+    /// The component's policy file
+    Policy,
+    /// The state index itself failed verification or was removed, so none of its records can
+    /// be trusted
+    StateIndex,
+}
+
+/// A component whose files were changed outside wassette
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityViolation {
+    /// ID of the affected component
+    pub component_id: String,
+    /// Which file was changed
+    pub artifact: TamperedArtifact,
+    /// Digest recorded when wassette last wrote the file, if it existed
+    pub expected: Option<String>,
+    /// Digest of the file on disk, if it exists
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for IntegrityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = &self.component_id;
+        match (self.artifact, &self.expected, &self.actual) {
+            (TamperedArtifact::Component, None, _) => {
+                write!(f, "component '{id}' was added outside wassette")
+            }
+            (TamperedArtifact::Component, _, _) => {
+                write!(f, "component '{id}' was modified outside wassette")
+            }
+            (TamperedArtifact::Policy, None, _) => {
+                write!(f, "a policy was added to component '{id}' outside wassette")
+            }
+            (TamperedArtifact::Policy, _, None) => {
+                write!(
+                    f,
+                    "the policy of component '{id}' was removed outside wassette"
+                )
+            }
+            (TamperedArtifact::Policy, _, _) => {
+                write!(
+                    f,
+                    "the policy of component '{id}' was modified outside wassette"
+                )
+            }
+            (TamperedArtifact::StateIndex, _, _) => write!(
+                f,
+                "the state index failed verification or was removed, so component '{id}' cannot be verified"
+            ),
+        }
+    }
+}
+
+impl ComponentStorage {
+    /// Record the component file wassette installed for a component. The policy of a component
+    /// seen for the first time is recorded as it is.
+    pub(crate) async fn record_component(&self, component_id: &str, digest: &str) -> Result<()> {
+        let policy = file_digest_if_exists(&self.policy_path(component_id)).await?;
+        self.state()
+            .update(|components| {
+                components
+                    .entry(component_id.to_string())
+                    .or_insert_with(|| ArtifactDigests {
+                        wasm: String::new(),
+                        policy,
+                    })
+                    .wasm = digest.to_string();
+            })
+            .await
+    }
+
+    /// Record the policy file wassette wrote or removed for a component.
+    pub(crate) async fn record_policy(&self, component_id: &str) -> Result<()> {
+        let policy = file_digest_if_exists(&self.policy_path(component_id)).await?;
+        self.state()
+            .update(|components| {
+                if let Some(entry) = components.get_mut(component_id) {
+                    entry.policy = policy;
+                }
+            })
+            .await
+    }
+
+    /// Drop the records of a component wassette removed.
+    pub(crate) async fn forget_component(&self, component_id: &str) -> Result<()> {
+        self.state()
+            .update(|components| {
+                components.remove(component_id);
+            })
+            .await
+    }
+
+    /// Compare the files of every installed component with the state index. The first time,
+    /// before any index was written, the installed files are recorded as they are. After that,
+    /// components the index does not know are reported as added outside wassette, and records of
+    /// components that are no longer installed are dropped.
+    pub async fn verify_integrity(&self) -> Result<Vec<IntegrityViolation>> {
+        let installed = self.installed_state().await?;
+
+        let recorded = match self.state().read().await? {
+            IndexState::Tampered | IndexState::Removed => {
+                return Ok(installed
+                    .into_keys()
+                    .map(|component_id| IntegrityViolation {
+                        component_id,
+                        artifact: TamperedArtifact::StateIndex,
+                        expected: None,
+                        actual: None,
+                    })
+                    .collect());
+            }
+            IndexState::Missing => {
+                info!(
+                    count = installed.len(),
+                    "Recording installed components in a new state index"
+                );
+                self.state().replace(installed).await?;
+                return Ok(Vec::new());
+            }
+            IndexState::Valid(recorded) => recorded,
+        };
+
+        let mut violations = Vec::new();
+        for (component_id, actual) in &installed {
+            let Some(expected) = recorded.get(component_id) else {
+                violations.push(IntegrityViolation {
+                    component_id: component_id.clone(),
+                    artifact: TamperedArtifact::Component,
+                    expected: None,
+                    actual: Some(actual.wasm.clone()),
+                });
+                continue;
+            };
+            if !digests_match(&expected.wasm, &actual.wasm) {
+                violations.push(IntegrityViolation {
+                    component_id: component_id.clone(),
+                    artifact: TamperedArtifact::Component,
+                    expected: Some(expected.wasm.clone()),
+                    actual: Some(actual.wasm.clone()),
+                });
+            }
+            let policy_matches = match (&expected.policy, &actual.policy) {
+                (Some(expected), Some(actual)) => digests_match(expected, actual),
+                (expected, actual) => expected.is_none() && actual.is_none(),
+            };
+            if !policy_matches {
+                violations.push(IntegrityViolation {
+                    component_id: component_id.clone(),
+                    artifact: TamperedArtifact::Policy,
+                    expected: expected.policy.clone(),
+                    actual: actual.policy.clone(),
+                });
+            }
+        }
+
+        let removed: Vec<&String> = recorded
+            .keys()
+            .filter(|component_id| !installed.contains_key(*component_id))
+            .collect();
+        if !removed.is_empty() {
+            for component_id in &removed {
+                debug!(%component_id, "Dropping removed component from the state index");
+            }
+            self.state()
+                .update(|components| {
+                    components.retain(|component_id, _| installed.contains_key(component_id));
+                })
+                .await?;
+        }
+
+        Ok(violations)
+    }
+
+    /// Record the current files of every installed component as trusted, replacing the state
+    /// index.
+    pub async fn accept_current_state(&self) -> Result<()> {
+        let installed = self.installed_state().await?;
+        self.state().replace(installed).await
+    }
+
+    /// Move the files of a component into `quarantine/<id>-<timestamp>` below the component
+    /// directory and drop its records, so it is no longer installed. Returns the directory the
+    /// files were moved to.
+    pub async fn quarantine_component(&self, component_id: &str) -> Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let dir = self
+            .root()
+            .join(crate::QUARANTINE_DIR)
+            .join(format!("{component_id}-{timestamp}"));
+        tokio::fs::create_dir_all(&dir).await.with_context(|| {
+            format!("Failed to create quarantine directory at {}", dir.display())
+        })?;
+
+        for path in [
+            self.component_path(component_id),
+            self.metadata_path(component_id),
+            self.policy_path(component_id),
+            self.policy_metadata_path(component_id),
+            self.versions_dir(component_id),
+        ] {
+            if !path.exists() {
+                continue;
+            }
+            let dest = dir.join(path.file_name().context("Artifact path has no file name")?);
+            tokio::fs::rename(&path, &dest).await.with_context(|| {
+                format!("Failed to move {} to {}", path.display(), dest.display())
+            })?;
+        }
+
+        // The index cannot be updated when it failed verification; the component is gone either
+        // way
+        self.forget_component(component_id).await?;
+        warn!(%component_id, path = %dir.display(), "Quarantined component");
+        Ok(dir)
+    }
+
+    /// Digests of the files of every installed component
+    async fn installed_state(&self) -> Result<BTreeMap<String, ArtifactDigests>> {
+        let mut installed = BTreeMap::new();
+        let mut entries = tokio::fs::read_dir(self.root()).await.with_context(|| {
+            format!(
+                "Failed to read component directory {}",
+                self.root().display()
+            )
+        })?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(component_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".wasm"))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let wasm = file_sha256_digest(&entry.path()).await?;
+            let policy = file_digest_if_exists(&self.policy_path(&component_id)).await?;
+            installed.insert(component_id, ArtifactDigests { wasm, policy });
+        }
+        Ok(installed)
+    }
+}
+
+/// Digest of the file at `path`, or `None` if there is no such file
+async fn file_digest_if_exists(path: &Path) -> Result<Option<String>> {
+    if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+        return Ok(None);
+    }
+    file_sha256_digest(path).await.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_integrity_detects_external_changes() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let key_dir = tempfile::tempdir()?;
+        let storage = ComponentStorage::new(
+            tempdir.path(),
+            key_dir.path(),
+            1,
+            None,
+            &wasmtime::Engine::default(),
+        )
+        .await?;
+
+        // Components installed before the index existed are recorded as they are
+        std::fs::write(storage.component_path("legacy"), "legacy")?;
+        std::fs::write(storage.policy_path("legacy"), "version: \"1.0\"")?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        std::fs::write(storage.component_path("tool"), "wasm")?;
+        storage
+            .record_component("tool", &crate::sha256_digest(b"wasm"))
+            .await?;
+        std::fs::write(storage.policy_path("tool"), "version: \"1.0\"")?;
+        storage.record_policy("tool").await?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        // Changes made outside wassette are reported
+        std::fs::write(storage.component_path("tool"), "swapped")?;
+        std::fs::remove_file(storage.policy_path("legacy"))?;
+        let violations = storage.verify_integrity().await?;
+        let found: Vec<(&str, TamperedArtifact)> = violations
+            .iter()
+            .map(|v| (v.component_id.as_str(), v.artifact))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("legacy", TamperedArtifact::Policy),
+                ("tool", TamperedArtifact::Component),
+            ]
+        );
+        assert_eq!(violations[0].actual, None);
+
+        // Changes wassette makes itself are not
+        storage.record_policy("legacy").await?;
+        storage.accept_current_state().await?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        // Editing the index to match the changed files invalidates it
+        let index = tempdir.path().join(crate::STATE_INDEX);
+        let edited = std::fs::read_to_string(&index)?.replace(
+            &crate::sha256_digest(b"swapped"),
+            &crate::sha256_digest(b"wasm"),
+        );
+        std::fs::write(&index, edited)?;
+        let violations = storage.verify_integrity().await?;
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .all(|v| v.artifact == TamperedArtifact::StateIndex));
+
+        let quarantined = storage.quarantine_component("tool").await?;
+        assert!(quarantined.join("tool.wasm").exists());
+        assert!(quarantined.join("tool.policy.yaml").exists());
+        assert!(!storage.component_path("tool").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity_detects_removed_index_and_added_components() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let key_dir = tempfile::tempdir()?;
+        let engine = wasmtime::Engine::default();
+        let open = || ComponentStorage::new(tempdir.path(), key_dir.path(), 1, None, &engine);
+        let storage = open().await?;
+
+        std::fs::write(storage.component_path("tool"), "wasm")?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        // Once the index exists, a component dropped into the directory is not trusted
+        std::fs::write(storage.component_path("added"), "added")?;
+        let violations = storage.verify_integrity().await?;
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].component_id, "added");
+        assert_eq!(violations[0].artifact, TamperedArtifact::Component);
+        assert_eq!(violations[0].expected, None);
+        assert!(violations[0].to_string().contains("added outside wassette"));
+        // Nor is it recorded by verifying again
+        assert_eq!(storage.verify_integrity().await?.len(), 1);
+
+        storage.accept_current_state().await?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        // Removing the index does not make the installed files trusted again, even after a
+        // restart
+        std::fs::remove_file(tempdir.path().join(crate::STATE_INDEX))?;
+        let storage = open().await?;
+        let violations = storage.verify_integrity().await?;
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .all(|v| v.artifact == TamperedArtifact::StateIndex));
+
+        // Installs do not recreate it behind the user's back
+        std::fs::write(storage.component_path("other"), "other")?;
+        storage
+            .record_component("other", &crate::sha256_digest(b"other"))
+            .await?;
+        assert!(!tempdir.path().join(crate::STATE_INDEX).exists());
+
+        storage.accept_current_state().await?;
+        assert!(storage.verify_integrity().await?.is_empty());
+
+        // The key is kept outside the component directory
+        assert!(std::fs::read_dir(key_dir.path())?.count() > 0);
+        assert!(!std::fs::read_dir(tempdir.path())?
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.file_name().to_string_lossy().ends_with(".key")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,797 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Filesystem host that records storage permission denials.
+//!
+//! [`GuardedFilesystem`] forwards every `wasi:filesystem` call to the wasmtime-wasi
+//! implementation. When an operation is refused because it escapes a preopened directory or needs
+//! more access than the policy grants, the attempted path is recorded as a
+//! [`PermissionError::StorageDenied`] so the caller gets an actionable error instead of a bare
+//! WASI error code.
+//!
+//! Guests resolve absolute paths against their preopens themselves, so a path outside every
+//! granted directory would never reach the host. Stores therefore also preopen an empty
+//! directory without any permissions at `/`, one per lifecycle manager (see
+//! [`crate::WasiStateTemplate::unmapped_root`]); those paths resolve to it and are denied and
+//! recorded here like any other.
+//!
+//! Writes into an in-memory (`mem://`) mount with a quota are counted here as well. A write,
+//! resize or output stream that would take the mount over its quota fails with
+//! `insufficient-space` before any data reaches the mount.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use wasmtime::component::{HasData, Linker, Resource};
+use wasmtime_wasi::filesystem::{Descriptor, WasiFilesystemCtxView, WasiFilesystemView};
+use wasmtime_wasi::p2::bindings::filesystem::types::{
+    ErrorCode, HostDescriptor, HostDirectoryEntryStream,
+};
+use wasmtime_wasi::p2::bindings::filesystem::{preopens, types};
+use wasmtime_wasi::p2::{DynInputStream, DynOutputStream, FsError, FsResult};
+
+use crate::memory_mount::{ActiveMemoryMount, QuotaOutputStream};
+use crate::wasistate::{PermissionError, WasiState};
+use crate::WassetteWasiState;
+
+type State = WassetteWasiState<WasiState>;
+
+/// Paths of open descriptors, keyed by resource rep
+pub type DescriptorPaths = Arc<Mutex<HashMap<u32, DescriptorPath>>>;
+
+/// Where an open descriptor, or a path under it, points
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DescriptorPath {
+    /// Path as the guest sees it
+    pub guest: String,
+    /// Path on the host, when the descriptor comes from an `fs://` storage rule
+    pub host: Option<PathBuf>,
+    /// Index of the in-memory mount the descriptor lies on
+    pub memory_mount: Option<usize>,
+}
+
+impl DescriptorPath {
+    fn join(&self, path: &str) -> DescriptorPath {
+        DescriptorPath {
+            guest: join_guest_path(Some(&self.guest), path),
+            host: self.host.as_ref().map(|host| {
+                if path.is_empty() {
+                    host.clone()
+                } else {
+                    host.join(path)
+                }
+            }),
+            memory_mount: self.memory_mount,
+        }
+    }
+}
+
+const READ: &str = "read";
+const WRITE: &str = "write";
+
+/// Replaces the `wasi:filesystem` interfaces added by `wasmtime_wasi::p2::add_to_linker_async`
+/// with [`GuardedFilesystem`]. Must be called after it.
+pub(crate) fn add_to_linker(linker: &mut Linker<State>) -> Result<()> {
+    linker.allow_shadowing(true);
+    preopens::add_to_linker::<State, GuardedFilesystemData>(linker, guarded_filesystem)?;
+    types::add_to_linker::<State, GuardedFilesystemData>(linker, guarded_filesystem)?;
+    linker.allow_shadowing(false);
+    Ok(())
+}
+
+fn guarded_filesystem(state: &mut State) -> GuardedFilesystem<'_> {
+    let descriptor_paths = state.inner.descriptor_paths.clone();
+    let mount_hosts = state.inner.mount_hosts.clone();
+    let memory_mounts = state.inner.memory_mounts.clone();
+    let last_permission_error = state.inner.last_permission_error.clone();
+    GuardedFilesystem {
+        fs: state.filesystem(),
+        descriptor_paths,
+        mount_hosts,
+        memory_mounts,
+        last_permission_error,
+    }
+}
+
+struct GuardedFilesystemData;
+
+impl HasData for GuardedFilesystemData {
+    type Data<'a> = GuardedFilesystem<'a>;
+}
+
+/// A `wasi:filesystem` host that records storage denials before handing results to the guest
+pub(crate) struct GuardedFilesystem<'a> {
+    fs: WasiFilesystemCtxView<'a>,
+    descriptor_paths: DescriptorPaths,
+    mount_hosts: Arc<HashMap<String, PathBuf>>,
+    memory_mounts: Arc<Vec<ActiveMemoryMount>>,
+    last_permission_error: Arc<Mutex<Option<PermissionError>>>,
+}
+
+impl GuardedFilesystem<'_> {
+    fn target_path(&self, fd: &Resource<types::Descriptor>, path: &str) -> DescriptorPath {
+        let paths = self
+            .descriptor_paths
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match paths.get(&fd.rep()) {
+            Some(base) => base.join(path),
+            None => DescriptorPath {
+                guest: path.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The in-memory mount with a quota that `fd` lies on, if any
+    fn quota_mount(&self, fd: &Resource<types::Descriptor>) -> Option<ActiveMemoryMount> {
+        let index = self
+            .descriptor_paths
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&fd.rep())?
+            .memory_mount?;
+        self.memory_mounts
+            .get(index)
+            .filter(|mount| mount.quota.is_some())
+            .cloned()
+    }
+
+    /// Current size of the file behind `fd`
+    fn file_size(&self, fd: &Resource<types::Descriptor>) -> FsResult<u64> {
+        match self.fs.table.get(fd)? {
+            Descriptor::File(file) => Ok(file.file.metadata()?.len()),
+            Descriptor::Dir(_) => Err(ErrorCode::IsDirectory.into()),
+        }
+    }
+
+    /// Accounts for growing the file behind `fd` to at least `end` bytes if it lies on an
+    /// in-memory mount with a quota, failing with `insufficient-space` if the mount is full
+    fn reserve(
+        &self,
+        fd: &Resource<types::Descriptor>,
+        end: u64,
+    ) -> FsResult<Option<ActiveMemoryMount>> {
+        let Some(mount) = self.quota_mount(fd) else {
+            return Ok(None);
+        };
+        let growth = end.saturating_sub(self.file_size(fd)?);
+        if !mount.reserve(growth) {
+            return Err(ErrorCode::InsufficientSpace.into());
+        }
+        Ok(Some(mount))
+    }
+
+    /// Has the in-memory mount behind `fd` counted again after data was removed from it
+    fn release(&self, fd: &Resource<types::Descriptor>) {
+        if let Some(mount) = self.quota_mount(fd) {
+            mount.release();
+        }
+    }
+
+    /// Replaces `stream` with one that counts what it writes against the quota of the
+    /// in-memory mount behind `fd`. `position` is `None` for a stream that appends.
+    fn limit_stream(
+        &mut self,
+        fd: &Resource<types::Descriptor>,
+        stream: Resource<DynOutputStream>,
+        position: Option<u64>,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let Some(mount) = self.quota_mount(fd) else {
+            return Ok(stream);
+        };
+        let size = self.file_size(fd)?;
+        let inner = self.fs.table.delete(stream)?;
+        let limited: DynOutputStream =
+            Box::new(QuotaOutputStream::new(inner, mount, position, size));
+        Ok(self.fs.table.push(limited)?)
+    }
+
+    fn remember_path(&self, fd: &Resource<types::Descriptor>, path: DescriptorPath) {
+        if let Ok(mut paths) = self.descriptor_paths.lock() {
+            paths.insert(fd.rep(), path);
+        }
+    }
+
+    /// Records a storage denial if `result` failed for permission reasons
+    fn check<T>(&self, result: FsResult<T>, path: DescriptorPath, access: &str) -> FsResult<T> {
+        if let Err(err) = &result {
+            if err
+                .downcast_ref()
+                .copied()
+                .is_some_and(is_permission_denial)
+            {
+                record_storage_denial(&self.last_permission_error, path, access);
+            }
+        }
+        result
+    }
+}
+
+fn join_guest_path(base: Option<&str>, path: &str) -> String {
+    match base {
+        Some(base) if path.is_empty() || path == "." => base.to_string(),
+        Some(base) => format!("{}/{}", base.trim_end_matches('/'), path),
+        None => path.to_string(),
+    }
+}
+
+/// Resolves `.` and `..` segments without touching the filesystem, so a path that escaped its
+/// directory is reported as the location it actually pointed at
+fn normalize_path(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                Some(last) if *last != ".." => {
+                    parts.pop();
+                }
+                _ if absolute => {}
+                _ => parts.push(part),
+            },
+            _ => parts.push(part),
+        }
+    }
+    match (absolute, parts.is_empty()) {
+        (true, _) => format!("/{}", parts.join("/")),
+        (false, true) => ".".to_string(),
+        (false, false) => parts.join("/"),
+    }
+}
+
+fn is_permission_denial(code: ErrorCode) -> bool {
+    matches!(
+        code,
+        ErrorCode::NotPermitted | ErrorCode::Access | ErrorCode::ReadOnly
+    )
+}
+
+fn record_storage_denial(
+    last_permission_error: &Mutex<Option<PermissionError>>,
+    path: DescriptorPath,
+    access: &str,
+) {
+    let guest_path = normalize_path(&path.guest);
+    let host_path = path
+        .host
+        .map(|host| normalize_path(&host.to_string_lossy()));
+    tracing::warn!(
+        path = guest_path,
+        host_path,
+        access,
+        "Storage access denied by policy"
+    );
+    if let Ok(mut last) = last_permission_error.lock() {
+        *last = Some(PermissionError::StorageDenied {
+            path: guest_path,
+            host_path,
+            access_type: access.to_string(),
+        });
+    }
+}
+
+impl preopens::Host for GuardedFilesystem<'_> {
+    fn get_directories(&mut self) -> Result<Vec<(Resource<types::Descriptor>, String)>> {
+        let dirs = preopens::Host::get_directories(&mut self.fs)?;
+        for (fd, guest_path) in &dirs {
+            let host = self.mount_hosts.get(guest_path).cloned();
+            let memory_mount = self
+                .memory_mounts
+                .iter()
+                .position(|mount| &mount.guest_path == guest_path);
+            self.remember_path(
+                fd,
+                DescriptorPath {
+                    guest: guest_path.clone(),
+                    host,
+                    memory_mount,
+                },
+            );
+        }
+        Ok(dirs)
+    }
+}
+
+impl types::Host for GuardedFilesystem<'_> {
+    fn convert_error_code(&mut self, err: FsError) -> Result<ErrorCode> {
+        types::Host::convert_error_code(&mut self.fs, err)
+    }
+
+    fn filesystem_error_code(&mut self, err: Resource<anyhow::Error>) -> Result<Option<ErrorCode>> {
+        types::Host::filesystem_error_code(&mut self.fs, err)
+    }
+}
+
+impl HostDescriptor for GuardedFilesystem<'_> {
+    async fn advise(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        offset: types::Filesize,
+        len: types::Filesize,
+        advice: types::Advice,
+    ) -> FsResult<()> {
+        self.fs.advise(fd, offset, len, advice).await
+    }
+
+    async fn sync_data(&mut self, fd: Resource<types::Descriptor>) -> FsResult<()> {
+        self.fs.sync_data(fd).await
+    }
+
+    async fn get_flags(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+    ) -> FsResult<types::DescriptorFlags> {
+        self.fs.get_flags(fd).await
+    }
+
+    async fn get_type(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+    ) -> FsResult<types::DescriptorType> {
+        self.fs.get_type(fd).await
+    }
+
+    async fn set_size(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        size: types::Filesize,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, "");
+        let mount = self.reserve(&fd, size)?;
+        let result = self.fs.set_size(fd, size).await;
+        // A shrunk file frees space, so count the mount again
+        if let Some(mount) = mount {
+            mount.release();
+        }
+        self.check(result, target, WRITE)
+    }
+
+    async fn set_times(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        atim: types::NewTimestamp,
+        mtim: types::NewTimestamp,
+    ) -> FsResult<()> {
+        self.fs.set_times(fd, atim, mtim).await
+    }
+
+    async fn read(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        len: types::Filesize,
+        offset: types::Filesize,
+    ) -> FsResult<(Vec<u8>, bool)> {
+        let target = self.target_path(&fd, "");
+        let result = self.fs.read(fd, len, offset).await;
+        self.check(result, target, READ)
+    }
+
+    async fn write(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        buf: Vec<u8>,
+        offset: types::Filesize,
+    ) -> FsResult<types::Filesize> {
+        let target = self.target_path(&fd, "");
+        let mount = self.reserve(&fd, offset.saturating_add(buf.len() as u64))?;
+        let result = self.fs.write(fd, buf, offset).await;
+        if let (Err(_), Some(mount)) = (&result, mount) {
+            mount.release();
+        }
+        self.check(result, target, WRITE)
+    }
+
+    async fn read_directory(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+    ) -> FsResult<Resource<types::DirectoryEntryStream>> {
+        let target = self.target_path(&fd, "");
+        let result = self.fs.read_directory(fd).await;
+        self.check(result, target, READ)
+    }
+
+    async fn sync(&mut self, fd: Resource<types::Descriptor>) -> FsResult<()> {
+        self.fs.sync(fd).await
+    }
+
+    async fn create_directory_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &path);
+        let result = self.fs.create_directory_at(fd, path).await;
+        self.check(result, target, WRITE)
+    }
+
+    async fn stat(&mut self, fd: Resource<types::Descriptor>) -> FsResult<types::DescriptorStat> {
+        self.fs.stat(fd).await
+    }
+
+    async fn stat_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+    ) -> FsResult<types::DescriptorStat> {
+        let target = self.target_path(&fd, &path);
+        let result = self.fs.stat_at(fd, path_flags, path).await;
+        self.check(result, target, READ)
+    }
+
+    async fn set_times_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+        atim: types::NewTimestamp,
+        mtim: types::NewTimestamp,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &path);
+        let result = self.fs.set_times_at(fd, path_flags, path, atim, mtim).await;
+        self.check(result, target, WRITE)
+    }
+
+    async fn link_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        old_path_flags: types::PathFlags,
+        old_path: String,
+        new_descriptor: Resource<types::Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&new_descriptor, &new_path);
+        let mount = self.quota_mount(&new_descriptor);
+        let result = self
+            .fs
+            .link_at(fd, old_path_flags, old_path, new_descriptor, new_path)
+            .await;
+        if let Some(mount) = mount {
+            mount.release();
+        }
+        self.check(result, target, WRITE)
+    }
+
+    async fn open_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+        oflags: types::OpenFlags,
+        flags: types::DescriptorFlags,
+    ) -> FsResult<Resource<types::Descriptor>> {
+        let target = self.target_path(&fd, &path);
+        let access = if flags.contains(types::DescriptorFlags::WRITE)
+            || oflags.intersects(types::OpenFlags::CREATE | types::OpenFlags::TRUNCATE)
+        {
+            WRITE
+        } else {
+            READ
+        };
+
+        let mount = self
+            .quota_mount(&fd)
+            .filter(|_| oflags.contains(types::OpenFlags::TRUNCATE));
+        let result = self.fs.open_at(fd, path_flags, path, oflags, flags).await;
+        if let Ok(opened) = &result {
+            self.remember_path(opened, target.clone());
+        }
+        if let Some(mount) = mount {
+            mount.release();
+        }
+        self.check(result, target, access)
+    }
+
+    fn drop(&mut self, fd: Resource<types::Descriptor>) -> Result<()> {
+        if let Ok(mut paths) = self.descriptor_paths.lock() {
+            paths.remove(&fd.rep());
+        }
+        HostDescriptor::drop(&mut self.fs, fd)
+    }
+
+    async fn readlink_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path: String,
+    ) -> FsResult<String> {
+        let target = self.target_path(&fd, &path);
+        let result = self.fs.readlink_at(fd, path).await;
+        self.check(result, target, READ)
+    }
+
+    async fn remove_directory_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &path);
+        let mount = self.quota_mount(&fd);
+        let result = self.fs.remove_directory_at(fd, path).await;
+        if let Some(mount) = mount {
+            mount.release();
+        }
+        self.check(result, target, WRITE)
+    }
+
+    async fn rename_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        old_path: String,
+        new_fd: Resource<types::Descriptor>,
+        new_path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &old_path);
+        // A rename can replace a file or move data between mounts
+        self.release(&fd);
+        self.release(&new_fd);
+        let result = self.fs.rename_at(fd, old_path, new_fd, new_path).await;
+        self.check(result, target, WRITE)
+    }
+
+    async fn symlink_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        src_path: String,
+        dest_path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &dest_path);
+        let result = self.fs.symlink_at(fd, src_path, dest_path).await;
+        self.check(result, target, WRITE)
+    }
+
+    async fn unlink_file_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path: String,
+    ) -> FsResult<()> {
+        let target = self.target_path(&fd, &path);
+        let mount = self.quota_mount(&fd);
+        let result = self.fs.unlink_file_at(fd, path).await;
+        if let Some(mount) = mount {
+            mount.release();
+        }
+        self.check(result, target, WRITE)
+    }
+
+    fn read_via_stream(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        offset: types::Filesize,
+    ) -> FsResult<Resource<DynInputStream>> {
+        let target = self.target_path(&fd, "");
+        let result = self.fs.read_via_stream(fd, offset);
+        self.check(result, target, READ)
+    }
+
+    fn write_via_stream(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        offset: types::Filesize,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let target = self.target_path(&fd, "");
+        let result = match self
+            .fs
+            .write_via_stream(Resource::new_borrow(fd.rep()), offset)
+        {
+            Ok(stream) => self.limit_stream(&fd, stream, Some(offset)),
+            Err(e) => Err(e),
+        };
+        self.check(result, target, WRITE)
+    }
+
+    fn append_via_stream(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+    ) -> FsResult<Resource<DynOutputStream>> {
+        let target = self.target_path(&fd, "");
+        let result = match self.fs.append_via_stream(Resource::new_borrow(fd.rep())) {
+            Ok(stream) => self.limit_stream(&fd, stream, None),
+            Err(e) => Err(e),
+        };
+        self.check(result, target, WRITE)
+    }
+
+    async fn is_same_object(
+        &mut self,
+        a: Resource<types::Descriptor>,
+        b: Resource<types::Descriptor>,
+    ) -> Result<bool> {
+        self.fs.is_same_object(a, b).await
+    }
+
+    async fn metadata_hash(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+    ) -> FsResult<types::MetadataHashValue> {
+        self.fs.metadata_hash(fd).await
+    }
+
+    async fn metadata_hash_at(
+        &mut self,
+        fd: Resource<types::Descriptor>,
+        path_flags: types::PathFlags,
+        path: String,
+    ) -> FsResult<types::MetadataHashValue> {
+        self.fs.metadata_hash_at(fd, path_flags, path).await
+    }
+}
+
+impl HostDirectoryEntryStream for GuardedFilesystem<'_> {
+    async fn read_directory_entry(
+        &mut self,
+        stream: Resource<types::DirectoryEntryStream>,
+    ) -> FsResult<Option<types::DirectoryEntry>> {
+        self.fs.read_directory_entry(stream).await
+    }
+
+    fn drop(&mut self, stream: Resource<types::DirectoryEntryStream>) -> Result<()> {
+        HostDirectoryEntryStream::drop(&mut self.fs, stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_guest_path() {
+        assert_eq!(
+            join_guest_path(Some("/data"), "out/report.txt"),
+            "/data/out/report.txt"
+        );
+        assert_eq!(join_guest_path(Some("/data"), "."), "/data");
+        assert_eq!(join_guest_path(Some("/data/"), ""), "/data/");
+        assert_eq!(join_guest_path(Some("/"), "etc"), "/etc");
+        assert_eq!(join_guest_path(None, "orphan"), "orphan");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/data/../etc/passwd"), "/etc/passwd");
+        assert_eq!(normalize_path("/data/./out//x"), "/data/out/x");
+        assert_eq!(normalize_path("/../.."), "/");
+        assert_eq!(normalize_path("work/../../x"), "../x");
+        assert_eq!(normalize_path("work/.."), ".");
+    }
+
+    #[test]
+    fn test_descriptor_path_join_tracks_host() {
+        let root = DescriptorPath {
+            guest: "/data".to_string(),
+            host: Some(PathBuf::from("/srv/data")),
+            ..Default::default()
+        };
+        let target = root.join("../secret");
+        assert_eq!(target.guest, "/data/../secret");
+        assert_eq!(target.host, Some(PathBuf::from("/srv/data/../secret")));
+        assert_eq!(root.join(""), root);
+
+        let unmapped = DescriptorPath {
+            guest: "/".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(unmapped.join("etc/passwd").host, None);
+    }
+
+    #[test]
+    fn test_only_permission_errors_are_denials() {
+        assert!(is_permission_denial(ErrorCode::NotPermitted));
+        assert!(is_permission_denial(ErrorCode::Access));
+        assert!(is_permission_denial(ErrorCode::ReadOnly));
+        assert!(!is_permission_denial(ErrorCode::NoEntry));
+        assert!(!is_permission_denial(ErrorCode::Exist));
+    }
+
+    #[test]
+    fn test_record_storage_denial() {
+        let last = Mutex::new(None);
+        let target = DescriptorPath {
+            guest: "/data/../out.txt".to_string(),
+            host: Some(PathBuf::from("/srv/data/../out.txt")),
+            ..Default::default()
+        };
+        record_storage_denial(&last, target, WRITE);
+
+        match last.into_inner().unwrap() {
+            Some(PermissionError::StorageDenied {
+                path,
+                host_path,
+                access_type,
+            }) => {
+                assert_eq!(path, "/out.txt");
+                assert_eq!(host_path.as_deref(), Some("/srv/out.txt"));
+                assert_eq!(access_type, "write");
+            }
+            other => panic!("unexpected permission error: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_counted_against_memory_quota() -> Result<()> {
+        let template = crate::WasiStateTemplate {
+            memory_mounts: vec![crate::memory_mount::MemoryMount {
+                guest_path: "scratch".to_string(),
+                dir_perms: wasmtime_wasi::DirPerms::all(),
+                file_perms: wasmtime_wasi::FilePerms::all(),
+                quota: Some(16),
+                session_dir: None,
+            }],
+            ..Default::default()
+        };
+        let mut state = WassetteWasiState::new(template.build()?, Default::default())?;
+        let mut fs = guarded_filesystem(&mut state);
+
+        let (dir, _) = preopens::Host::get_directories(&mut fs)?
+            .into_iter()
+            .find(|(_, guest_path)| guest_path == "scratch")
+            .unwrap();
+        let file = fs
+            .open_at(
+                dir,
+                types::PathFlags::empty(),
+                "data".to_string(),
+                types::OpenFlags::CREATE,
+                types::DescriptorFlags::READ | types::DescriptorFlags::WRITE,
+            )
+            .await?;
+        let borrow = || Resource::<types::Descriptor>::new_borrow(file.rep());
+
+        assert_eq!(fs.write(borrow(), vec![0; 10], 0).await?, 10);
+        // Rewriting existing bytes only counts what extends past the end of the file
+        assert_eq!(fs.write(borrow(), vec![1; 4], 8).await?, 4);
+        let err = fs.write(borrow(), vec![2; 8], 12).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ErrorCode::InsufficientSpace)
+        ));
+
+        let stream = fs.append_via_stream(borrow())?;
+        let stream = fs.fs.table.get_mut(&stream)?;
+        stream.write(bytes::Bytes::from_static(b"0123"))?;
+        assert!(stream.write(bytes::Bytes::from_static(b"4")).is_err());
+
+        // Shrinking the file makes room again
+        fs.set_size(borrow(), 0).await?;
+        assert_eq!(fs.write(borrow(), vec![3; 16], 0).await?, 16);
+
+        let exceeded = state.inner.memory_quota_error().unwrap();
+        assert_eq!(exceeded.guest_path, "scratch");
+        assert_eq!(exceeded.used, 17);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_grants_are_recorded() -> Result<()> {
+        let template = crate::WasiStateTemplate {
+            unmapped_root: Some(Arc::new(tempfile::tempdir()?)),
+            ..Default::default()
+        };
+        let mut state = WassetteWasiState::new(template.build()?, Default::default())?;
+        let mut fs = guarded_filesystem(&mut state);
+
+        let (root, _) = preopens::Host::get_directories(&mut fs)?
+            .into_iter()
+            .find(|(_, guest_path)| guest_path == "/")
+            .unwrap();
+        let result = fs
+            .open_at(
+                root,
+                types::PathFlags::empty(),
+                "etc/passwd".to_string(),
+                types::OpenFlags::empty(),
+                types::DescriptorFlags::READ,
+            )
+            .await;
+        assert!(result.is_err());
+
+        match state.inner.last_permission_error.lock().unwrap().clone() {
+            Some(PermissionError::StorageDenied { path, .. }) => assert_eq!(path, "/etc/passwd"),
+            other => panic!("unexpected permission error: {other:?}"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Restrictions on the sources components and policies may be loaded from
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::loader::Loadable;
+
+/// Sources components and policies may be loaded from.
+///
+/// Each source type has its own allowlist. A source type without a list is unrestricted, and an
+/// empty list blocks it entirely. The default policy allows every source.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustPolicy {
+    /// OCI registries (`ghcr.io`) or namespaces within a registry (`ghcr.io/microsoft`) that
+    /// `oci://` references may point to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oci: Option<Vec<String>>,
+    /// HTTPS origins (`https://example.com`) or URL prefixes
+    /// (`https://example.com/releases/`) that `https://` URLs may start with. A redirect is
+    /// only followed when its target is allowed as well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https: Option<Vec<String>>,
+    /// Directories that `file://` paths must be inside of, after resolving symbolic links
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<Vec<PathBuf>>,
+    /// Require `oci://` and `https://` sources to be pinned to a digest, either with an
+    /// `@sha256:` OCI reference or an expected digest the download is verified against
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub require_digest: bool,
+}
+
+impl TrustPolicy {
+    /// Check that `uri` is pinned to a digest when digests are required. `expected_digest` is a
+    /// digest the caller verifies the download against. Whether the source is allowed at all is
+    /// checked by the loader, see [`Self::check_source`].
+    pub(crate) fn check_pinned<T: Loadable>(
+        &self,
+        uri: &str,
+        expected_digest: Option<&str>,
+    ) -> Result<()> {
+        let uri = uri.trim();
+        if !self.require_digest || expected_digest.is_some() {
+            return Ok(());
+        }
+        let pinned = match uri.split_once("://") {
+            Some(("oci", reference)) => reference
+                .parse::<oci_client::Reference>()
+                .is_ok_and(|reference| reference.digest().is_some()),
+            Some(("https", _)) => false,
+            _ => true,
+        };
+        if !pinned {
+            bail!(
+                "Untrusted {} source {}: remote sources must be pinned to a digest because trust.require_digest is set. Reference the OCI manifest by digest (oci://<registry>/<repository>@sha256:<digest>){}",
+                T::RESOURCE_TYPE,
+                uri,
+                if T::RESOURCE_TYPE == crate::loader::ComponentResource::RESOURCE_TYPE {
+                    " or provide the expected digest of the component"
+                } else {
+                    ""
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that `uri` points into one of the allowlisted sources for its scheme.
+    pub(crate) fn check_source<T: Loadable>(&self, uri: &str) -> Result<()> {
+        let uri = uri.trim();
+        let Some((scheme, reference)) = uri.split_once("://") else {
+            // Malformed references are reported by the loader
+            return Ok(());
+        };
+
+        match scheme {
+            "oci" => {
+                let Some(allowed) = &self.oci else {
+                    return Ok(());
+                };
+                let reference: oci_client::Reference =
+                    reference.parse().context("Failed to parse OCI reference")?;
+                let repository =
+                    format!("{}/{}", reference.registry(), reference.repository()).to_lowercase();
+                let trusted = allowed.iter().any(|entry| {
+                    let entry = entry
+                        .trim()
+                        .trim_start_matches("oci://")
+                        .trim_end_matches('/')
+                        .to_lowercase();
+                    repository == entry || repository.starts_with(&format!("{entry}/"))
+                });
+                if !trusted {
+                    bail!(
+                        "Untrusted {} source {}: repository {} is not in the trust.oci allowlist ({})",
+                        T::RESOURCE_TYPE,
+                        uri,
+                        repository,
+                        describe(allowed)
+                    );
+                }
+            }
+            "https" => {
+                let Some(allowed) = &self.https else {
+                    return Ok(());
+                };
+                let url = reqwest::Url::parse(uri)
+                    .with_context(|| format!("Failed to parse {} URL", T::RESOURCE_TYPE))?;
+                if !https_allowed(allowed, &url) {
+                    bail!(
+                        "Untrusted {} source {}: the URL is not below any entry of the trust.https allowlist ({})",
+                        T::RESOURCE_TYPE,
+                        uri,
+                        describe(allowed)
+                    );
+                }
+            }
+            "file" => {
+                let Some(allowed) = &self.local else {
+                    return Ok(());
+                };
+                let path = Path::new(reference).canonicalize().with_context(|| {
+                    format!(
+                        "Untrusted {} source {}: the path cannot be resolved",
+                        T::RESOURCE_TYPE,
+                        uri
+                    )
+                })?;
+                let trusted = allowed.iter().any(|dir| {
+                    let dir = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+                    path.starts_with(dir)
+                });
+                if !trusted {
+                    let dirs: Vec<String> = allowed
+                        .iter()
+                        .map(|dir| dir.display().to_string())
+                        .collect();
+                    bail!(
+                        "Untrusted {} source {}: {} is not inside a directory of the trust.local allowlist ({})",
+                        T::RESOURCE_TYPE,
+                        uri,
+                        path.display(),
+                        describe(&dirs)
+                    );
+                }
+            }
+            // Unsupported schemes are reported by the loader
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The redirect policy for HTTP clients that download components and policies. Without a
+    /// `trust.https` allowlist redirects are followed as usual. With one, every hop has to be
+    /// allowed, so an allowed URL cannot redirect a download to another source.
+    pub fn redirect_policy(&self) -> reqwest::redirect::Policy {
+        let Some(allowed) = self.https.clone() else {
+            return reqwest::redirect::Policy::default();
+        };
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error(format!("Too many redirects (more than {MAX_REDIRECTS})"))
+            } else if https_allowed(&allowed, attempt.url()) {
+                attempt.follow()
+            } else {
+                let message = format!(
+                    "Untrusted redirect to {}: the URL is not below any entry of the trust.https allowlist ({})",
+                    attempt.url(),
+                    describe(&allowed)
+                );
+                attempt.error(message)
+            }
+        })
+    }
+}
+
+/// Redirects followed before a download fails, the same limit reqwest applies by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether `url` is below an entry of the `trust.https` allowlist
+fn https_allowed(allowed: &[String], url: &reqwest::Url) -> bool {
+    allowed
+        .iter()
+        .filter_map(|entry| reqwest::Url::parse(entry.trim()).ok())
+        .any(|prefix| url_has_prefix(url, &prefix))
+}
+
+/// Whether `url` has the origin of `prefix` and a path at or below the path of `prefix`
+fn url_has_prefix(url: &reqwest::Url, prefix: &reqwest::Url) -> bool {
+    if url.scheme() != prefix.scheme()
+        || url.host_str() != prefix.host_str()
+        || url.port_or_known_default() != prefix.port_or_known_default()
+    {
+        return false;
+    }
+
+    let prefix_path = prefix.path().trim_end_matches('/');
+    prefix_path.is_empty()
+        || url.path() == prefix_path
+        || url
+            .path()
+            .strip_prefix(prefix_path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Allowlist entries for error messages
+fn describe(entries: &[String]) -> String {
+    if entries.is_empty() {
+        "empty, so this source type is blocked".to_string()
+    } else {
+        format!("allowed: {}", entries.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::{ComponentResource, PolicyResource};
+
+    #[test]
+    fn test_default_trust_policy_allows_everything() {
+        let policy = TrustPolicy::default();
+        for uri in [
+            "oci://ghcr.io/microsoft/time-server-js:latest",
+            "https://example.com/time.wasm",
+            "file:///does/not/exist.wasm",
+        ] {
+            assert!(
+                policy.check_source::<ComponentResource>(uri).is_ok(),
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_oci_allowlist() {
+        let policy = TrustPolicy {
+            oci: Some(vec![
+                "ghcr.io/microsoft".to_string(),
+                "docker.io".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let check = |uri| policy.check_source::<ComponentResource>(uri);
+
+        assert!(check("oci://ghcr.io/microsoft/time-server-js:latest").is_ok());
+        assert!(check("oci://ghcr.io/microsoft/tools/fetch@sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef").is_ok());
+        assert!(check("oci://docker.io/library/hello:1").is_ok());
+        // A namespace only matches whole path segments
+        let err = check("oci://ghcr.io/microsoft-fake/time:latest").unwrap_err();
+        assert!(err.to_string().contains("trust.oci allowlist"), "{err}");
+        assert!(check("oci://ghcr.io/other/time:latest").is_err());
+        // Other source types are not restricted
+        assert!(check("https://example.com/time.wasm").is_ok());
+    }
+
+    #[test]
+    fn test_https_allowlist() {
+        let policy = TrustPolicy {
+            https: Some(vec![
+                "https://example.com/releases/".to_string(),
+                "https://cdn.example.org".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let check = |uri| policy.check_source::<PolicyResource>(uri);
+
+        assert!(check("https://example.com/releases/v1/time.wasm").is_ok());
+        assert!(check("https://cdn.example.org/anything/policy.yaml").is_ok());
+        assert!(check("https://example.com/releases-evil/time.wasm").is_err());
+        assert!(check("https://example.com/releases/../private/time.wasm").is_err());
+        assert!(check("https://example.com:8443/releases/time.wasm").is_err());
+        assert!(check("https://example.com.evil.net/releases/time.wasm").is_err());
+    }
+
+    #[test]
+    fn test_local_allowlist() -> Result<()> {
+        let allowed = tempfile::tempdir()?;
+        let other = tempfile::tempdir()?;
+        let inside = allowed.path().join("time.wasm");
+        let outside = other.path().join("time.wasm");
+        std::fs::write(&inside, "wasm")?;
+        std::fs::write(&outside, "wasm")?;
+
+        let policy = TrustPolicy {
+            local: Some(vec![allowed.path().to_path_buf()]),
+            oci: Some(vec![]),
+            ..Default::default()
+        };
+        let check = |path: &Path| {
+            policy.check_source::<ComponentResource>(&format!("file://{}", path.display()))
+        };
+
+        assert!(check(&inside).is_ok());
+        assert!(check(&outside).is_err());
+        // Paths are resolved before they are compared
+        assert!(check(
+            &allowed
+                .path()
+                .join("..")
+                .join(other.path().file_name().unwrap())
+                .join("time.wasm")
+        )
+        .is_err());
+        assert!(check(&allowed.path().join("missing.wasm")).is_err());
+        #[cfg(unix)]
+        {
+            let link = allowed.path().join("link.wasm");
+            std::os::unix::fs::symlink(&outside, &link)?;
+            assert!(check(&link).is_err());
+        }
+
+        // An empty list blocks the source type
+        let err = policy
+            .check_source::<ComponentResource>("oci://ghcr.io/microsoft/time:latest")
+            .unwrap_err();
+        assert!(err.to_string().contains("blocked"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_digest() {
+        let policy = TrustPolicy {
+            require_digest: true,
+            ..Default::default()
+        };
+
+        assert!(policy
+            .check_pinned::<ComponentResource>("oci://ghcr.io/microsoft/time:latest", None)
+            .is_err());
+        assert!(policy
+            .check_pinned::<ComponentResource>(
+                "oci://ghcr.io/microsoft/time@sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+                None
+            )
+            .is_ok());
+        assert!(policy
+            .check_pinned::<ComponentResource>("https://example.com/time.wasm", None)
+            .is_err());
+        assert!(policy
+            .check_pinned::<ComponentResource>("https://example.com/time.wasm", Some("sha256:abc"))
+            .is_ok());
+        assert!(policy
+            .check_pinned::<PolicyResource>("https://example.com/policy.yaml", None)
+            .is_err());
+        assert!(policy
+            .check_pinned::<ComponentResource>("file:///opt/time.wasm", None)
+            .is_ok());
+    }
+
+    /// Serve `GET <path>` from `routes` as (status, location) pairs and count the requests
+    async fn redirect_server(
+        routes: &'static [(&'static str, u16, Option<&'static str>)],
+    ) -> Result<(String, std::sync::Arc<std::sync::Mutex<Vec<String>>>)> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        let requested = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = requested.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let Ok(n) = stream.read(&mut buf).await else {
+                    continue;
+                };
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                log.lock().unwrap().push(path.clone());
+                let response = match routes.iter().find(|(route, _, _)| *route == path) {
+                    Some((_, status, Some(location))) => format!(
+                        "HTTP/1.1 {status} Redirect\r\nLocation: {location}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    ),
+                    Some((_, status, None)) => format!(
+                        "HTTP/1.1 {status} OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        Ok((base, requested))
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_checked_against_https_allowlist() -> Result<()> {
+        let (base, requested) = redirect_server(&[
+            ("/allowed/moved", 302, Some("/allowed/file")),
+            ("/allowed/file", 200, None),
+            ("/allowed/escape", 302, Some("/elsewhere/file")),
+            ("/elsewhere/file", 200, None),
+        ])
+        .await?;
+        let policy = TrustPolicy {
+            https: Some(vec![format!("{base}/allowed/")]),
+            ..Default::default()
+        };
+        let client = reqwest::Client::builder()
+            .redirect(policy.redirect_policy())
+            .build()?;
+
+        let response = client.get(format!("{base}/allowed/moved")).send().await?;
+        assert_eq!(response.text().await?, "ok");
+
+        let err = client
+            .get(format!("{base}/allowed/escape"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("trust.https"), "{err:?}");
+        assert!(!requested
+            .lock()
+            .unwrap()
+            .contains(&"/elsewhere/file".to_string()));
+
+        // Without an allowlist redirects are followed as usual
+        let client = reqwest::Client::builder()
+            .redirect(TrustPolicy::default().redirect_policy())
+            .build()?;
+        let response = client.get(format!("{base}/allowed/escape")).send().await?;
+        assert_eq!(response.text().await?, "ok");
+
+        Ok(())
+    }
+}
@@ -4,13 +4,24 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use policy::{AccessType, PolicyDocument};
+use cap_rand::rngs::StdRng;
+use cap_rand::SeedableRng;
+use policy::{AccessType, ClockMode, ClockPermissions, PolicyDocument};
+use tempfile::TempDir;
 use wasmtime::component::ResourceTable;
+use wasmtime_wasi::p2::pipe::{MemoryInputPipe, MemoryOutputPipe};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView};
 use wasmtime_wasi_config::WasiConfigVariables;
 use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
 
+use crate::clocks::{FixedWallClock, FrozenMonotonicClock, OffsetWallClock, VirtualMonotonicClock};
+use crate::memory_mount::{
+    extract_memory_mounts, ActiveMemoryMount, MemoryMount, MemoryQuotaExceeded,
+};
+use crate::storage_guard::DescriptorPaths;
+
 /// Represents a permission-related error that occurred during component execution
 #[derive(Debug, Clone)]
 pub enum PermissionError {
@@ -23,8 +34,11 @@ pub enum PermissionError {
     },
     /// Storage access was denied for a specific path
     StorageDenied {
-        /// The path that was denied
+        /// The path that was denied, as the component sees it
         path: String,
+        /// The host path behind `path`, when it lies under a host directory the component was
+        /// given; otherwise the component's path is taken to be the host path
+        host_path: Option<String>,
         /// The access type that was requested (read/write)
         access_type: String,
     },
@@ -42,12 +56,22 @@ impl PermissionError {
                     component_id, uri, host, component_id, host
                 )
             }
-            PermissionError::StorageDenied { path, access_type } => {
+            PermissionError::StorageDenied {
+                path,
+                host_path,
+                access_type,
+            } => {
+                let target = host_path.as_ref().unwrap_or(path);
+                let uri = if target.contains("://") {
+                    target.clone()
+                } else {
+                    format!("fs://{}", target)
+                };
                 format!(
                     "Storage permission denied: Component '{}' attempted to {} '{}' but does not have permission.\n\n\
                     To grant storage access, use:\n  \
                     grant-storage-permission --component-id=\"{}\" --uri=\"{}\" --access=\"{}\"",
-                    component_id, access_type, path, component_id, path, access_type
+                    component_id, access_type, path, component_id, uri, access_type
                 )
             }
         }
@@ -93,8 +117,27 @@ pub struct WasiState {
     pub http: wasmtime_wasi_http::WasiHttpCtx,
     pub wasi_config_vars: WasiConfigVariables,
     pub resource_limiter: Option<CustomResourceLimiter>,
+    /// Maximum time a single call may run before it is interrupted
+    pub cpu_time_limit: Option<Duration>,
     /// Tracks the last permission error that occurred during execution
     pub last_permission_error: Arc<Mutex<Option<PermissionError>>>,
+    /// In-memory mounts preopened for this state, in preopen order; kept here so they outlive
+    /// the guest's use
+    pub memory_mounts: Arc<Vec<ActiveMemoryMount>>,
+    /// Guest and host paths of open filesystem descriptors, used to report storage denials with
+    /// the full path the component attempted to access
+    pub descriptor_paths: DescriptorPaths,
+    /// Host directory behind each `fs://` preopen, keyed by guest path
+    pub mount_hosts: Arc<HashMap<String, PathBuf>>,
+}
+
+impl WasiState {
+    /// The last write an in-memory mount refused because of its quota, if any
+    pub fn memory_quota_error(&self) -> Option<MemoryQuotaExceeded> {
+        self.memory_mounts
+            .iter()
+            .find_map(ActiveMemoryMount::quota_error)
+    }
 }
 
 impl wasmtime_wasi::WasiView for WasiState {
@@ -120,16 +163,21 @@ impl WasiStateTemplate {
     /// Creates a new `WasiState` from the template.
     pub fn build(&self) -> anyhow::Result<WasiState> {
         let mut ctx_builder = WasiCtxBuilder::new();
-        if self.allow_stdout {
-            ctx_builder.inherit_stdout();
-        }
-        if self.allow_stderr {
-            ctx_builder.inherit_stderr();
-        }
-        ctx_builder.inherit_args();
-        if self.allow_args {
-            ctx_builder.inherit_args();
+        if let Some(io) = &self.command_io {
+            ctx_builder.stdin(io.stdin.clone());
+            ctx_builder.stdout(io.stdout.clone());
+            ctx_builder.stderr(io.stderr.clone());
+        } else {
+            if self.allow_stdout {
+                ctx_builder.inherit_stdout();
+            }
+            if self.allow_stderr {
+                ctx_builder.inherit_stderr();
+            }
         }
+        // Never inherit the host's argv: it would expose wassette's own command line
+        // (including any `--env KEY=VALUE` values) to every component.
+        ctx_builder.args(&self.args);
         // Note(mossaka): removed ctx_builder.inherit_network() to implement deny-by-default network policy
         // For HTTP requests to work, we need to allow TCP and DNS lookups when there are network permissions
         // But HTTP-level filtering happens in WassetteWasiState::send_request
@@ -149,6 +197,41 @@ impl WasiStateTemplate {
                 preopened_dir.file_perms,
             )?;
         }
+        let mount_hosts = self
+            .preopened_dirs
+            .iter()
+            .map(|dir| (dir.guest_path.clone(), dir.host_path.clone()))
+            .collect();
+        let mut memory_mounts = Vec::with_capacity(self.memory_mounts.len());
+        for mount in &self.memory_mounts {
+            let dir = mount.dir_for_invocation()?;
+            ctx_builder.preopened_dir(
+                dir.path(),
+                mount.guest_path.as_str(),
+                mount.dir_perms,
+                mount.file_perms,
+            )?;
+            memory_mounts.push(ActiveMemoryMount {
+                guest_path: mount.guest_path.clone(),
+                quota: mount.quota,
+                dir,
+                exceeded: Arc::default(),
+            });
+        }
+        let root_is_mounted = self
+            .preopened_dirs
+            .iter()
+            .map(|dir| dir.guest_path.as_str())
+            .chain(self.memory_mounts.iter().map(|m| m.guest_path.as_str()))
+            .any(|guest_path| guest_path.trim_end_matches('/').is_empty());
+        if let Some(dir) = self.unmapped_root.as_ref().filter(|_| !root_is_mounted) {
+            ctx_builder.preopened_dir(
+                dir.path(),
+                "/",
+                wasmtime_wasi::DirPerms::empty(),
+                wasmtime_wasi::FilePerms::empty(),
+            )?;
+        }
 
         // Inject forwarded config variables as real WASI environment variables so that
         // component code using std::env::var can observe them.
@@ -156,6 +239,29 @@ impl WasiStateTemplate {
             ctx_builder.env(k, v);
         }
 
+        if let Some(clock) = &self.clock {
+            match clock.mode {
+                ClockMode::Real => {}
+                ClockMode::Fixed => {
+                    ctx_builder.wall_clock(FixedWallClock::new(clock.time.unwrap_or_default()));
+                    ctx_builder.monotonic_clock(FrozenMonotonicClock);
+                }
+                ClockMode::Offset => {
+                    ctx_builder.wall_clock(OffsetWallClock::new(clock.offset.unwrap_or_default()));
+                }
+                ClockMode::Deny => {
+                    ctx_builder.wall_clock(FixedWallClock::new(0));
+                    ctx_builder.monotonic_clock(VirtualMonotonicClock::new());
+                }
+            }
+        }
+
+        if let Some(seed) = self.random_seed {
+            ctx_builder.secure_random(StdRng::seed_from_u64(seed));
+            ctx_builder.insecure_random(StdRng::seed_from_u64(seed.wrapping_add(1)));
+            ctx_builder.insecure_random_seed(seed.into());
+        }
+
         Ok(WasiState {
             ctx: ctx_builder.build(),
             table: wasmtime_wasi::ResourceTable::default(),
@@ -165,7 +271,11 @@ impl WasiStateTemplate {
                 .store_limits
                 .as_ref()
                 .map(|limits| CustomResourceLimiter::new(limits.clone())),
+            cpu_time_limit: self.cpu_time_limit,
             last_permission_error: Arc::new(Mutex::new(None)),
+            memory_mounts: Arc::new(memory_mounts),
+            descriptor_paths: DescriptorPaths::default(),
+            mount_hosts: Arc::new(mount_hosts),
         })
     }
 }
@@ -187,6 +297,34 @@ pub struct NetworkPermissions {
     pub allow_ip_name_lookup: bool,
 }
 
+/// Maximum number of bytes captured from each of stdout and stderr for a command invocation
+pub(crate) const COMMAND_OUTPUT_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// In-memory standard streams for a single invocation of a WASI CLI command component.
+///
+/// When set on a [`WasiStateTemplate`], stdin is fed from memory and stdout/stderr are captured
+/// instead of being inherited from the host process.
+#[derive(Clone)]
+pub struct CommandIo {
+    /// Data provided on the guest's standard input
+    pub stdin: MemoryInputPipe,
+    /// Captured standard output
+    pub stdout: MemoryOutputPipe,
+    /// Captured standard error
+    pub stderr: MemoryOutputPipe,
+}
+
+impl CommandIo {
+    /// Creates streams that feed `stdin` to the guest and capture its output
+    pub fn new(stdin: impl Into<Vec<u8>>) -> Self {
+        Self {
+            stdin: MemoryInputPipe::new(stdin.into()),
+            stdout: MemoryOutputPipe::new(COMMAND_OUTPUT_CAPACITY),
+            stderr: MemoryOutputPipe::new(COMMAND_OUTPUT_CAPACITY),
+        }
+    }
+}
+
 /// A template for the wasi state
 /// this includes the wasmtime_wasi, wasmtime_wasi_config and wasmtime_wasi_http states
 #[derive(Clone)]
@@ -195,20 +333,37 @@ pub struct WasiStateTemplate {
     pub allow_stdout: bool,
     /// Whether to allow stderr access
     pub allow_stderr: bool,
-    /// Whether to allow command line arguments access
-    pub allow_args: bool,
+    /// Command line arguments (including the program name) passed to the guest. Empty unless
+    /// set by the policy or a command invocation.
+    pub args: Vec<String>,
+    /// In-memory standard streams used instead of the host's stdio
+    pub command_io: Option<CommandIo>,
     /// Network permissions configuration
     pub network_perms: NetworkPermissions,
     /// Configuration variables for wasmtime_wasi_config
     pub config_vars: HashMap<String, String>,
+    /// Environment variable keys the policy allows, which command invocations may set
+    pub allowed_env_keys: HashSet<String>,
     /// Preopened directories for filesystem access
     pub preopened_dirs: Vec<PreopenedDir>,
+    /// In-memory (`mem://`) directories mounted into the guest
+    pub memory_mounts: Vec<MemoryMount>,
     /// Allowed network hosts for HTTP requests
     pub allowed_hosts: HashSet<String>,
     /// Memory limit in bytes for the component
     pub memory_limit: Option<u64>,
     /// Store limits for wasmtime (built from memory_limit)
     pub store_limits: Option<wasmtime::StoreLimits>,
+    /// Maximum time a single call may run before it is interrupted
+    pub cpu_time_limit: Option<Duration>,
+    /// Clock virtualization; `None` exposes the host clocks
+    pub clock: Option<ClockPermissions>,
+    /// Seed for deterministic random number generation; `None` uses host entropy
+    pub random_seed: Option<u64>,
+    /// Empty directory preopened at `/` without permissions when the policy does not mount
+    /// `/`, so accesses outside every granted directory reach the host and are reported as
+    /// storage denials. Shared by every template of a lifecycle manager.
+    pub unmapped_root: Option<Arc<TempDir>>,
 }
 
 impl Default for WasiStateTemplate {
@@ -216,13 +371,20 @@ impl Default for WasiStateTemplate {
         Self {
             allow_stdout: true,
             allow_stderr: true,
-            allow_args: true,
+            args: Vec::new(),
+            command_io: None,
             network_perms: NetworkPermissions::default(),
             config_vars: HashMap::new(),
+            allowed_env_keys: HashSet::new(),
             preopened_dirs: Vec::new(),
+            memory_mounts: Vec::new(),
             allowed_hosts: HashSet::new(),
             memory_limit: None,
             store_limits: None,
+            cpu_time_limit: None,
+            clock: None,
+            random_seed: None,
+            unmapped_root: None,
         }
     }
 }
@@ -235,10 +397,16 @@ pub fn create_wasi_state_template_from_policy(
     secrets: Option<&HashMap<String, String>>,
 ) -> anyhow::Result<WasiStateTemplate> {
     let env_vars = extract_env_vars(policy, environment_vars, secrets)?;
+    let allowed_env_keys = extract_allowed_env_keys(policy);
     let network_perms = extract_network_perms(policy);
     let preopened_dirs = extract_storage_permissions(policy, component_dir)?;
+    let memory_mounts = extract_memory_mounts(policy)?;
     let allowed_hosts = extract_allowed_hosts(policy);
     let memory_limit = extract_memory_limit(policy)?;
+    let cpu_time_limit = extract_cpu_time_limit(policy);
+    let args = extract_args(policy);
+    let clock = extract_clock(policy);
+    let random_seed = extract_random_seed(policy);
     let store_limits = memory_limit
         .map(|limit| -> anyhow::Result<wasmtime::StoreLimits> {
             let limit_usize = limit.try_into().map_err(|_| {
@@ -251,12 +419,18 @@ pub fn create_wasi_state_template_from_policy(
         .transpose()?;
 
     Ok(WasiStateTemplate {
+        args,
         network_perms,
         config_vars: env_vars,
+        allowed_env_keys,
         preopened_dirs,
+        memory_mounts,
         allowed_hosts,
         memory_limit,
         store_limits,
+        cpu_time_limit,
+        clock,
+        random_seed,
         ..Default::default()
     })
 }
@@ -291,6 +465,34 @@ pub(crate) fn extract_env_vars(
     Ok(env_vars)
 }
 
+/// Environment variable keys listed in the policy's allow list
+pub(crate) fn extract_allowed_env_keys(policy: &PolicyDocument) -> HashSet<String> {
+    policy
+        .permissions
+        .environment
+        .as_ref()
+        .and_then(|env_perms| env_perms.allow.as_ref())
+        .map(|allow| {
+            allow
+                .iter()
+                .map(|env_allow| env_allow.key.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn extract_args(policy: &PolicyDocument) -> Vec<String> {
+    policy.args.clone().unwrap_or_default()
+}
+
+pub(crate) fn extract_clock(policy: &PolicyDocument) -> Option<ClockPermissions> {
+    policy.permissions.clock.clone()
+}
+
+pub(crate) fn extract_random_seed(policy: &PolicyDocument) -> Option<u64> {
+    policy.permissions.random.as_ref().and_then(|r| r.seed)
+}
+
 pub(crate) fn extract_network_perms(policy: &PolicyDocument) -> NetworkPermissions {
     if let Some(network_perms) = &policy.permissions.network {
         let has_network_perms =
@@ -333,11 +535,16 @@ pub(crate) fn extract_storage_permissions(
     if let Some(storage) = &policy.permissions.storage {
         if let Some(allow) = &storage.allow {
             for storage_permission in allow {
-                if storage_permission.uri.starts_with("fs://") {
-                    let uri = storage_permission.uri.strip_prefix("fs://").unwrap();
-                    let path = Path::new(uri);
+                if let Some(uri) = storage_permission.uri.strip_prefix("fs://") {
+                    let path = expand_host_path(uri)?;
                     let (file_perms, dir_perms) = calculate_permissions(&storage_permission.access);
-                    let guest_path = path.to_string_lossy().to_string();
+                    // Without an explicit mount the guest sees the path as written in the
+                    // policy. Validation requires a mount for paths with `~` or variables, so
+                    // expanded values from the host never reach the component
+                    let guest_path = storage_permission
+                        .mount
+                        .clone()
+                        .unwrap_or_else(|| uri.to_string());
                     let host_path = component_dir.join(path);
                     preopened_dirs.push(PreopenedDir {
                         host_path,
@@ -352,6 +559,74 @@ pub(crate) fn extract_storage_permissions(
     Ok(preopened_dirs)
 }
 
+/// Host environment variables that storage paths may reference
+const EXPANDABLE_PATH_VARS: &[&str] = &[
+    "HOME",
+    "TMPDIR",
+    "XDG_CACHE_HOME",
+    "XDG_CONFIG_HOME",
+    "XDG_DATA_HOME",
+    "XDG_STATE_HOME",
+    "XDG_RUNTIME_DIR",
+];
+
+/// Expands a leading `~` to the user's home directory and `$VAR` / `${VAR}` references to the
+/// values of the host's environment variables. Only the variables in [`EXPANDABLE_PATH_VARS`]
+/// are expanded, so a policy cannot point a mount at a location chosen by arbitrary host state.
+pub(crate) fn expand_host_path(path: &str) -> anyhow::Result<PathBuf> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+
+    if let Some(after_tilde) = rest.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with('/') {
+            let home = etcetera::home_dir()
+                .map_err(|e| anyhow::anyhow!("Cannot expand '~' in '{}': {}", path, e))?;
+            expanded.push_str(&home.to_string_lossy());
+            rest = after_tilde;
+        }
+    }
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("Unterminated '${{' in storage path '{}'", path))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            anyhow::bail!("Empty variable name in storage path '{}'", path);
+        }
+        if !EXPANDABLE_PATH_VARS.contains(&name) {
+            anyhow::bail!(
+                "Environment variable '{}' cannot be used in storage path '{}'; supported variables are {}",
+                name,
+                path,
+                EXPANDABLE_PATH_VARS.join(", ")
+            );
+        }
+        let value = std::env::var(name).map_err(|_| {
+            anyhow::anyhow!(
+                "Environment variable '{}' used in storage path '{}' is not set",
+                name,
+                path
+            )
+        })?;
+        expanded.push_str(&value);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+
+    Ok(PathBuf::from(expanded))
+}
+
 pub(crate) fn calculate_permissions(
     access_types: &[AccessType],
 ) -> (wasmtime_wasi::FilePerms, wasmtime_wasi::DirPerms) {
@@ -378,6 +653,19 @@ pub(crate) fn calculate_permissions(
     (file_perms, dir_perms)
 }
 
+/// Extract the per-call time limit from the policy document. Despite its `cpu_time_ms` name the
+/// limit counts wall-clock time, including time spent blocked in host calls.
+pub(crate) fn extract_cpu_time_limit(policy: &PolicyDocument) -> Option<Duration> {
+    policy
+        .permissions
+        .resources
+        .as_ref()?
+        .limits
+        .as_ref()?
+        .cpu_time_ms
+        .map(Duration::from_millis)
+}
+
 /// Extract memory limit from the policy document
 pub(crate) fn extract_memory_limit(policy: &PolicyDocument) -> anyhow::Result<Option<u64>> {
     if let Some(resources) = &policy.permissions.resources {
@@ -652,6 +940,98 @@ permissions:
         );
     }
 
+    #[test]
+    fn test_extract_storage_permissions_with_mount() {
+        let temp_dir = TempDir::new().unwrap();
+        let host_dir = temp_dir.path().join("host-data");
+        let yaml_content = format!(
+            r#"
+version: "1.0"
+description: "Policy with explicit mount paths"
+permissions:
+  storage:
+    allow:
+      - uri: "fs://{}"
+        access: ["read"]
+        mount: "/data"
+"#,
+            host_dir.display()
+        );
+        let policy = PolicyParser::parse_str(&yaml_content).unwrap();
+
+        let preopened_dirs = extract_storage_permissions(&policy, temp_dir.path()).unwrap();
+        assert_eq!(preopened_dirs.len(), 1);
+        assert_eq!(preopened_dirs[0].guest_path, "/data");
+        assert_eq!(preopened_dirs[0].host_path, host_dir);
+    }
+
+    #[test]
+    fn test_expand_host_path() {
+        temp_env::with_vars(
+            [
+                ("XDG_DATA_HOME", Some("/srv/wassette")),
+                ("XDG_CACHE_HOME", None),
+                ("WASSETTE_TEST_ROOT", Some("/srv/other")),
+            ],
+            || {
+                assert_eq!(
+                    expand_host_path("$XDG_DATA_HOME/data").unwrap(),
+                    PathBuf::from("/srv/wassette/data")
+                );
+                assert_eq!(
+                    expand_host_path("${XDG_DATA_HOME}-cache/x").unwrap(),
+                    PathBuf::from("/srv/wassette-cache/x")
+                );
+                assert_eq!(
+                    expand_host_path("relative/path").unwrap(),
+                    PathBuf::from("relative/path")
+                );
+                assert!(expand_host_path("$XDG_CACHE_HOME/data").is_err());
+                assert!(expand_host_path("${XDG_DATA_HOME").is_err());
+                assert!(expand_host_path("/tmp/$/x").is_err());
+                // Variables outside the allowlist are rejected even when set
+                let err = expand_host_path("$WASSETTE_TEST_ROOT/data").unwrap_err();
+                assert!(err.to_string().contains("cannot be used in storage path"));
+            },
+        );
+
+        let home = etcetera::home_dir().unwrap();
+        assert_eq!(expand_host_path("~").unwrap(), home);
+        assert_eq!(expand_host_path("~/work").unwrap(), home.join("work"));
+        // Only a leading `~/` refers to the home directory
+        assert_eq!(
+            expand_host_path("~user/work").unwrap(),
+            PathBuf::from("~user/work")
+        );
+    }
+
+    #[test]
+    fn test_host_dependent_path_needs_mount() {
+        let temp_dir = TempDir::new().unwrap();
+        let policy = |mount: &str| {
+            format!(
+                r#"
+version: "1.0"
+permissions:
+  storage:
+    allow:
+      - uri: "fs://~/data"
+        access: ["read"]
+{mount}"#
+            )
+        };
+
+        assert!(PolicyParser::parse_str(policy("")).is_err());
+
+        let policy = PolicyParser::parse_str(policy("        mount: \"/data\"\n")).unwrap();
+        let preopened_dirs = extract_storage_permissions(&policy, temp_dir.path()).unwrap();
+        assert_eq!(preopened_dirs[0].guest_path, "/data");
+        assert_eq!(
+            preopened_dirs[0].host_path,
+            etcetera::home_dir().unwrap().join("data")
+        );
+    }
+
     #[test]
     fn test_extract_storage_permissions_skips_non_fs_uri() {
         let temp_dir = TempDir::new().unwrap();
@@ -754,10 +1134,130 @@ permissions:
         assert!(template.preopened_dirs.is_empty());
         assert!(template.allow_stdout);
         assert!(template.allow_stderr);
-        assert!(template.allow_args);
+        assert!(template.args.is_empty());
         assert_eq!(template.memory_limit, None);
     }
 
+    #[test]
+    fn test_extract_args_from_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let env_vars = HashMap::new();
+
+        let policy = create_policy_without_permissions();
+        assert!(extract_args(&policy).is_empty());
+
+        let yaml_content = r#"
+version: "1.0"
+description: "Policy with explicit arguments"
+permissions: {}
+args: ["tool", "--verbose"]
+"#;
+        let policy = PolicyParser::parse_str(yaml_content).unwrap();
+        let template =
+            create_wasi_state_template_from_policy(&policy, temp_dir.path(), &env_vars, None)
+                .unwrap();
+        assert_eq!(template.args, vec!["tool", "--verbose"]);
+    }
+
+    #[test]
+    fn test_extract_clock_and_random_from_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let env_vars = HashMap::new();
+
+        let policy = create_policy_without_permissions();
+        assert!(extract_clock(&policy).is_none());
+        assert!(extract_random_seed(&policy).is_none());
+
+        let yaml_content = r#"
+version: "1.0"
+description: "Policy with virtualized time and randomness"
+permissions:
+  clock:
+    mode: fixed
+    time: 1700000000
+  random:
+    seed: 7
+"#;
+        let policy = PolicyParser::parse_str(yaml_content).unwrap();
+        let template =
+            create_wasi_state_template_from_policy(&policy, temp_dir.path(), &env_vars, None)
+                .unwrap();
+        let clock = template.clock.as_ref().unwrap();
+        assert_eq!(clock.mode, ClockMode::Fixed);
+        assert_eq!(clock.time, Some(1_700_000_000));
+        assert_eq!(template.random_seed, Some(7));
+        assert!(template.build().is_ok());
+    }
+
+    #[test]
+    fn test_build_preopens_memory_mounts() {
+        let temp_dir = TempDir::new().unwrap();
+        let yaml_content = r#"
+version: "1.0"
+description: "Policy with in-memory mounts"
+permissions:
+  storage:
+    allow:
+      - uri: "mem://scratch"
+        access: ["read", "write"]
+      - uri: "mem://session"
+        access: ["read", "write"]
+        memory:
+          scope: session
+"#;
+        let policy = PolicyParser::parse_str(yaml_content).unwrap();
+        let template =
+            create_wasi_state_template_from_policy(&policy, temp_dir.path(), &HashMap::new(), None)
+                .unwrap();
+        assert!(template.preopened_dirs.is_empty());
+        assert_eq!(template.memory_mounts.len(), 2);
+
+        let first = template.build().unwrap();
+        let second = template.build().unwrap();
+        assert_ne!(
+            first.memory_mounts[0].dir.path(),
+            second.memory_mounts[0].dir.path()
+        );
+        assert_eq!(
+            first.memory_mounts[1].dir.path(),
+            second.memory_mounts[1].dir.path()
+        );
+        assert!(first.memory_quota_error().is_none());
+    }
+
+    #[test]
+    fn test_extract_cpu_time_limit() {
+        let policy = PolicyParser::parse_str(
+            r#"
+version: "1.0"
+permissions:
+  resources:
+    limits:
+      cpu_time_ms: 1500
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            extract_cpu_time_limit(&policy),
+            Some(Duration::from_millis(1500))
+        );
+
+        let template = create_wasi_state_template_from_policy(
+            &policy,
+            Path::new("/tmp"),
+            &HashMap::new(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            template.build().unwrap().cpu_time_limit,
+            Some(Duration::from_millis(1500))
+        );
+
+        let policy = PolicyParser::parse_str("version: \"1.0\"\npermissions: {}\n").unwrap();
+        assert_eq!(extract_cpu_time_limit(&policy), None);
+    }
+
     #[test]
     fn test_extract_memory_limit() {
         // Test with k8s-style memory limit
@@ -0,0 +1,37 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! A minimal WASI CLI command used to exercise command component support.
+//!
+//! Echoes its arguments to stdout, copies stdin to stdout, prints the value of
+//! `ECHO_PREFIX` to stderr when set, and exits with a failure status when
+//! `--fail` is passed.
+
+use std::io::{Read, Write};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let fail = args.iter().any(|arg| arg == "--fail");
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != "--fail")
+        .collect();
+
+    let mut stdin = String::new();
+    let _ = std::io::stdin().read_to_string(&mut stdin);
+
+    let mut stdout = std::io::stdout();
+    writeln!(stdout, "{}", words.join(" ")).unwrap();
+    write!(stdout, "{stdin}").unwrap();
+    stdout.flush().unwrap();
+
+    if let Ok(prefix) = std::env::var("ECHO_PREFIX") {
+        eprintln!("{prefix}");
+    }
+
+    if fail {
+        std::process::exit(1);
+    }
+}
@@ -16,7 +16,8 @@ use mcp_server::tools::{
     handle_revoke_environment_variable_permission, handle_revoke_network_permission,
     handle_revoke_storage_permission,
 };
-use mcp_server::LifecycleManager;
+use mcp_server::{ComponentRegistry, LifecycleManager};
+use policy::PolicyDocument;
 use rmcp::model::CallToolRequestParam;
 use serde_json::{Map, Value};
 
@@ -92,19 +93,18 @@ pub async fn create_lifecycle_manager(component_dir: Option<PathBuf>) -> Result<
                 PathBuf::from("./secrets")
             }),
             environment_vars: std::collections::HashMap::new(),
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: Default::default(),
+            trust: Default::default(),
             bind_address: "127.0.0.1:9001".to_string(),
+            registries: vec![],
+            registry_cache_dir: PathBuf::new(),
+            retained_versions: wassette::DEFAULT_RETAINED_VERSIONS,
+            precompiled_cache_dir: None,
         }
     } else {
-        config::Config::from_serve(&crate::commands::Serve {
-            component_dir: None,
-            transport: Default::default(),
-            env_vars: vec![],
-            env_file: None,
-            disable_builtin_tools: false,
-            bind_address: None,
-            manifest: None,
-        })
-        .context("Failed to load configuration")?
+        load_default_config()?
     };
 
     // Use unloaded manager for fast CLI startup, but preserve custom secrets dir
@@ -112,15 +112,76 @@ pub async fn create_lifecycle_manager(component_dir: Option<PathBuf>) -> Result<
         component_dir,
         secrets_dir,
         environment_vars,
+        inherit_env_without_policy,
+        gc_on_startup: _,
+        integrity_mode: _,
+        trust,
         bind_address: _,
+        registries: _,
+        registry_cache_dir: _,
+        retained_versions,
+        precompiled_cache_dir,
     } = config;
 
-    LifecycleManager::builder(component_dir)
+    let http_client = reqwest::Client::builder()
+        .redirect(trust.redirect_policy())
+        .build()?;
+
+    let mut builder = LifecycleManager::builder(component_dir)
         .with_environment_vars(environment_vars)
+        .with_env_inheritance_without_policy(inherit_env_without_policy)
+        .with_retained_versions(retained_versions)
+        .with_trust_policy(trust)
         .with_secrets_dir(secrets_dir)
         .with_oci_client(oci_client::Client::default())
-        .with_http_client(reqwest::Client::default())
-        .with_eager_loading(false)
-        .build()
+        .with_http_client(http_client)
+        .with_eager_loading(false);
+    if let Some(dir) = precompiled_cache_dir {
+        builder = builder.with_precompiled_cache_dir(dir);
+    }
+    builder.build().await
+}
+
+/// Create the component registry from the configured sources
+///
+/// `extra_sources` come from the command line and take priority over the configured ones.
+pub fn create_component_registry(extra_sources: &[String]) -> Result<ComponentRegistry> {
+    let mut config = load_default_config()?;
+    config
+        .registries
+        .splice(0..0, extra_sources.iter().cloned());
+    config.component_registry()
+}
+
+/// Load the configuration the server would use, without any CLI overrides
+fn load_default_config() -> Result<config::Config> {
+    config::Config::from_serve(&crate::commands::Serve {
+        component_dir: None,
+        transport: Default::default(),
+        env_vars: vec![],
+        env_file: None,
+        disable_builtin_tools: false,
+        inherit_env_without_policy: false,
+        gc_on_startup: false,
+        integrity_mode: None,
+        bind_address: None,
+        manifest: None,
+        reconcile: false,
+        auto_update_interval: None,
+    })
+    .context("Failed to load configuration")
+}
+
+/// Attach `policy` to a loaded component. `source` is the registry index that declared it, which
+/// has to pass the trust policy.
+pub async fn attach_declared_policy(
+    lifecycle_manager: &LifecycleManager,
+    component_id: &str,
+    policy: &PolicyDocument,
+    source: Option<&str>,
+) -> Result<()> {
+    lifecycle_manager
+        .attach_policy_document(component_id, policy, source)
         .await
+        .with_context(|| format!("Failed to attach policy to component {component_id}"))
 }
@@ -26,6 +26,19 @@ pub enum Shell {
     Elvish,
 }
 
+/// What the server does at startup with components whose files changed outside wassette
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrityMode {
+    /// Log a warning and load the component anyway
+    #[default]
+    Warn,
+    /// Refuse to start until the changes are accepted or the components reinstalled
+    Refuse,
+    /// Move the component's files to the quarantine directory and start without it
+    Quarantine,
+}
+
 #[derive(Parser, Debug)]
 #[command(
     name = "wassette-mcp-server",
@@ -89,6 +102,29 @@ pub enum Commands {
         #[command(subcommand)]
         command: RegistryCommands,
     },
+    /// Manage provisioning manifests.
+    Manifest {
+        #[command(subcommand)]
+        command: ManifestCommands,
+    },
+    /// Remove orphaned artifacts from the component directory.
+    #[command(after_help = "EXAMPLES:
+    # Show what would be removed
+    wassette gc --dry-run
+
+    # Remove orphaned artifacts
+    wassette gc")]
+    Gc {
+        /// Report what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Output format
+        #[arg(short = 'o', long = "output-format", default_value = "json")]
+        output_format: OutputFormat,
+    },
     /// Generate shell completion scripts.
     Autocomplete {
         /// Shell type to generate completions for
@@ -119,6 +155,33 @@ pub struct Run {
     #[arg(long)]
     #[serde(default)]
     pub disable_builtin_tools: bool,
+
+    /// Give components without a policy every configured environment variable (legacy behavior).
+    /// By default they receive an empty environment.
+    #[arg(long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inherit_env_without_policy: bool,
+
+    /// Remove orphaned artifacts from the component directory before loading components
+    #[arg(long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub gc_on_startup: bool,
+
+    /// What to do with components whose wasm or policy file was changed outside wassette.
+    /// Defaults to warn
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity_mode: Option<IntegrityMode>,
+
+    /// Path to provisioning manifest to apply before the server starts
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+
+    /// Unload components the manifest does not list and rewrite drifted policies
+    #[arg(long, requires = "manifest")]
+    #[serde(skip)]
+    pub reconcile: bool,
 }
 
 /// Configuration for serving remotely over HTTP transports
@@ -147,6 +210,23 @@ pub struct Serve {
     #[serde(default)]
     pub disable_builtin_tools: bool,
 
+    /// Give components without a policy every configured environment variable (legacy behavior).
+    /// By default they receive an empty environment.
+    #[arg(long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub inherit_env_without_policy: bool,
+
+    /// Remove orphaned artifacts from the component directory before loading components
+    #[arg(long)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub gc_on_startup: bool,
+
+    /// What to do with components whose wasm or policy file was changed outside wassette.
+    /// Defaults to warn
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity_mode: Option<IntegrityMode>,
+
     /// Bind address for HTTP-based transports (SSE and StreamableHttp). Defaults to 127.0.0.1:9001
     #[arg(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -156,6 +236,17 @@ pub struct Serve {
     #[arg(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub manifest: Option<PathBuf>,
+
+    /// Unload components the manifest does not list and rewrite drifted policies
+    #[arg(long, requires = "manifest")]
+    #[serde(skip)]
+    pub reconcile: bool,
+
+    /// Check every SECONDS whether components installed from an OCI tag have a newer version,
+    /// and update them. Disabled by default.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    #[serde(skip)]
+    pub auto_update_interval: Option<u64>,
 }
 
 /// HTTP transport options for the Serve command
@@ -216,6 +307,79 @@ pub enum ComponentCommands {
         #[arg(short = 'o', long = "output-format", default_value = "json")]
         output_format: OutputFormat,
     },
+    /// List the installed version of a component and the previous versions kept for rollback.
+    Versions {
+        /// Component ID to list versions for
+        id: String,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Output format
+        #[arg(short = 'o', long = "output-format", default_value = "json")]
+        output_format: OutputFormat,
+    },
+    /// List components installed from an OCI tag that now points to a newer version.
+    Outdated {
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Output format
+        #[arg(short = 'o', long = "output-format", default_value = "json")]
+        output_format: OutputFormat,
+    },
+    /// Re-pull and reload components whose OCI tag points to a newer version.
+    #[command(after_help = "EXAMPLES:
+    # Update a single component
+    wassette component update weather_server
+
+    # Update every component installed from an OCI tag
+    wassette component update --all")]
+    Update {
+        /// Component ID to update
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<String>,
+        /// Update every component installed from an OCI tag
+        #[arg(long)]
+        all: bool,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+    },
+    /// Roll a component back to a previous version.
+    #[command(after_help = "EXAMPLES:
+    # Restore the version that was installed before the current one
+    wassette component rollback fetch_rs
+
+    # Restore a specific retained version
+    wassette component rollback fetch_rs --to sha256:3f1a...")]
+    Rollback {
+        /// Component ID to roll back
+        id: String,
+        /// Digest of the version to restore. Defaults to the most recent previous version.
+        #[arg(long = "to")]
+        digest: Option<String>,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+    },
+    /// Report components whose wasm or policy file was changed outside wassette.
+    #[command(after_help = "EXAMPLES:
+    # List modified components
+    wassette component verify
+
+    # Trust the files as they are now
+    wassette component verify --accept")]
+    Verify {
+        /// Record the current files of every component as trusted
+        #[arg(long)]
+        accept: bool,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Output format
+        #[arg(short = 'o', long = "output-format", default_value = "json")]
+        output_format: OutputFormat,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -266,7 +430,10 @@ pub enum GrantPermissionCommands {
     wassette permission grant storage my-component fs:///tmp/output --access read,write
 
     # Grant write-only access to a workspace
-    wassette permission grant storage my-component fs:///home/user/workspace --access write")]
+    wassette permission grant storage my-component fs:///home/user/workspace --access write
+
+    # Grant access to a directory under the home directory, mounted at /cache in the component
+    wassette permission grant storage my-component 'fs://~/.cache/app' --access read,write --mount /cache")]
     Storage {
         /// Component ID to grant permission to
         component_id: String,
@@ -275,6 +442,10 @@ pub enum GrantPermissionCommands {
         /// Access level (read, write, or read,write)
         #[arg(long, value_delimiter = ',')]
         access: Vec<String>,
+        /// Path to mount the directory at inside the component. Required when the URI uses `~` or
+        /// a variable
+        #[arg(long)]
+        mount: Option<String>,
         /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
         #[arg(long)]
         component_dir: Option<PathBuf>,
@@ -429,6 +600,17 @@ pub enum ToolCommands {
         #[arg(short = 'o', long = "output-format", default_value = "json")]
         output_format: OutputFormat,
     },
+    /// Search the tools of installed components by name and description.
+    Search {
+        /// Search query
+        query: String,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Output format
+        #[arg(short = 'o', long = "output-format", default_value = "json")]
+        output_format: OutputFormat,
+    },
     /// Read details of a specific tool.
     Read {
         /// Name of the tool to read
@@ -462,6 +644,10 @@ pub enum RegistryCommands {
     Search {
         /// Search query (matches against component name and description)
         query: Option<String>,
+        /// Additional registry index to search (file path or https:// URL), ahead of the
+        /// configured ones. Can be specified multiple times.
+        #[arg(long = "registry")]
+        registries: Vec<String>,
         /// Output format
         #[arg(short = 'o', long = "output-format", default_value = "json")]
         output_format: OutputFormat,
@@ -470,8 +656,48 @@ pub enum RegistryCommands {
     Get {
         /// Component name or URI from the registry
         component: String,
+        /// Additional registry index to search (file path or https:// URL), ahead of the
+        /// configured ones. Can be specified multiple times.
+        #[arg(long = "registry")]
+        registries: Vec<String>,
         /// Directory where plugins are stored. Defaults to $XDG_DATA_HOME/wassette/components
         #[arg(long)]
         plugin_dir: Option<PathBuf>,
+        /// Attach the permissions declared by the registry entry as the component's policy
+        #[arg(long)]
+        with_policy: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ManifestCommands {
+    /// Make the installed components match a provisioning manifest.
+    ///
+    /// Installs missing components, rewrites policies that differ from the manifest and unloads
+    /// components the manifest does not list.
+    Apply {
+        /// Path to the provisioning manifest
+        manifest: PathBuf,
+        /// Print the changes that would be made without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
+        /// Load variables for `${VAR}` references in the manifest from a file (supports .env format)
+        #[arg(long = "env-file")]
+        env_file: Option<PathBuf>,
+    },
+    /// Write the installed components and their policies as a provisioning manifest.
+    ///
+    /// Components whose policy cannot be expressed as inline manifest permissions are left out
+    /// and reported on stderr.
+    Export {
+        /// File to write the manifest to. Defaults to stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Directory where components are stored. Defaults to $XDG_DATA_HOME/wassette/components
+        #[arg(long)]
+        component_dir: Option<PathBuf>,
     },
 }
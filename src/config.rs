@@ -7,9 +7,10 @@ use std::path::{Path, PathBuf};
 use anyhow::Context;
 use etcetera::BaseStrategy;
 use figment::providers::{Env, Format, Serialized, Toml};
+use mcp_server::{ComponentRegistry, RegistrySource};
 use serde::{Deserialize, Serialize};
 
-use crate::commands::{Run, Serve};
+use crate::commands::{IntegrityMode, Run, Serve};
 
 /// Get the default component directory path based on the OS
 pub fn get_component_dir() -> Result<PathBuf, anyhow::Error> {
@@ -23,6 +24,12 @@ pub fn get_secrets_dir() -> Result<PathBuf, anyhow::Error> {
     Ok(dir_strategy.config_dir().join("wassette").join("secrets"))
 }
 
+/// Get the default directory for cached registry indexes based on the OS
+pub fn get_registry_cache_dir() -> Result<PathBuf, anyhow::Error> {
+    let dir_strategy = etcetera::choose_base_strategy().context("Unable to get home directory")?;
+    Ok(dir_strategy.cache_dir().join("wassette").join("registry"))
+}
+
 fn default_component_dir() -> PathBuf {
     get_component_dir().unwrap_or_else(|_| {
         eprintln!("WARN: Unable to determine default component directory, using `components` directory in the current working directory");
@@ -37,6 +44,17 @@ fn default_secrets_dir() -> PathBuf {
     })
 }
 
+fn default_registry_cache_dir() -> PathBuf {
+    get_registry_cache_dir().unwrap_or_else(|_| {
+        eprintln!("WARN: Unable to determine default registry cache directory, using `registry-cache` directory in the current working directory");
+        PathBuf::from("./registry-cache")
+    })
+}
+
+fn default_retained_versions() -> usize {
+    wassette::DEFAULT_RETAINED_VERSIONS
+}
+
 fn default_bind_address() -> String {
     // Default bind address using PORT and BIND_HOST environment variables (twelve-factor app compliance).
     // This is only used when bind_address is not set via CLI, config file, or other higher-precedence sources.
@@ -60,10 +78,46 @@ pub struct Config {
     #[serde(default)]
     pub environment_vars: HashMap<String, String>,
 
+    /// Give components without a policy every entry of `environment_vars` instead of an
+    /// empty environment
+    #[serde(default)]
+    pub inherit_env_without_policy: bool,
+
+    /// Remove orphaned artifacts from the component directory when the server starts
+    #[serde(default)]
+    pub gc_on_startup: bool,
+
+    /// What the server does at startup with components whose wasm or policy file was changed
+    /// outside wassette
+    #[serde(default)]
+    pub integrity_mode: IntegrityMode,
+
     /// Bind address for HTTP-based transports (SSE and StreamableHttp)
     /// Configured via PORT and BIND_HOST environment variables or CLI/config file
     #[serde(default = "default_bind_address", rename = "bind_address")]
     pub bind_address: String,
+
+    /// Registry indexes to search for components, highest priority first. Each entry is a file
+    /// path or an https:// URL. The built-in registry is always searched last.
+    #[serde(default)]
+    pub registries: Vec<String>,
+
+    /// Directory where registry indexes fetched over HTTPS are cached for offline use
+    #[serde(default = "default_registry_cache_dir")]
+    pub registry_cache_dir: PathBuf,
+
+    /// Number of previous versions of each component kept for rollback
+    #[serde(default = "default_retained_versions")]
+    pub retained_versions: usize,
+
+    /// Directory where precompiled components are cached. Defaults to a directory below the
+    /// component directory
+    #[serde(default)]
+    pub precompiled_cache_dir: Option<PathBuf>,
+
+    /// Sources components and policies may be loaded from
+    #[serde(default)]
+    pub trust: wassette::TrustPolicy,
 }
 
 impl Config {
@@ -107,6 +161,17 @@ impl Config {
             .context("Unable to merge configs")
     }
 
+    /// Builds the component registry from the configured sources
+    pub fn component_registry(&self) -> Result<ComponentRegistry, anyhow::Error> {
+        let sources = self
+            .registries
+            .iter()
+            .map(|source| source.parse::<RegistrySource>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid registry source in configuration")?;
+        Ok(ComponentRegistry::new(sources).with_cache_dir(&self.registry_cache_dir))
+    }
+
     /// Creates a new config from a Run struct for local stdio transport
     pub fn from_run(run_config: &Run) -> Result<Self, anyhow::Error> {
         // Start with the base config using existing logic
@@ -186,6 +251,11 @@ mod tests {
             env_vars: vec![],
             env_file: None,
             disable_builtin_tools: false,
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: None,
+            manifest: None,
+            reconcile: false,
         }
     }
 
@@ -196,6 +266,11 @@ mod tests {
             env_vars: vec![],
             env_file: None,
             disable_builtin_tools: false,
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: None,
+            manifest: None,
+            reconcile: false,
         }
     }
 
@@ -206,8 +281,13 @@ mod tests {
             env_vars: vec![],
             env_file: None,
             disable_builtin_tools: false,
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: None,
             bind_address: None,
             manifest: None,
+            reconcile: false,
+            auto_update_interval: None,
         }
     }
 
@@ -218,8 +298,13 @@ mod tests {
             env_vars: vec![],
             env_file: None,
             disable_builtin_tools: false,
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: None,
             bind_address: None,
             manifest: None,
+            reconcile: false,
+            auto_update_interval: None,
         }
     }
 
@@ -326,6 +411,122 @@ component_dir = "/config/component/dir"
         assert_eq!(config.component_dir, PathBuf::from("/config/component/dir"));
     }
 
+    #[test]
+    fn test_config_file_registries() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let toml_content = r#"
+registries = ["https://registry.example.com/index.json", "/etc/wassette/registry.json"]
+registry_cache_dir = "/config/cache"
+"#;
+        fs::write(&config_file, toml_content).unwrap();
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+
+        let registry = config.component_registry().unwrap();
+        assert_eq!(
+            registry.sources(),
+            &[
+                RegistrySource::Url("https://registry.example.com/index.json".to_string()),
+                RegistrySource::File(PathBuf::from("/etc/wassette/registry.json")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_retained_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(
+            config.retained_versions,
+            wassette::DEFAULT_RETAINED_VERSIONS
+        );
+
+        fs::write(&config_file, "retained_versions = 0").unwrap();
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(config.retained_versions, 0);
+    }
+
+    #[test]
+    fn test_config_integrity_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(config.integrity_mode, IntegrityMode::Warn);
+
+        fs::write(&config_file, "integrity_mode = \"refuse\"").unwrap();
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(config.integrity_mode, IntegrityMode::Refuse);
+
+        let mut serve_config = empty_test_cli_config();
+        serve_config.integrity_mode = Some(IntegrityMode::Quarantine);
+        let config =
+            Config::new_from_path(&serve_config, &config_file).expect("Failed to create config");
+        assert_eq!(config.integrity_mode, IntegrityMode::Quarantine);
+    }
+
+    #[test]
+    fn test_config_trust_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(config.trust, wassette::TrustPolicy::default());
+
+        fs::write(
+            &config_file,
+            r#"
+[trust]
+oci = ["ghcr.io/microsoft"]
+https = []
+local = ["/opt/wassette/components"]
+require_digest = true
+"#,
+        )
+        .unwrap();
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert_eq!(
+            config.trust.oci,
+            Some(vec!["ghcr.io/microsoft".to_string()])
+        );
+        assert_eq!(config.trust.https, Some(vec![]));
+        assert_eq!(
+            config.trust.local,
+            Some(vec![PathBuf::from("/opt/wassette/components")])
+        );
+        assert!(config.trust.require_digest);
+
+        fs::write(&config_file, "[trust]\nregistries = [\"ghcr.io\"]").unwrap();
+        assert!(Config::new_from_path(&empty_test_cli_config(), &config_file).is_err());
+    }
+
+    #[test]
+    fn test_config_invalid_registry_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        fs::write(
+            &config_file,
+            r#"registries = ["http://insecure.example.com"]"#,
+        )
+        .unwrap();
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert!(config.component_registry().is_err());
+    }
+
     #[test]
     fn test_new_method_without_wassette_config_file_env() {
         // This test verifies that new() works when WASSETTE_CONFIG_FILE is not set
@@ -380,6 +581,29 @@ policy_file = "custom_policy.yaml"
         assert_eq!(config.component_dir, PathBuf::from("/custom/component/dir"));
     }
 
+    #[test]
+    fn test_inherit_env_without_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file = temp_dir.path().join("config.toml");
+
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert!(!config.inherit_env_without_policy);
+
+        // An unset CLI flag must not override the config file
+        fs::write(&config_file, "inherit_env_without_policy = true\n").unwrap();
+        let config = Config::new_from_path(&empty_test_cli_config(), &config_file)
+            .expect("Failed to create config");
+        assert!(config.inherit_env_without_policy);
+
+        let mut serve_config = empty_test_cli_config();
+        serve_config.inherit_env_without_policy = true;
+        fs::remove_file(&config_file).unwrap();
+        let config =
+            Config::new_from_path(&serve_config, &config_file).expect("Failed to create config");
+        assert!(config.inherit_env_without_policy);
+    }
+
     #[test]
     fn test_bind_address_default() {
         temp_env::with_vars_unset(vec!["PORT", "BIND_HOST"], || {
@@ -430,8 +654,13 @@ bind_address = "0.0.0.0:8080"
             env_vars: vec![],
             env_file: None,
             disable_builtin_tools: false,
+            inherit_env_without_policy: false,
+            gc_on_startup: false,
+            integrity_mode: None,
             bind_address: Some("192.168.1.100:9090".to_string()),
             manifest: None,
+            reconcile: false,
+            auto_update_interval: None,
         };
 
         let config =
@@ -0,0 +1,137 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File name of the lockfile written next to a provisioning manifest
+pub const LOCKFILE_NAME: &str = "wassette.lock";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Pins every component URI of a provisioning manifest to the digest it resolved to, so that
+/// later provisioning runs install exactly the same artifacts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Lockfile schema version
+    pub version: u32,
+
+    /// Resolved components, in manifest order
+    #[serde(default)]
+    pub components: Vec<LockedComponent>,
+}
+
+/// A component URI and the digest it is pinned to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedComponent {
+    /// Component URI as written in the manifest
+    pub uri: String,
+
+    /// Digest the URI resolved to (`sha256:<hex>`)
+    pub digest: String,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            components: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Returns the lockfile path that belongs to the manifest at `manifest_path`
+    pub fn path_for_manifest(manifest_path: &Path) -> PathBuf {
+        manifest_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(LOCKFILE_NAME)
+    }
+
+    /// Loads the lockfile at `path`, returning `None` if it does not exist
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read lockfile: {}", path.display()))
+            }
+        };
+
+        let lockfile: Self = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse lockfile: {}", path.display()))?;
+        if lockfile.version != LOCKFILE_VERSION {
+            bail!(
+                "Unsupported lockfile version {} in {}. Only version {} is supported.",
+                lockfile.version,
+                path.display(),
+                LOCKFILE_VERSION
+            );
+        }
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile to `path`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_yaml::to_string(self).context("Failed to serialize lockfile")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write lockfile: {}", path.display()))
+    }
+
+    /// Returns the digest pinned for `uri`, if any
+    pub fn digest_for(&self, uri: &str) -> Option<&str> {
+        self.components
+            .iter()
+            .find(|c| c.uri == uri)
+            .map(|c| c.digest.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef";
+
+    #[test]
+    fn test_lockfile_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = Lockfile::path_for_manifest(&temp_dir.path().join("manifest.yaml"));
+        assert_eq!(path, temp_dir.path().join(LOCKFILE_NAME));
+        assert!(Lockfile::load(&path).unwrap().is_none());
+
+        let lockfile = Lockfile {
+            components: vec![LockedComponent {
+                uri: "oci://ghcr.io/example/component:latest".to_string(),
+                digest: DIGEST.to_string(),
+            }],
+            ..Default::default()
+        };
+        lockfile.save(&path).unwrap();
+
+        let loaded = Lockfile::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, lockfile);
+        assert_eq!(
+            loaded.digest_for("oci://ghcr.io/example/component:latest"),
+            Some(DIGEST)
+        );
+        assert_eq!(
+            loaded.digest_for("oci://ghcr.io/example/other:latest"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unsupported_lockfile_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+        std::fs::write(&path, "version: 2\ncomponents: []\n").unwrap();
+
+        let err = Lockfile::load(&path).unwrap_err();
+        assert!(err.to_string().contains("Unsupported lockfile version"));
+    }
+}
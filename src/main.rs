@@ -5,10 +5,13 @@
 
 #![warn(missing_docs)]
 
+use std::collections::HashMap;
+use std::path::Path;
+
 use anyhow::{bail, Context, Result};
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, shells};
-use mcp_server::{handle_tools_list, LifecycleManager};
+use mcp_server::{handle_tools_list, registry, search, LifecycleManager};
 use rmcp::service::serve_server;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::streamable_http_server::StreamableHttpService;
@@ -21,23 +24,27 @@ mod cli_handlers;
 mod commands;
 mod config;
 mod format;
+mod lockfile;
 mod manifest;
 mod permission_synthesis;
 mod provisioning_controller;
-mod registry;
 mod server;
 mod tools;
 mod utils;
 
-use cli_handlers::{create_lifecycle_manager, handle_tool_cli_command};
+use cli_handlers::{
+    attach_declared_policy, create_component_registry, create_lifecycle_manager,
+    handle_tool_cli_command,
+};
 use commands::{
-    Cli, Commands, ComponentCommands, GrantPermissionCommands, PermissionCommands, PolicyCommands,
-    RegistryCommands, RevokePermissionCommands, SecretCommands, Shell, ToolCommands, Transport,
+    Cli, Commands, ComponentCommands, GrantPermissionCommands, IntegrityMode, ManifestCommands,
+    PermissionCommands, PolicyCommands, RegistryCommands, RevokePermissionCommands, SecretCommands,
+    Shell, ToolCommands, Transport,
 };
 use format::{print_result, OutputFormat};
 use server::McpServer;
 use tools::ToolName;
-use utils::{format_build_info, load_component_registry, parse_env_var};
+use utils::{format_build_info, format_size, load_env_file, parse_env_var};
 
 // Health and info endpoint handlers
 mod endpoints {
@@ -67,6 +74,68 @@ mod endpoints {
     }
 }
 
+/// Parse and validate the provisioning manifest at `path`
+fn load_manifest(
+    path: &Path,
+    vars: &HashMap<String, String>,
+) -> Result<manifest::ProvisioningManifest> {
+    let manifest = manifest::ProvisioningManifest::from_file(path, vars)
+        .context("Failed to parse provisioning manifest")?;
+
+    tracing::info!("Validating provisioning manifest from: {}", path.display());
+    manifest.validate().context("Manifest validation failed")?;
+
+    tracing::info!(
+        "Successfully validated manifest with {} component(s)",
+        manifest.components.len()
+    );
+    Ok(manifest)
+}
+
+/// Check the files of installed components against the state index before they are loaded, and
+/// handle changes made outside wassette as `mode` says
+async fn enforce_integrity(
+    lifecycle_manager: &LifecycleManager,
+    mode: IntegrityMode,
+) -> Result<()> {
+    let violations = lifecycle_manager
+        .verify_integrity()
+        .await
+        .context("Failed to verify installed components")?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    match mode {
+        IntegrityMode::Warn => {
+            for violation in &violations {
+                tracing::warn!(
+                    "{violation}; run `wassette component verify --accept` to trust the current files"
+                );
+            }
+        }
+        IntegrityMode::Refuse => {
+            let details: Vec<String> = violations.iter().map(|v| format!("  - {v}")).collect();
+            bail!(
+                "Refusing to start because installed files were changed outside wassette:\n{}\nRun `wassette component verify --accept` to trust the current files, or reinstall the affected components",
+                details.join("\n")
+            );
+        }
+        IntegrityMode::Quarantine => {
+            let mut quarantined = std::collections::HashSet::new();
+            for violation in &violations {
+                tracing::warn!("{violation}");
+                if quarantined.insert(violation.component_id.as_str()) {
+                    lifecycle_manager
+                        .quarantine_component(&violation.component_id)
+                        .await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -100,25 +169,84 @@ async fn main() -> Result<()> {
                 let config =
                     config::Config::from_run(cfg).context("Failed to load configuration")?;
 
+                // Parse and validate manifest if provided
+                let manifest = cfg
+                    .manifest
+                    .as_deref()
+                    .map(|path| load_manifest(path, &config.environment_vars))
+                    .transpose()?;
+
+                let registry = config.component_registry()?;
+
                 // Build the lifecycle manager without eagerly loading components so the
                 // background loader is the single source of tool registration.
                 let config::Config {
                     component_dir,
                     secrets_dir,
                     environment_vars,
+                    inherit_env_without_policy,
+                    gc_on_startup,
+                    integrity_mode,
+                    trust,
                     bind_address: _,
+                    registries: _,
+                    registry_cache_dir: _,
+                    retained_versions,
+                    precompiled_cache_dir,
                 } = config;
 
-                let lifecycle_manager = LifecycleManager::builder(component_dir)
+                let http_client = reqwest::Client::builder()
+                    .redirect(trust.redirect_policy())
+                    .build()?;
+
+                let mut builder = LifecycleManager::builder(component_dir)
                     .with_environment_vars(environment_vars)
+                    .with_env_inheritance_without_policy(inherit_env_without_policy)
+                    .with_retained_versions(retained_versions)
+                    .with_trust_policy(trust)
                     .with_secrets_dir(secrets_dir)
                     .with_oci_client(oci_client::Client::default())
-                    .with_http_client(reqwest::Client::default())
-                    .with_eager_loading(false)
-                    .build()
+                    .with_http_client(http_client)
+                    .with_eager_loading(false);
+                if let Some(dir) = precompiled_cache_dir {
+                    builder = builder.with_precompiled_cache_dir(dir);
+                }
+                let lifecycle_manager = builder.build().await?;
+
+                if gc_on_startup {
+                    match lifecycle_manager.collect_garbage(false).await {
+                        Ok(report) => tracing::info!(
+                            "Removed {} orphaned artifacts, reclaiming {}",
+                            report.removed.len(),
+                            format_size(report.reclaimed_bytes)
+                        ),
+                        Err(e) => tracing::warn!("Garbage collection failed: {:#}", e),
+                    }
+                }
+
+                enforce_integrity(&lifecycle_manager, integrity_mode).await?;
+
+                // Provision components from manifest if provided. Stdout belongs to the MCP
+                // transport, so progress and errors only go to the stderr log.
+                if let (Some(manifest), Some(manifest_path)) = (&manifest, &cfg.manifest) {
+                    tracing::info!("Provisioning components from manifest...");
+
+                    provisioning_controller::apply_manifest(
+                        manifest,
+                        manifest_path,
+                        &lifecycle_manager,
+                        cfg.reconcile,
+                    )
                     .await?;
 
-                let server = McpServer::new(lifecycle_manager.clone(), cfg.disable_builtin_tools);
+                    tracing::info!("All components provisioned successfully");
+                }
+
+                let server = McpServer::new(
+                    lifecycle_manager.clone(),
+                    registry,
+                    cfg.disable_builtin_tools,
+                );
 
                 // Start background component loading
                 let server_clone = server.clone();
@@ -171,24 +299,13 @@ async fn main() -> Result<()> {
                     config::Config::from_serve(cfg).context("Failed to load configuration")?;
 
                 // Parse and validate manifest if provided
-                let manifest = if let Some(manifest_path) = &cfg.manifest {
-                    let m = manifest::ProvisioningManifest::from_file(manifest_path)
-                        .context("Failed to parse provisioning manifest")?;
+                let manifest = cfg
+                    .manifest
+                    .as_deref()
+                    .map(|path| load_manifest(path, &config.environment_vars))
+                    .transpose()?;
 
-                    tracing::info!(
-                        "Validating provisioning manifest from: {}",
-                        manifest_path.display()
-                    );
-                    m.validate().context("Manifest validation failed")?;
-
-                    tracing::info!(
-                        "Successfully validated manifest with {} component(s)",
-                        m.components.len()
-                    );
-                    Some(m)
-                } else {
-                    None
-                };
+                let registry = config.component_registry()?;
 
                 // Build the lifecycle manager without eagerly loading components so the
                 // background loader is the single source of tool registration.
@@ -196,41 +313,68 @@ async fn main() -> Result<()> {
                     component_dir,
                     secrets_dir,
                     environment_vars,
+                    inherit_env_without_policy,
+                    gc_on_startup,
+                    integrity_mode,
+                    trust,
                     bind_address,
+                    registries: _,
+                    registry_cache_dir: _,
+                    retained_versions,
+                    precompiled_cache_dir,
                 } = config;
 
-                // Keep a clone of component_dir for provisioning
-                let component_dir_path = component_dir.clone();
+                let http_client = reqwest::Client::builder()
+                    .redirect(trust.redirect_policy())
+                    .build()?;
 
-                let lifecycle_manager = LifecycleManager::builder(component_dir)
+                let mut builder = LifecycleManager::builder(component_dir)
                     .with_environment_vars(environment_vars)
+                    .with_env_inheritance_without_policy(inherit_env_without_policy)
+                    .with_retained_versions(retained_versions)
+                    .with_trust_policy(trust)
                     .with_secrets_dir(secrets_dir)
                     .with_oci_client(oci_client::Client::default())
-                    .with_http_client(reqwest::Client::default())
-                    .with_eager_loading(false)
-                    .build()
-                    .await?;
+                    .with_http_client(http_client)
+                    .with_eager_loading(false);
+                if let Some(dir) = precompiled_cache_dir {
+                    builder = builder.with_precompiled_cache_dir(dir);
+                }
+                let lifecycle_manager = builder.build().await?;
+
+                if gc_on_startup {
+                    match lifecycle_manager.collect_garbage(false).await {
+                        Ok(report) => tracing::info!(
+                            "Removed {} orphaned artifacts, reclaiming {}",
+                            report.removed.len(),
+                            format_size(report.reclaimed_bytes)
+                        ),
+                        Err(e) => tracing::warn!("Garbage collection failed: {:#}", e),
+                    }
+                }
+
+                enforce_integrity(&lifecycle_manager, integrity_mode).await?;
 
                 // Provision components from manifest if provided
-                if let Some(manifest) = &manifest {
+                if let (Some(manifest), Some(manifest_path)) = (&manifest, &cfg.manifest) {
                     tracing::info!("Provisioning components from manifest...");
 
-                    let provisioner = provisioning_controller::ProvisioningController::new(
+                    provisioning_controller::apply_manifest(
                         manifest,
+                        manifest_path,
                         &lifecycle_manager,
-                        lifecycle_manager.secrets_manager(),
-                        &component_dir_path,
-                    );
-
-                    provisioner
-                        .provision()
-                        .await
-                        .context("Component provisioning failed")?;
+                        cfg.reconcile,
+                    )
+                    .await?;
 
                     tracing::info!("All components provisioned successfully");
                 }
 
-                let server = McpServer::new(lifecycle_manager.clone(), cfg.disable_builtin_tools);
+                let server = McpServer::new(
+                    lifecycle_manager.clone(),
+                    registry,
+                    cfg.disable_builtin_tools,
+                );
 
                 // Start background component loading
                 let server_clone = server.clone();
@@ -256,6 +400,49 @@ async fn main() -> Result<()> {
                     }
                 });
 
+                // Periodically update components whose OCI tag moved
+                if let Some(interval) = cfg.auto_update_interval {
+                    let server_clone = server.clone();
+                    let lifecycle_manager_clone = lifecycle_manager.clone();
+                    tokio::spawn(async move {
+                        let mut ticker =
+                            tokio::time::interval(std::time::Duration::from_secs(interval));
+                        // The first tick completes immediately; start checking after one interval
+                        ticker.tick().await;
+                        loop {
+                            ticker.tick().await;
+                            for component_id in
+                                lifecycle_manager_clone.list_components_known().await
+                            {
+                                match lifecycle_manager_clone
+                                    .update_component(&component_id)
+                                    .await
+                                {
+                                    Ok(Some(outcome)) => {
+                                        tracing::info!(
+                                            %component_id,
+                                            digest = %outcome.digest,
+                                            "Updated component"
+                                        );
+                                        if let Some(peer) = server_clone.get_peer() {
+                                            if let Err(e) = peer.notify_tool_list_changed().await {
+                                                tracing::warn!(
+                                                    "Failed to notify tool list changed: {}",
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => {
+                                        tracing::warn!(%component_id, "Failed to update component: {e:#}")
+                                    }
+                                }
+                            }
+                        }
+                    });
+                }
+
                 let transport: Transport = (&cfg.transport).into();
                 match transport {
                     Transport::StreamableHttp => {
@@ -370,6 +557,219 @@ async fn main() -> Result<()> {
                     )
                     .await?;
                 }
+                ComponentCommands::Versions {
+                    id,
+                    component_dir,
+                    output_format,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let versions = lifecycle_manager.list_component_versions(id).await?;
+
+                    let result = json!({
+                        "status": "success",
+                        "id": id,
+                        "versions": versions
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        *output_format,
+                    )?;
+                }
+                ComponentCommands::Outdated {
+                    component_dir,
+                    output_format,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let mut outdated = Vec::new();
+                    for component_id in lifecycle_manager.list_components_known().await {
+                        match lifecycle_manager
+                            .check_component_update(&component_id)
+                            .await
+                        {
+                            Ok(Some(status)) if status.is_outdated() => outdated.push(status),
+                            Ok(_) => {}
+                            Err(e) => {
+                                eprintln!("Failed to check {component_id} for updates: {e:#}")
+                            }
+                        }
+                    }
+
+                    let result = json!({
+                        "status": "success",
+                        "count": outdated.len(),
+                        "components": outdated
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        *output_format,
+                    )?;
+                }
+                ComponentCommands::Update {
+                    id,
+                    all,
+                    component_dir,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let component_ids = match id {
+                        Some(id) if !*all => vec![id.clone()],
+                        _ => lifecycle_manager.list_components_known().await,
+                    };
+
+                    let mut updated = Vec::new();
+                    let mut up_to_date = Vec::new();
+                    let mut failed = Vec::new();
+                    for component_id in component_ids {
+                        let status = match lifecycle_manager
+                            .check_component_update(&component_id)
+                            .await
+                        {
+                            Ok(Some(status)) => status,
+                            Ok(None) if *all => continue,
+                            Ok(None) => bail!(
+                                "Component '{}' was not installed from an OCI tag and cannot be updated",
+                                component_id
+                            ),
+                            Err(e) => {
+                                failed.push(json!({"id": component_id, "error": format!("{e:#}")}));
+                                continue;
+                            }
+                        };
+                        if !status.is_outdated() {
+                            up_to_date.push(component_id);
+                            continue;
+                        }
+                        match lifecycle_manager.load_component(&status.source_uri).await {
+                            Ok(outcome) => updated.push(json!({
+                                "id": outcome.component_id,
+                                "digest": outcome.digest,
+                                "tools": outcome.tool_names
+                            })),
+                            Err(e) => {
+                                failed.push(json!({"id": component_id, "error": format!("{e:#}")}))
+                            }
+                        }
+                    }
+
+                    let result = json!({
+                        "status": if failed.is_empty() { "success" } else { "error" },
+                        "updated": updated,
+                        "up_to_date": up_to_date,
+                        "failed": failed
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        OutputFormat::Json,
+                    )?;
+
+                    if !failed.is_empty() {
+                        std::process::exit(1);
+                    }
+                }
+                ComponentCommands::Verify {
+                    accept,
+                    component_dir,
+                    output_format,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let violations = lifecycle_manager.verify_integrity().await?;
+                    if *accept {
+                        lifecycle_manager.accept_current_state().await?;
+                    }
+
+                    let status = match (violations.is_empty(), *accept) {
+                        (true, _) => "verified",
+                        (false, true) => "accepted",
+                        (false, false) => "modified",
+                    };
+                    let result = json!({
+                        "status": status,
+                        "count": violations.len(),
+                        "violations": violations
+                            .iter()
+                            .map(|violation| json!({
+                                "component_id": violation.component_id,
+                                "artifact": violation.artifact,
+                                "expected": violation.expected,
+                                "actual": violation.actual,
+                                "message": violation.to_string()
+                            }))
+                            .collect::<Vec<_>>()
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        *output_format,
+                    )?;
+
+                    if status == "modified" {
+                        std::process::exit(1);
+                    }
+                }
+                ComponentCommands::Rollback {
+                    id,
+                    digest,
+                    component_dir,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let outcome = lifecycle_manager
+                        .rollback_component(id, digest.as_deref())
+                        .await?;
+
+                    let result = json!({
+                        "status": "component rolled back successfully",
+                        "id": outcome.component_id,
+                        "digest": outcome.digest,
+                        "tools": outcome.tool_names
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        OutputFormat::Json,
+                    )?;
+                }
             },
             Commands::Policy { command } => match command {
                 PolicyCommands::Get {
@@ -391,6 +791,7 @@ async fn main() -> Result<()> {
                         component_id,
                         uri,
                         access,
+                        mount,
                         component_dir,
                     } => {
                         let component_dir =
@@ -398,13 +799,14 @@ async fn main() -> Result<()> {
                         let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
                         let mut args = Map::new();
                         args.insert("component_id".to_string(), json!(component_id));
-                        args.insert(
-                            "details".to_string(),
-                            json!({
-                                "uri": uri,
-                                "access": access
-                            }),
-                        );
+                        let mut details = json!({
+                            "uri": uri,
+                            "access": access
+                        });
+                        if let Some(mount) = mount {
+                            details["mount"] = json!(mount);
+                        }
+                        args.insert("details".to_string(), details);
                         handle_tool_cli_command(
                             &lifecycle_manager,
                             "grant-storage-permission",
@@ -726,6 +1128,33 @@ async fn main() -> Result<()> {
                         *output_format,
                     )?;
                 }
+                ToolCommands::Search {
+                    query,
+                    component_dir,
+                    output_format,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let tools = search::search_tools(&lifecycle_manager, query).await?;
+
+                    let result = json!({
+                        "status": "success",
+                        "count": tools.len(),
+                        "tools": tools
+                    });
+
+                    print_result(
+                        &rmcp::model::CallToolResult {
+                            content: Some(vec![rmcp::model::Content::text(
+                                serde_json::to_string_pretty(&result)?,
+                            )]),
+                            structured_content: None,
+                            is_error: None,
+                        },
+                        *output_format,
+                    )?;
+                }
                 ToolCommands::Read {
                     name,
                     component_dir,
@@ -833,9 +1262,7 @@ async fn main() -> Result<()> {
 
                 // Display tools information
                 if let Some(arr) = schema["tools"].as_array() {
-                    for t in arr {
-                        // The tool info is nested in properties.result
-                        let tool_info = &t["properties"]["result"];
+                    for tool_info in arr {
                         let name = tool_info["name"]
                             .as_str()
                             .unwrap_or("<unnamed>")
@@ -862,9 +1289,10 @@ async fn main() -> Result<()> {
             Commands::Registry { command } => match command {
                 RegistryCommands::Search {
                     query,
+                    registries,
                     output_format,
                 } => {
-                    let components = load_component_registry()?;
+                    let components = create_component_registry(registries)?.load().await?;
                     let results = registry::search_components(&components, query.as_deref());
 
                     let result = json!({
@@ -886,9 +1314,11 @@ async fn main() -> Result<()> {
                 }
                 RegistryCommands::Get {
                     component,
+                    registries,
                     plugin_dir,
+                    with_policy,
                 } => {
-                    let components = load_component_registry()?;
+                    let components = create_component_registry(registries)?.load().await?;
 
                     // Find the component by name or URI
                     let registry_component =
@@ -900,11 +1330,34 @@ async fn main() -> Result<()> {
                                 )
                             })?;
 
+                    // Show what the component needs before it is loaded. Stdout carries the
+                    // load result, so this goes to stderr.
+                    match &registry_component.permissions {
+                        Some(_) => {
+                            let summary = registry_component.permission_summary();
+                            if summary.is_empty() {
+                                eprintln!("{} requires no permissions", registry_component.name);
+                            } else {
+                                eprintln!("{} requires:", registry_component.name);
+                                for line in summary {
+                                    eprintln!("  {line}");
+                                }
+                            }
+                        }
+                        None => eprintln!(
+                            "{} does not declare the permissions it requires",
+                            registry_component.name
+                        ),
+                    }
+
                     // Use the existing load-component functionality
                     let plugin_dir = plugin_dir.clone().or_else(|| cli.component_dir.clone());
                     let lifecycle_manager = create_lifecycle_manager(plugin_dir).await?;
                     let mut args = Map::new();
                     args.insert("path".to_string(), json!(registry_component.uri));
+                    if let Some(digest) = &registry_component.digest {
+                        args.insert("digest".to_string(), json!(digest));
+                    }
                     handle_tool_cli_command(
                         &lifecycle_manager,
                         "load-component",
@@ -912,8 +1365,126 @@ async fn main() -> Result<()> {
                         OutputFormat::Json,
                     )
                     .await?;
+
+                    if *with_policy {
+                        match registry_component.declared_policy() {
+                            Some(policy) => {
+                                let component_id =
+                                    wassette::component_id_from_uri(&registry_component.uri)?;
+                                let source = registry_component
+                                    .source
+                                    .as_ref()
+                                    .map(registry::RegistrySource::uri);
+                                attach_declared_policy(
+                                    &lifecycle_manager,
+                                    &component_id,
+                                    &policy,
+                                    source.as_deref(),
+                                )
+                                .await?;
+                                eprintln!("Attached the declared policy to {component_id}");
+                            }
+                            None => eprintln!(
+                                "No policy attached: {} does not declare its permissions",
+                                registry_component.name
+                            ),
+                        }
+                    }
                 }
             },
+            Commands::Manifest { command } => match command {
+                ManifestCommands::Apply {
+                    manifest: manifest_path,
+                    dry_run,
+                    component_dir,
+                    env_file,
+                } => {
+                    let vars = match env_file {
+                        Some(env_file) => load_env_file(env_file).with_context(|| {
+                            format!("Failed to load environment file: {}", env_file.display())
+                        })?,
+                        None => HashMap::new(),
+                    };
+                    let manifest = load_manifest(manifest_path, &vars)?;
+
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let plan = provisioning_controller::ProvisioningController::new(
+                        &manifest,
+                        &lifecycle_manager,
+                        lifecycle_manager.secrets_manager(),
+                    )
+                    .plan()
+                    .await?;
+                    println!("{plan}");
+                    if *dry_run && !plan.errors.is_empty() {
+                        bail!("Failed to plan {} component(s)", plan.errors.len());
+                    }
+
+                    if !*dry_run {
+                        provisioning_controller::apply_manifest(
+                            &manifest,
+                            manifest_path,
+                            &lifecycle_manager,
+                            true,
+                        )
+                        .await?;
+                        println!("Manifest applied.");
+                    }
+                }
+                ManifestCommands::Export {
+                    output,
+                    component_dir,
+                } => {
+                    let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                    let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                    let export = provisioning_controller::export_manifest(&lifecycle_manager).await;
+                    for (component_id, reason) in &export.skipped {
+                        eprintln!("Skipped {component_id}: {reason}");
+                    }
+
+                    let yaml = serde_yaml::to_string(&export.manifest)
+                        .context("Failed to serialize manifest")?;
+                    match output {
+                        Some(path) => std::fs::write(path, yaml).with_context(|| {
+                            format!("Failed to write manifest to {}", path.display())
+                        })?,
+                        None => print!("{yaml}"),
+                    }
+                }
+            },
+            Commands::Gc {
+                dry_run,
+                component_dir,
+                output_format,
+            } => {
+                let component_dir = component_dir.clone().or_else(|| cli.component_dir.clone());
+                let lifecycle_manager = create_lifecycle_manager(component_dir).await?;
+
+                let report = lifecycle_manager.collect_garbage(*dry_run).await?;
+
+                let result = json!({
+                    "status": "success",
+                    "dry_run": report.dry_run,
+                    "count": report.removed.len(),
+                    "reclaimed_bytes": report.reclaimed_bytes,
+                    "reclaimed": format_size(report.reclaimed_bytes),
+                    "removed": report.removed
+                });
+
+                print_result(
+                    &rmcp::model::CallToolResult {
+                        content: Some(vec![rmcp::model::Content::text(
+                            serde_json::to_string_pretty(&result)?,
+                        )]),
+                        structured_content: None,
+                        is_error: None,
+                    },
+                    *output_format,
+                )?;
+            }
             Commands::Autocomplete { shell } => {
                 let mut cmd = Cli::command();
                 let bin_name = cmd.get_name().to_string();
@@ -994,6 +1565,73 @@ mod cli_tests {
         matches!(cli.command, Some(Commands::Serve(_)));
     }
 
+    #[test]
+    fn test_run_manifest_parsing() {
+        let args = vec![
+            "wassette",
+            "run",
+            "--manifest",
+            "manifest.yaml",
+            "--reconcile",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Run(run)) => {
+                assert_eq!(
+                    run.manifest,
+                    Some(std::path::PathBuf::from("manifest.yaml"))
+                );
+                assert!(run.reconcile);
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        // --reconcile only makes sense with a manifest
+        let args = vec!["wassette", "run", "--reconcile"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_gc_parsing() {
+        let args = vec!["wassette", "gc", "--dry-run"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Gc { dry_run, .. }) => assert!(dry_run),
+            _ => panic!("Expected Gc command"),
+        }
+
+        let args = vec!["wassette", "serve", "--gc-on-startup"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Serve(serve)) => assert!(serve.gc_on_startup),
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
+    #[test]
+    fn test_component_verify_parsing() {
+        let args = vec!["wassette", "component", "verify", "--accept"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Component {
+                command: ComponentCommands::Verify { accept, .. },
+            }) => assert!(accept),
+            _ => panic!("Expected Component Verify command"),
+        }
+
+        let args = vec!["wassette", "run", "--integrity-mode", "quarantine"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Run(run)) => {
+                assert_eq!(run.integrity_mode, Some(IntegrityMode::Quarantine))
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        let args = vec!["wassette", "serve", "--integrity-mode", "ignore"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
     #[test]
     fn test_permission_grant_storage_parsing() {
         let args = vec![
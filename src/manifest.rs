@@ -1,8 +1,10 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
@@ -31,15 +33,68 @@ pub struct ComponentDeclaration {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub digest: Option<String>,
 
-    /// Permissions configuration (inline only in MVP)
+    /// Policy file to start from, as an alternative or a base for inline permissions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<PolicyReference>,
+
+    /// Inline permissions. Combined with `policy`, each section given here replaces the same
+    /// section of the referenced policy.
+    #[serde(default, skip_serializing_if = "InlinePermissions::is_empty")]
     pub permissions: InlinePermissions,
 
-    /// Optional retry policy (deferred to post-MVP)
+    /// Command line arguments passed to the component, program name first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+
+    /// Retries for transient download failures
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry_policy: Option<RetryPolicy>,
+
+    /// Where the component was declared, for error reporting
+    #[serde(skip)]
+    pub source: Option<SourceLocation>,
+}
+
+/// Location of a component declaration in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Manifest file, if the manifest was read from disk
+    pub file: Option<PathBuf>,
+
+    /// 1-based line number
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
 }
 
-/// Inline permission declarations (only mode supported in MVP)
+/// A single manifest file as written, before its includes are merged
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    version: u32,
+
+    /// Other manifest files whose components are merged in, relative to this file
+    #[serde(default)]
+    include: Vec<String>,
+
+    #[serde(default)]
+    components: Vec<ComponentDeclaration>,
+}
+
+/// Reference to a policy document (file://, oci://, https://)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyReference {
+    /// URI of the policy document
+    pub uri: String,
+}
+
+/// Inline permission declarations
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct InlinePermissions {
     /// Network permissions
@@ -54,7 +109,7 @@ pub struct InlinePermissions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub environment: Option<EnvironmentPermissions>,
 
-    /// Memory and resource limits (deferred to post-MVP)
+    /// Memory and CPU time limits
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resources: Option<ResourceLimits>,
 }
@@ -88,6 +143,10 @@ pub struct StorageRule {
 
     /// Access types (read, write)
     pub access: Vec<AccessType>,
+
+    /// Guest path to mount the directory at (defaults to the path in `uri`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mount: Option<String>,
 }
 
 /// Storage access type
@@ -116,22 +175,52 @@ pub struct EnvironmentRule {
     pub value_from: Option<String>,
 }
 
-/// Resource limits (deferred to post-MVP)
+/// Resource limits enforced while the component runs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceLimits {
     /// Memory limit in bytes
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_number"
+    )]
     pub memory_bytes: Option<u64>,
 
-    /// CPU time limit in milliseconds
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Maximum wall-clock time in milliseconds a single call may run
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_number"
+    )]
     pub cpu_time_ms: Option<u64>,
 }
 
-/// Retry policy (deferred to post-MVP)
+/// Read a limit written either as a number or as a string of digits, which is what
+/// `memory_bytes: ${MEMORY}` becomes once its variable is expanded
+fn deserialize_number<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(number)) => Ok(Some(number)),
+        Some(NumberOrString::String(text)) => text.trim().parse().map(Some).map_err(|_| {
+            serde::de::Error::custom(format!("expected a whole number, found '{text}'"))
+        }),
+    }
+}
+
+/// Retry policy for downloading a component
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetryPolicy {
-    /// Number of retry attempts
+    /// Number of retries after the first attempt
     pub attempts: u32,
 
     /// Backoff strategy
@@ -142,23 +231,42 @@ pub struct RetryPolicy {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum BackoffStrategy {
+    /// Wait `base_ms`, doubling the delay after every further failure
     Exponential { base_ms: u64 },
+    /// Wait `increment_ms` longer after every failure
     Linear { increment_ms: u64 },
 }
 
-impl ProvisioningManifest {
-    /// Parse manifest from a YAML file
-    pub fn from_file(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read manifest file: {}", path.display()))?;
+impl RetryPolicy {
+    /// The equivalent retry policy understood by the lifecycle manager
+    pub fn to_retry_policy(&self) -> wassette::RetryPolicy {
+        let backoff = match self.backoff {
+            BackoffStrategy::Exponential { base_ms } => wassette::Backoff::Exponential {
+                base: Duration::from_millis(base_ms),
+            },
+            BackoffStrategy::Linear { increment_ms } => wassette::Backoff::Linear {
+                increment: Duration::from_millis(increment_ms),
+            },
+        };
+        wassette::RetryPolicy {
+            attempts: self.attempts,
+            backoff,
+        }
+    }
+}
 
-        Self::from_yaml(&content)
-            .with_context(|| format!("Failed to parse manifest file: {}", path.display()))
+impl ProvisioningManifest {
+    /// Parse manifest from a YAML file, resolving `${VAR}` references from `vars` before the
+    /// process environment
+    pub fn from_file(path: &Path, vars: &HashMap<String, String>) -> Result<Self> {
+        ManifestLoader::new(vars).load_file(path)
     }
 
-    /// Parse manifest from YAML string
-    pub fn from_yaml(content: &str) -> Result<Self> {
-        serde_yaml::from_str(content).context("Failed to deserialize manifest YAML")
+    /// Parse manifest from YAML string, resolving `${VAR}` references from `vars` before the
+    /// process environment. Includes are resolved relative to the working directory.
+    #[cfg(test)]
+    pub fn from_yaml(content: &str, vars: &HashMap<String, String>) -> Result<Self> {
+        ManifestLoader::new(vars).load(content, None, Path::new("."))
     }
 
     /// Validate the manifest
@@ -182,18 +290,17 @@ impl ProvisioningManifest {
 
         for component in &self.components {
             if !seen_uris.insert(&component.uri) {
-                duplicate_uris.push(&component.uri);
+                duplicate_uris.push(match &component.source {
+                    Some(source) => format!("{} ({})", component.uri, source),
+                    None => component.uri.clone(),
+                });
             }
         }
 
         if !duplicate_uris.is_empty() {
             bail!(
                 "Duplicate component URIs found: {}",
-                duplicate_uris
-                    .iter()
-                    .map(|s| s.as_str())
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                duplicate_uris.join(", ")
             );
         }
 
@@ -201,7 +308,10 @@ impl ProvisioningManifest {
         for (idx, component) in self.components.iter().enumerate() {
             component
                 .validate()
-                .with_context(|| format!("Invalid component at index {}", idx))?;
+                .with_context(|| match &component.source {
+                    Some(source) => format!("Invalid component at {}", source),
+                    None => format!("Invalid component at index {}", idx),
+                })?;
         }
 
         Ok(())
@@ -248,24 +358,46 @@ impl ComponentDeclaration {
             }
         }
 
-        // Validate permissions
-        self.permissions
-            .validate()
-            .context("Invalid permissions configuration")?;
+        // Validate the policy reference
+        if let Some(policy) = &self.policy {
+            let valid_schemes = ["file://", "oci://", "https://"];
+            if !valid_schemes
+                .iter()
+                .any(|scheme| policy.uri.starts_with(scheme))
+            {
+                bail!(
+                    "Policy URI must start with one of: {}. Got: {}",
+                    valid_schemes.join(", "),
+                    policy.uri
+                );
+            }
+        }
+
+        // Validate permissions. They are optional: a component without a policy or permissions
+        // runs without a policy.
+        if !self.permissions.is_empty() {
+            self.permissions
+                .validate()
+                .context("Invalid permissions configuration")?;
+        }
 
         Ok(())
     }
 }
 
 impl InlinePermissions {
-    /// Validate inline permissions
-    pub fn validate(&self) -> Result<()> {
-        // At least one permission type should be specified
-        if self.network.is_none()
+    /// Whether no permission type is declared
+    pub fn is_empty(&self) -> bool {
+        self.network.is_none()
             && self.storage.is_none()
             && self.environment.is_none()
             && self.resources.is_none()
-        {
+    }
+
+    /// Validate inline permissions
+    pub fn validate(&self) -> Result<()> {
+        // At least one permission type should be specified
+        if self.is_empty() {
             bail!("Inline permissions must specify at least one permission type (network, storage, environment, or resources)");
         }
 
@@ -321,10 +453,270 @@ impl InlinePermissions {
             }
         }
 
+        // Validate resource limits
+        if let Some(resources) = &self.resources {
+            if resources.memory_bytes.is_none() && resources.cpu_time_ms.is_none() {
+                bail!("Resource limits must specify memory_bytes or cpu_time_ms");
+            }
+            if resources.memory_bytes == Some(0) {
+                bail!("Resource limit memory_bytes cannot be zero");
+            }
+            if resources.cpu_time_ms == Some(0) {
+                bail!("Resource limit cpu_time_ms cannot be zero");
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Reads manifest files, expanding variables and merging includes
+struct ManifestLoader<'a> {
+    vars: &'a HashMap<String, String>,
+    /// Files currently being loaded, to detect include cycles
+    stack: Vec<PathBuf>,
+}
+
+impl<'a> ManifestLoader<'a> {
+    fn new(vars: &'a HashMap<String, String>) -> Self {
+        Self {
+            vars,
+            stack: Vec::new(),
+        }
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<ProvisioningManifest> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest file: {}", path.display()))?;
+
+        let canonical = path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve manifest path: {}", path.display()))?;
+        if self.stack.contains(&canonical) {
+            bail!("Manifest include cycle at {}", path.display());
+        }
+
+        self.stack.push(canonical);
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let manifest = self
+            .load(&content, Some(path), base_dir)
+            .with_context(|| format!("Failed to parse manifest file: {}", path.display()));
+        self.stack.pop();
+        manifest
+    }
+
+    fn load(
+        &mut self,
+        content: &str,
+        file: Option<&Path>,
+        base_dir: &Path,
+    ) -> Result<ProvisioningManifest> {
+        let mut document: serde_yaml::Value =
+            serde_yaml::from_str(content).context("Failed to deserialize manifest YAML")?;
+        let written_uris = component_uris(&document);
+        expand_vars(&mut document, self.vars, "")?;
+        let parsed: ManifestFile =
+            serde_yaml::from_value(document).context("Failed to deserialize manifest YAML")?;
+
+        let mut components = Vec::new();
+        for include in &parsed.include {
+            let included = self.load_file(&base_dir.join(include))?;
+            if included.version != parsed.version {
+                bail!(
+                    "Included manifest {} has version {}, expected {}",
+                    include,
+                    included.version,
+                    parsed.version
+                );
+            }
+            components.extend(included.components);
+        }
+
+        let mut own_components = parsed.components;
+        locate_components(content, &written_uris, file, &mut own_components);
+        components.extend(own_components);
+
+        Ok(ProvisioningManifest {
+            version: parsed.version,
+            components,
+        })
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` references in the string values of a parsed manifest.
+/// `$${` is a literal `${`. Keys and comments are left alone, and every value stays a single
+/// scalar, so a variable cannot add keys or entries to the manifest.
+fn expand_vars(
+    value: &mut serde_yaml::Value,
+    vars: &HashMap<String, String>,
+    path: &str,
+) -> Result<()> {
+    match value {
+        serde_yaml::Value::String(text) => {
+            *text = expand_string(text, vars, path)?;
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                expand_vars(item, vars, &format!("{path}[{index}]"))?;
+            }
+        }
+        serde_yaml::Value::Mapping(entries) => {
+            for (key, item) in entries.iter_mut() {
+                let key = match key.as_str() {
+                    Some(key) => key.to_string(),
+                    None => format!("{key:?}"),
+                };
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{path}.{key}")
+                };
+                expand_vars(item, vars, &path)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => expand_vars(&mut tagged.value, vars, path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand the references in a single string value found at `path`
+fn expand_string(text: &str, vars: &HashMap<String, String>, path: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${") else {
+            expanded.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = after
+            .find('}')
+            .with_context(|| format!("Unterminated variable reference at {}", path))?;
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after[..end], None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!("Invalid variable name '{}' at {}", name, path);
+        }
+
+        // Like the shell, a default also replaces an empty value
+        let value = vars
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+            .filter(|value| default.is_none() || !value.is_empty());
+        match (value, default) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => bail!("Variable '{}' referenced at {} is not set", name, path),
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+/// The `uri` of every component as written in the manifest, before variables are expanded
+fn component_uris(document: &serde_yaml::Value) -> Vec<Option<String>> {
+    document
+        .get("components")
+        .and_then(serde_yaml::Value::as_sequence)
+        .map(|components| {
+            components
+                .iter()
+                .map(|component| {
+                    component
+                        .get("uri")
+                        .and_then(serde_yaml::Value::as_str)
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Record the line each component's `uri` appears on. `written_uris` holds the URIs as written,
+/// in the same order as `components`; a line matches only if it holds a component's own `uri`
+/// key and its value is exactly that URI.
+fn locate_components(
+    content: &str,
+    written_uris: &[Option<String>],
+    file: Option<&Path>,
+    components: &mut [ComponentDeclaration],
+) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut next = lines
+        .iter()
+        .position(|line| line.starts_with("components:"))
+        .unwrap_or(0);
+    // Indentation of the `-` that starts each component
+    let Some(item_indent) = lines[next..]
+        .iter()
+        .find(|line| line.trim_start().starts_with('-'))
+        .map(|line| indentation(line))
+    else {
+        return;
+    };
+
+    for (component, written_uri) in components.iter_mut().zip(written_uris) {
+        let Some(written_uri) = written_uri else {
+            continue;
+        };
+        let Some(offset) = lines[next..]
+            .iter()
+            .position(|line| component_uri_value(line, item_indent) == Some(written_uri.as_str()))
+        else {
+            continue;
+        };
+        next += offset;
+        component.source = Some(SourceLocation {
+            file: file.map(Path::to_path_buf),
+            line: next + 1,
+        });
+        next += 1;
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The value of a component's own `uri` key on a manifest line, without quotes or a trailing
+/// comment. Keys nested deeper in the component, such as `policy.uri`, are not matched.
+fn component_uri_value(line: &str, item_indent: usize) -> Option<&str> {
+    let indent = indentation(line);
+    let line = line.trim_start();
+    let key = match line.strip_prefix('-') {
+        Some(rest) if indent == item_indent => rest.trim_start(),
+        None if indent == item_indent + 2 => line,
+        _ => return None,
+    };
+    let value = key.strip_prefix("uri:")?.trim();
+    let value = value
+        .split_once(" #")
+        .map_or(value, |(value, _)| value)
+        .trim_end();
+    Some(
+        ["\"", "'"]
+            .iter()
+            .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -346,7 +738,7 @@ components:
           - key: OPENWEATHER_API_KEY
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert_eq!(manifest.version, 1);
         assert_eq!(manifest.components.len(), 1);
         assert_eq!(
@@ -376,11 +768,32 @@ components:
             access: [read, write]
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert_eq!(manifest.components.len(), 2);
         manifest.validate().unwrap();
     }
 
+    #[test]
+    fn test_parse_component_args() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: file:///opt/components/tool.wasm
+    args: ["tool", "--verbose"]
+    permissions:
+      network:
+        allow:
+          - host: api.example.com
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        assert_eq!(
+            manifest.components[0].args,
+            Some(vec!["tool".to_string(), "--verbose".to_string()])
+        );
+        manifest.validate().unwrap();
+    }
+
     #[test]
     fn test_invalid_version() {
         let manifest = ProvisioningManifest {
@@ -418,7 +831,7 @@ components:
           - host: api.example.com
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
 
@@ -434,7 +847,7 @@ components:
           - host: api.example.com
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
 
@@ -451,20 +864,62 @@ components:
           - host: api.example.com
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
 
     #[test]
-    fn test_empty_inline_permissions() {
+    fn test_component_without_permissions() {
         let yaml = r#"
 version: 1
 components:
   - uri: oci://example.com/component:latest
     permissions: {}
+  - uri: oci://example.com/other:latest
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        manifest.validate().unwrap();
+        assert!(manifest
+            .components
+            .iter()
+            .all(|component| component.permissions.is_empty()));
+    }
+
+    #[test]
+    fn test_policy_reference() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://example.com/component:latest
+    policy:
+      uri: oci://example.com/policies/shared:1.0
+  - uri: oci://example.com/other:latest
+    policy:
+      uri: https://example.com/shared.policy.yaml
+    permissions:
+      network:
+        allow:
+          - host: api.example.com
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        assert_eq!(
+            manifest.components[0].policy.as_ref().unwrap().uri,
+            "oci://example.com/policies/shared:1.0"
+        );
+        assert!(manifest.components[0].permissions.is_empty());
+        manifest.validate().unwrap();
+
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://example.com/component:latest
+    policy:
+      uri: /etc/wassette/shared.policy.yaml
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
 
@@ -481,7 +936,57 @@ components:
             access: [read]
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_policy_and_resources() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://example.com/component:latest
+    permissions:
+      resources:
+        memory_bytes: 67108864
+        cpu_time_ms: 500
+    retry_policy:
+      attempts: 3
+      backoff:
+        type: exponential
+        base_ms: 200
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        assert!(manifest.validate().is_ok());
+
+        let component = &manifest.components[0];
+        let resources = component.permissions.resources.as_ref().unwrap();
+        assert_eq!(resources.memory_bytes, Some(64 * 1024 * 1024));
+        assert_eq!(resources.cpu_time_ms, Some(500));
+
+        let retry_policy = component.retry_policy.as_ref().unwrap().to_retry_policy();
+        assert_eq!(retry_policy.attempts, 3);
+        assert_eq!(
+            retry_policy.backoff,
+            wassette::Backoff::Exponential {
+                base: Duration::from_millis(200)
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_resource_limits() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://example.com/component:latest
+    permissions:
+      resources:
+        cpu_time_ms: 0
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
 
@@ -498,7 +1003,236 @@ components:
           - key: API_KEY
 "#;
 
-        let manifest = ProvisioningManifest::from_yaml(yaml).unwrap();
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
         assert!(manifest.validate().is_err());
     }
+
+    #[test]
+    fn test_variable_expansion() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://${REGISTRY}/weather:${TAG:-latest}
+    permissions:
+      network:
+        allow:
+          - host: ${WEATHER_HOST:-api.openweathermap.org}
+      storage:
+        allow:
+          - uri: fs://$${literal}
+            access: [read]
+"#;
+
+        let vars = HashMap::from([
+            ("REGISTRY".to_string(), "ghcr.io/example".to_string()),
+            ("TAG".to_string(), String::new()),
+        ]);
+        let manifest = ProvisioningManifest::from_yaml(yaml, &vars).unwrap();
+        let component = &manifest.components[0];
+        assert_eq!(component.uri, "oci://ghcr.io/example/weather:latest");
+        assert_eq!(
+            component.permissions.network.as_ref().unwrap().allow[0].host,
+            "api.openweathermap.org"
+        );
+        assert_eq!(
+            component.permissions.storage.as_ref().unwrap().allow[0].uri,
+            "fs://${literal}"
+        );
+    }
+
+    #[test]
+    fn test_unset_variable() {
+        let yaml = "version: 1\ncomponents:\n  - uri: oci://${WASSETTE_TEST_UNSET_REGISTRY}/c\n";
+
+        let err = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("WASSETTE_TEST_UNSET_REGISTRY"));
+        assert!(err.to_string().contains("components[0].uri"));
+    }
+
+    #[test]
+    fn test_variables_cannot_change_manifest_structure() {
+        let yaml = r#"
+version: 1
+components:
+  # ${WASSETTE_TEST_UNSET_IN_COMMENT} is not expanded
+  - uri: oci://example.com/app:${TAG}
+    permissions:
+      resources:
+        memory_bytes: ${MEMORY}
+"#;
+
+        let vars = HashMap::from([
+            (
+                "TAG".to_string(),
+                "1.0\n    digest: \"sha256:0000\"".to_string(),
+            ),
+            ("MEMORY".to_string(), "1048576".to_string()),
+        ]);
+        let manifest = ProvisioningManifest::from_yaml(yaml, &vars).unwrap();
+        let component = &manifest.components[0];
+        assert_eq!(
+            component.uri,
+            "oci://example.com/app:1.0\n    digest: \"sha256:0000\""
+        );
+        assert!(component.digest.is_none());
+        assert_eq!(
+            component
+                .permissions
+                .resources
+                .as_ref()
+                .unwrap()
+                .memory_bytes,
+            Some(1048576)
+        );
+    }
+
+    #[test]
+    fn test_numeric_variables_stay_strings() {
+        let yaml = r#"
+version: 1
+components:
+  - uri: oci://example.com/app:1.0
+    args: ["--port", "${PORT}"]
+    permissions:
+      resources:
+        cpu_time_ms: "${TIMEOUT}"
+"#;
+
+        let vars = HashMap::from([
+            ("PORT".to_string(), "8080".to_string()),
+            ("TIMEOUT".to_string(), "5000".to_string()),
+        ]);
+        let manifest = ProvisioningManifest::from_yaml(yaml, &vars).unwrap();
+        let component = &manifest.components[0];
+        assert_eq!(
+            component.args,
+            Some(vec!["--port".to_string(), "8080".to_string()])
+        );
+        assert_eq!(
+            component
+                .permissions
+                .resources
+                .as_ref()
+                .unwrap()
+                .cpu_time_ms,
+            Some(5000)
+        );
+
+        let vars = HashMap::from([
+            ("PORT".to_string(), "8080".to_string()),
+            ("TIMEOUT".to_string(), "soon".to_string()),
+        ]);
+        let err = ProvisioningManifest::from_yaml(yaml, &vars).unwrap_err();
+        assert!(format!("{err:#}").contains("expected a whole number"));
+    }
+
+    #[test]
+    fn test_components_are_located_by_exact_uri() {
+        let yaml = r#"version: 1
+components:
+  - uri: oci://example.com/app:1.0-debug
+    policy:
+      uri: "oci://example.com/app:1.0"
+  - uri: oci://example.com/app:1.0
+"#;
+
+        let manifest = ProvisioningManifest::from_yaml(yaml, &HashMap::new()).unwrap();
+        assert_eq!(manifest.components[0].source.as_ref().unwrap().line, 3);
+        assert_eq!(manifest.components[1].source.as_ref().unwrap().line, 6);
+    }
+
+    #[test]
+    fn test_includes_merge_components() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("shared")).unwrap();
+        std::fs::write(
+            dir.path().join("shared/base.yaml"),
+            r#"version: 1
+components:
+  - uri: oci://example.com/base:latest
+    permissions:
+      network:
+        allow:
+          - host: ${HOST}
+"#,
+        )
+        .unwrap();
+        let main = dir.path().join("manifest.yaml");
+        std::fs::write(
+            &main,
+            r#"version: 1
+include:
+  - shared/base.yaml
+components:
+  - uri: oci://example.com/app:latest
+    permissions:
+      network:
+        allow:
+          - host: api.example.com
+"#,
+        )
+        .unwrap();
+
+        let vars = HashMap::from([("HOST".to_string(), "base.example.com".to_string())]);
+        let manifest = ProvisioningManifest::from_file(&main, &vars).unwrap();
+        let uris: Vec<_> = manifest.components.iter().map(|c| c.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            [
+                "oci://example.com/base:latest",
+                "oci://example.com/app:latest"
+            ]
+        );
+        assert_eq!(
+            manifest.components[0].source,
+            Some(SourceLocation {
+                file: Some(dir.path().join("shared/base.yaml")),
+                line: 3,
+            })
+        );
+        assert_eq!(manifest.components[1].source.as_ref().unwrap().line, 5);
+        manifest.validate().unwrap();
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), "version: 1\ninclude: [b.yaml]\n").unwrap();
+        std::fs::write(dir.path().join("b.yaml"), "version: 1\ninclude: [a.yaml]\n").unwrap();
+
+        let err = ProvisioningManifest::from_file(&dir.path().join("a.yaml"), &HashMap::new())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("include cycle"));
+    }
+
+    #[test]
+    fn test_validation_error_reports_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.yaml");
+        std::fs::write(
+            &path,
+            r#"version: 1
+components:
+  - uri: oci://example.com/good:latest
+    permissions:
+      network:
+        allow:
+          - host: api.example.com
+  - uri: oci://example.com/bad:latest
+    digest: invalid-digest
+    permissions:
+      network:
+        allow:
+          - host: api.example.com
+"#,
+        )
+        .unwrap();
+
+        let manifest = ProvisioningManifest::from_file(&path, &HashMap::new()).unwrap();
+        let err = manifest.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Invalid component at {}:8", path.display())
+        );
+    }
 }
@@ -1,13 +1,17 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use policy::{
-    AccessType as PolicyAccessType, EnvironmentPermission, EnvironmentPermissions,
-    NetworkHostPermission, NetworkPermission, PermissionList, PolicyDocument, StoragePermission,
+    AccessType as PolicyAccessType, EnvironmentPermission, EnvironmentPermissions, MemoryLimit,
+    NetworkHostPermission, NetworkPermission, PermissionList, PolicyDocument, ResourceLimitValues,
+    ResourceLimits, StoragePermission,
 };
 
-use crate::manifest::{AccessType, InlinePermissions};
+use crate::manifest::{
+    self, AccessType, ComponentDeclaration, EnvironmentRule, InlinePermissions, NetworkRule,
+    StorageRule,
+};
 
 /// Synthesize a PolicyDocument from inline permissions in the manifest
 pub fn synthesize_policy_from_inline(
@@ -21,7 +25,18 @@ pub fn synthesize_policy_from_inline(
             component_name.unwrap_or("component")
         )),
     );
+    apply_inline_permissions(&mut policy, inline);
+
+    // Validate the generated policy
+    policy
+        .validate()
+        .context("Generated policy failed validation")?;
+
+    Ok(policy)
+}
 
+/// Replace every section of `policy` that `inline` declares
+fn apply_inline_permissions(policy: &mut PolicyDocument, inline: &InlinePermissions) {
     // Convert network permissions
     if let Some(network_perms) = &inline.network {
         let mut network_allow = Vec::new();
@@ -53,6 +68,8 @@ pub fn synthesize_policy_from_inline(
             storage_allow.push(StoragePermission {
                 uri: rule.uri.clone(),
                 access,
+                mount: rule.mount.clone(),
+                memory: None,
             });
         }
 
@@ -76,26 +93,154 @@ pub fn synthesize_policy_from_inline(
         });
     }
 
-    // Validate the generated policy
-    policy
-        .validate()
-        .context("Generated policy failed validation")?;
+    // Convert resource limits
+    if let Some(resources) = &inline.resources {
+        let mut limits = ResourceLimitValues::new(
+            None,
+            resources
+                .memory_bytes
+                .map(|bytes| MemoryLimit::String(bytes.to_string())),
+        );
+        limits.cpu_time_ms = resources.cpu_time_ms;
+
+        policy.permissions.resources = Some(ResourceLimits {
+            limits: Some(limits),
+            ..Default::default()
+        });
+    }
+}
 
+/// Synthesize the complete PolicyDocument for a manifest component declaration. `base` is the
+/// policy the declaration references, if any; inline permissions replace its sections.
+pub fn synthesize_policy_for_component(
+    component: &ComponentDeclaration,
+    base: Option<PolicyDocument>,
+) -> Result<PolicyDocument> {
+    let mut policy = match base {
+        Some(mut policy) => {
+            apply_inline_permissions(&mut policy, &component.permissions);
+            policy
+                .validate()
+                .context("Policy with inline overrides failed validation")?;
+            policy
+        }
+        None => synthesize_policy_from_inline(&component.permissions, component.name.as_deref())?,
+    };
+    if component.args.is_some() {
+        policy.args = component.args.clone();
+    }
     Ok(policy)
 }
 
-/// Serialize a PolicyDocument to YAML string
-pub fn serialize_policy_to_yaml(policy: &PolicyDocument) -> Result<String> {
-    serde_yaml::to_string(policy).context("Failed to serialize policy to YAML")
-}
+/// Convert a policy back into inline manifest permissions. Fails if the policy uses anything
+/// inline permissions cannot express, rather than silently dropping it.
+pub fn inline_permissions_from_policy(policy: &PolicyDocument) -> Result<InlinePermissions> {
+    let permissions = &policy.permissions;
+    let mut unsupported = Vec::new();
+    if permissions.runtime.is_some() {
+        unsupported.push("runtime");
+    }
+    if permissions.ipc.is_some() {
+        unsupported.push("ipc");
+    }
+    if permissions.clock.is_some() {
+        unsupported.push("clock");
+    }
+    if permissions.random.is_some() {
+        unsupported.push("random");
+    }
 
-/// Full synthesis: convert inline permissions to YAML string
-pub fn synthesize_policy_yaml(
-    inline: &InlinePermissions,
-    component_name: Option<&str>,
-) -> Result<String> {
-    let policy = synthesize_policy_from_inline(inline, component_name)?;
-    serialize_policy_to_yaml(&policy)
+    let mut inline = InlinePermissions::default();
+
+    if let Some(network) = &permissions.network {
+        if network.deny.is_some() {
+            unsupported.push("network deny rules");
+        }
+        let mut allow = Vec::new();
+        for rule in network.allow.iter().flatten() {
+            match rule {
+                NetworkPermission::Host(host) => allow.push(NetworkRule {
+                    host: host.host.clone(),
+                }),
+                NetworkPermission::Cidr(_) => unsupported.push("network CIDR rules"),
+            }
+        }
+        if !allow.is_empty() {
+            inline.network = Some(manifest::NetworkPermissions { allow });
+        }
+    }
+
+    if let Some(storage) = &permissions.storage {
+        if storage.deny.is_some() {
+            unsupported.push("storage deny rules");
+        }
+        let mut allow = Vec::new();
+        for rule in storage.allow.iter().flatten() {
+            if !rule.uri.starts_with("fs://") || rule.memory.is_some() {
+                unsupported.push("non-filesystem storage");
+                continue;
+            }
+            allow.push(StorageRule {
+                uri: rule.uri.clone(),
+                access: rule
+                    .access
+                    .iter()
+                    .map(|access| match access {
+                        PolicyAccessType::Read => AccessType::Read,
+                        PolicyAccessType::Write => AccessType::Write,
+                    })
+                    .collect(),
+                mount: rule.mount.clone(),
+            });
+        }
+        if !allow.is_empty() {
+            inline.storage = Some(manifest::StoragePermissions { allow });
+        }
+    }
+
+    if let Some(environment) = &permissions.environment {
+        let allow: Vec<_> = environment
+            .allow
+            .iter()
+            .flatten()
+            .map(|rule| EnvironmentRule {
+                key: rule.key.clone(),
+                value_from: None,
+            })
+            .collect();
+        if !allow.is_empty() {
+            inline.environment = Some(manifest::EnvironmentPermissions { allow });
+        }
+    }
+
+    if let Some(resources) = &permissions.resources {
+        if resources.cpu.is_some() || resources.memory.is_some() || resources.io.is_some() {
+            unsupported.push("legacy resource limits");
+        }
+        if let Some(limits) = &resources.limits {
+            if limits.cpu.is_some() {
+                unsupported.push("CPU limits");
+            }
+            let memory_bytes = limits
+                .memory_bytes()
+                .context("Invalid memory limit in policy")?;
+            if memory_bytes.is_some() || limits.cpu_time_ms.is_some() {
+                inline.resources = Some(manifest::ResourceLimits {
+                    memory_bytes,
+                    cpu_time_ms: limits.cpu_time_ms,
+                });
+            }
+        }
+    }
+
+    if !unsupported.is_empty() {
+        bail!(
+            "Policy uses permissions that inline manifest permissions cannot express: {}",
+            unsupported.join(", ")
+        );
+    }
+
+    Ok(inline)
 }
 
 #[cfg(test)]
@@ -104,9 +249,15 @@ mod tests {
     use crate::manifest::{
         EnvironmentPermissions as ManifestEnvPerms, EnvironmentRule,
         NetworkPermissions as ManifestNetPerms, NetworkRule,
-        StoragePermissions as ManifestStoragePerms, StorageRule,
+        ResourceLimits as ManifestResourceLimits, StoragePermissions as ManifestStoragePerms,
+        StorageRule,
     };
 
+    fn synthesize_yaml(component: &ComponentDeclaration) -> String {
+        let policy = synthesize_policy_for_component(component, None).unwrap();
+        serde_yaml::to_string(&policy).unwrap()
+    }
+
     #[test]
     fn test_synthesize_network_only() {
         let inline = InlinePermissions {
@@ -148,6 +299,7 @@ mod tests {
                 allow: vec![StorageRule {
                     uri: "fs:///tmp/data".to_string(),
                     access: vec![AccessType::Read, AccessType::Write],
+                    mount: None,
                 }],
             }),
             environment: None,
@@ -204,6 +356,7 @@ mod tests {
                 allow: vec![StorageRule {
                     uri: "fs:///tmp/data".to_string(),
                     access: vec![AccessType::Read],
+                    mount: None,
                 }],
             }),
             environment: Some(ManifestEnvPerms {
@@ -223,19 +376,191 @@ mod tests {
     }
 
     #[test]
-    fn test_synthesize_to_yaml() {
+    fn test_synthesize_policy_for_component_args() {
+        let component = ComponentDeclaration {
+            uri: "file:///opt/components/tool.wasm".to_string(),
+            name: Some("tool".to_string()),
+            digest: None,
+            policy: None,
+            permissions: InlinePermissions {
+                network: Some(ManifestNetPerms {
+                    allow: vec![NetworkRule {
+                        host: "api.example.com".to_string(),
+                    }],
+                }),
+                storage: None,
+                environment: None,
+                resources: None,
+            },
+            args: Some(vec!["tool".to_string(), "--verbose".to_string()]),
+            retry_policy: None,
+            source: None,
+        };
+
+        let policy = synthesize_policy_for_component(&component, None).unwrap();
+        assert_eq!(
+            policy.args,
+            Some(vec!["tool".to_string(), "--verbose".to_string()])
+        );
+
+        let yaml = synthesize_yaml(&component);
+        assert!(yaml.contains("args:"));
+        assert!(yaml.contains("--verbose"));
+    }
+
+    #[test]
+    fn test_synthesize_resource_limits() {
+        let inline = InlinePermissions {
+            network: None,
+            storage: None,
+            environment: None,
+            resources: Some(ManifestResourceLimits {
+                memory_bytes: Some(64 * 1024 * 1024),
+                cpu_time_ms: Some(500),
+            }),
+        };
+
+        let policy = synthesize_policy_from_inline(&inline, Some("test-component")).unwrap();
+        let yaml = serde_yaml::to_string(&policy).unwrap();
+
+        // The limits survive a round trip through the policy file
+        let parsed = policy::PolicyParser::parse_str(&yaml).unwrap();
+        let limits = parsed.permissions.resources.unwrap().limits.unwrap();
+        assert_eq!(limits.memory_bytes().unwrap(), Some(64 * 1024 * 1024));
+        assert_eq!(limits.cpu_time_ms, Some(500));
+    }
+
+    #[test]
+    fn test_inline_permissions_override_referenced_policy() {
+        let base = policy::PolicyParser::parse_str(
+            r#"
+version: "1.0"
+description: "Shared policy"
+permissions:
+  network:
+    allow:
+      - host: shared.example.com
+  environment:
+    allow:
+      - key: SHARED_KEY
+"#,
+        )
+        .unwrap();
+
+        let component = ComponentDeclaration {
+            uri: "oci://example.com/test-component:latest".to_string(),
+            name: None,
+            digest: None,
+            policy: Some(crate::manifest::PolicyReference {
+                uri: "file:///etc/wassette/shared.policy.yaml".to_string(),
+            }),
+            permissions: InlinePermissions {
+                network: Some(ManifestNetPerms {
+                    allow: vec![NetworkRule {
+                        host: "api.example.com".to_string(),
+                    }],
+                }),
+                storage: None,
+                environment: None,
+                resources: None,
+            },
+            args: None,
+            retry_policy: None,
+            source: None,
+        };
+
+        let policy = synthesize_policy_for_component(&component, Some(base)).unwrap();
+        assert_eq!(policy.description.as_deref(), Some("Shared policy"));
+
+        // The inline network section replaces the referenced one...
+        let network = policy.permissions.network.unwrap().allow.unwrap();
+        assert_eq!(network.len(), 1);
+        match &network[0] {
+            NetworkPermission::Host(host) => assert_eq!(host.host, "api.example.com"),
+            _ => panic!("Expected host permission"),
+        }
+
+        // ...while sections it does not declare are kept
+        let environment = policy.permissions.environment.unwrap().allow.unwrap();
+        assert_eq!(environment[0].key, "SHARED_KEY");
+    }
+
+    #[test]
+    fn test_inline_permissions_round_trip() {
         let inline = InlinePermissions {
             network: Some(ManifestNetPerms {
                 allow: vec![NetworkRule {
                     host: "api.example.com".to_string(),
                 }],
             }),
-            storage: None,
-            environment: None,
-            resources: None,
+            storage: Some(ManifestStoragePerms {
+                allow: vec![StorageRule {
+                    uri: "fs:///tmp/data".to_string(),
+                    access: vec![AccessType::Read, AccessType::Write],
+                    mount: Some("/data".to_string()),
+                }],
+            }),
+            environment: Some(ManifestEnvPerms {
+                allow: vec![EnvironmentRule {
+                    key: "API_KEY".to_string(),
+                    value_from: None,
+                }],
+            }),
+            resources: Some(ManifestResourceLimits {
+                memory_bytes: Some(67108864),
+                cpu_time_ms: Some(500),
+            }),
+        };
+
+        let policy = synthesize_policy_from_inline(&inline, None).unwrap();
+        let exported = inline_permissions_from_policy(&policy).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&exported).unwrap(),
+            serde_yaml::to_string(&inline).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_inline_permissions_reject_unsupported_policy() {
+        let policy = policy::PolicyParser::parse_str(
+            r#"
+version: "1.0"
+permissions:
+  network:
+    allow:
+      - host: api.example.com
+      - cidr: 10.0.0.0/8
+"#,
+        )
+        .unwrap();
+
+        let err = inline_permissions_from_policy(&policy).unwrap_err();
+        assert!(err.to_string().contains("network CIDR rules"));
+    }
+
+    #[test]
+    fn test_synthesize_to_yaml() {
+        let component = ComponentDeclaration {
+            uri: "oci://example.com/test-component:latest".to_string(),
+            name: Some("test-component".to_string()),
+            digest: None,
+            policy: None,
+            permissions: InlinePermissions {
+                network: Some(ManifestNetPerms {
+                    allow: vec![NetworkRule {
+                        host: "api.example.com".to_string(),
+                    }],
+                }),
+                storage: None,
+                environment: None,
+                resources: None,
+            },
+            args: None,
+            retry_policy: None,
+            source: None,
         };
 
-        let yaml = synthesize_policy_yaml(&inline, Some("test-component")).unwrap();
+        let yaml = synthesize_yaml(&component);
 
         // Check that YAML is valid and contains expected fields
         assert!(yaml.contains("version:"));
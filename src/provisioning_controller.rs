@@ -1,13 +1,16 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
-use wassette::{LifecycleManager, SecretsManager};
+use policy::{PolicyDocument, PolicyParser};
+use wassette::{component_id_from_uri, ComponentVersion, LifecycleManager, SecretsManager};
 
-use crate::manifest::{ComponentDeclaration, ProvisioningManifest};
+use crate::lockfile::{LockedComponent, Lockfile};
+use crate::manifest::{ComponentDeclaration, InlinePermissions, ProvisioningManifest, RetryPolicy};
 use crate::permission_synthesis;
 
 /// Controller for provisioning components from a manifest
@@ -16,7 +19,8 @@ pub struct ProvisioningController<'a> {
     lifecycle_manager: &'a LifecycleManager,
     #[allow(dead_code)] // Reserved for future use in secrets seeding
     secrets_manager: &'a SecretsManager,
-    plugin_dir: &'a Path,
+    /// Digests pinned by a previous run, used for components without an explicit digest
+    lockfile: Option<Lockfile>,
 }
 
 impl<'a> ProvisioningController<'a> {
@@ -25,24 +29,31 @@ impl<'a> ProvisioningController<'a> {
         manifest: &'a ProvisioningManifest,
         lifecycle_manager: &'a LifecycleManager,
         secrets_manager: &'a SecretsManager,
-        plugin_dir: &'a Path,
     ) -> Self {
         Self {
             manifest,
             lifecycle_manager,
             secrets_manager,
-            plugin_dir,
+            lockfile: None,
         }
     }
 
-    /// Provision all components from the manifest
-    pub async fn provision(&self) -> Result<()> {
+    /// Pin components to the digests recorded in `lockfile`
+    pub fn with_lockfile(mut self, lockfile: Option<Lockfile>) -> Self {
+        self.lockfile = lockfile;
+        self
+    }
+
+    /// Provision all components from the manifest, returning a lockfile that pins every
+    /// component URI to the digest it was installed from
+    pub async fn provision(&self) -> Result<Lockfile> {
         tracing::info!(
             "Starting provisioning of {} component(s)",
             self.manifest.components.len()
         );
 
         let mut errors = Vec::new();
+        let mut lockfile = Lockfile::default();
 
         for (idx, component) in self.manifest.components.iter().enumerate() {
             let component_name = component.name.as_deref().unwrap_or(&component.uri);
@@ -54,12 +65,20 @@ impl<'a> ProvisioningController<'a> {
                 component_name
             );
 
-            if let Err(e) = self.provision_component(component).await {
-                tracing::error!("Failed to provision component {}: {}", component_name, e);
-                errors.push((component_name.to_string(), e));
+            match self.provision_component(component).await {
+                Ok(locked) => lockfile.components.push(locked),
+                Err(e) => {
+                    tracing::error!("Failed to provision component {}: {}", component_name, e);
+                    errors.push((component_name.to_string(), e));
+                }
             }
         }
 
+        Self::finish(lockfile, errors)
+    }
+
+    /// Returns `lockfile`, or an error listing every component that failed
+    fn finish(lockfile: Lockfile, errors: Vec<(String, anyhow::Error)>) -> Result<Lockfile> {
         if !errors.is_empty() {
             let error_summary = errors
                 .iter()
@@ -75,43 +94,143 @@ impl<'a> ProvisioningController<'a> {
         }
 
         tracing::info!("Successfully provisioned all components");
-        Ok(())
+        Ok(lockfile)
     }
 
     /// Provision a single component
-    async fn provision_component(&self, component: &ComponentDeclaration) -> Result<()> {
+    async fn provision_component(
+        &self,
+        component: &ComponentDeclaration,
+    ) -> Result<LockedComponent> {
         // Step 1: Seed secrets from environment variables
         self.seed_secrets(component)
             .context("Failed to seed secrets")?;
 
-        // Step 2: Synthesize and write policy file
-        let policy_path = self
-            .synthesize_policy(component)
+        // Step 2: Synthesize the policy
+        let policy = self
+            .desired_policy(component)
+            .await
             .context("Failed to synthesize policy")?;
 
-        tracing::debug!(
-            "Synthesized policy for component to: {}",
-            policy_path.display()
-        );
-
         // Step 3: Load component using existing lifecycle manager
         // Note: The lifecycle manager will automatically:
-        // - Download the component from the URI
+        // - Download the component from the URI, retrying transient failures per the
+        //   component's retry policy
+        // - Verify it against the expected digest before installing it
         // - Compile and cache it
-        // - Load the co-located policy file we just created
         // - Register the component and its tools
-        self.lifecycle_manager
-            .load_component(&component.uri)
+        let expected_digest = self.expected_digest(component);
+        let retry_policy = component
+            .retry_policy
+            .as_ref()
+            .map(RetryPolicy::to_retry_policy)
+            .unwrap_or_default();
+        let outcome = retry_policy
+            .run(|| async move {
+                match expected_digest {
+                    Some(digest) => {
+                        self.lifecycle_manager
+                            .load_component_with_digest(&component.uri, digest)
+                            .await
+                    }
+                    None => self.lifecycle_manager.load_component(&component.uri).await,
+                }
+            })
             .await
             .with_context(|| format!("Failed to load component from URI: {}", component.uri))?;
 
-        // Step 4: Verify digest if specified
-        if let Some(digest) = &component.digest {
-            self.verify_digest(component, digest)
-                .context("Digest verification failed")?;
+        // Step 4: Attach the synthesized policy, replacing whatever policy the component had
+        self.apply_policy(component, &outcome.component_id, policy.as_ref())
+            .await?;
+
+        // OCI components are pinned by their manifest digest, everything else by its bytes
+        Ok(LockedComponent {
+            uri: component.uri.clone(),
+            digest: expected_digest
+                .map(str::to_string)
+                .or(outcome.manifest_digest)
+                .unwrap_or(outcome.digest),
+        })
+    }
+
+    /// Rewrite the policy of an installed component without reloading it
+    async fn update_component_policy(
+        &self,
+        component: &ComponentDeclaration,
+        component_id: &str,
+    ) -> Result<LockedComponent> {
+        self.seed_secrets(component)
+            .context("Failed to seed secrets")?;
+        let policy = self
+            .desired_policy(component)
+            .await
+            .context("Failed to synthesize policy")?;
+        self.apply_policy(component, component_id, policy.as_ref())
+            .await?;
+        self.lock_installed(component, component_id).await
+    }
+
+    /// Attach `policy` to a component, or detach its policy if the manifest declares none. The
+    /// manifest file the component was declared in is the policy's source for the trust policy.
+    async fn apply_policy(
+        &self,
+        component: &ComponentDeclaration,
+        component_id: &str,
+        policy: Option<&PolicyDocument>,
+    ) -> Result<()> {
+        let source_uri = component
+            .source
+            .as_ref()
+            .and_then(|source| source.file.as_ref())
+            .map(|file| format!("file://{}", file.display()));
+        match policy {
+            Some(policy) => self
+                .lifecycle_manager
+                .attach_policy_document(component_id, policy, source_uri.as_deref())
+                .await
+                .context("Failed to attach synthesized policy"),
+            None => self
+                .lifecycle_manager
+                .detach_policy(component_id)
+                .await
+                .context("Failed to detach policy"),
         }
+    }
 
-        Ok(())
+    /// Pin a component to the version that is already installed
+    async fn lock_installed(
+        &self,
+        component: &ComponentDeclaration,
+        component_id: &str,
+    ) -> Result<LockedComponent> {
+        let installed = self.installed_version(component_id).await?;
+        Ok(LockedComponent {
+            uri: component.uri.clone(),
+            digest: self
+                .expected_digest(component)
+                .unwrap_or(installed.pinned_digest())
+                .to_string(),
+        })
+    }
+
+    /// The version of a component that is currently in use
+    async fn installed_version(&self, component_id: &str) -> Result<ComponentVersion> {
+        self.lifecycle_manager
+            .list_component_versions(component_id)
+            .await?
+            .into_iter()
+            .find(|version| version.active)
+            .with_context(|| format!("Component {component_id} has no active version"))
+    }
+
+    /// The digest a component must match: the one declared in the manifest, or else the one
+    /// pinned by the lockfile
+    fn expected_digest<'b>(&'b self, component: &'b ComponentDeclaration) -> Option<&'b str> {
+        component.digest.as_deref().or_else(|| {
+            self.lockfile
+                .as_ref()
+                .and_then(|lockfile| lockfile.digest_for(&component.uri))
+        })
     }
 
     /// Seed secrets from environment variables
@@ -159,80 +278,791 @@ impl<'a> ProvisioningController<'a> {
         Ok(())
     }
 
-    /// Synthesize policy from inline permissions
-    fn synthesize_policy(&self, component: &ComponentDeclaration) -> Result<PathBuf> {
-        // Synthesize policy YAML
-        let policy_yaml = permission_synthesis::synthesize_policy_yaml(
-            &component.permissions,
-            component.name.as_deref(),
-        )
-        .context("Failed to synthesize policy from inline permissions")?;
+    /// The policy a component should end up with: the policy it references, if any, with its
+    /// inline permissions applied on top. A component that declares no policy, permissions or
+    /// arguments runs without a policy.
+    async fn desired_policy(
+        &self,
+        component: &ComponentDeclaration,
+    ) -> Result<Option<PolicyDocument>> {
+        if component.policy.is_none()
+            && component.permissions.is_empty()
+            && component.args.is_none()
+        {
+            return Ok(None);
+        }
+        let base = match &component.policy {
+            Some(reference) => Some(
+                self.lifecycle_manager
+                    .fetch_policy(&reference.uri)
+                    .await
+                    .with_context(|| format!("Failed to fetch policy from {}", reference.uri))?,
+            ),
+            None => None,
+        };
+        permission_synthesis::synthesize_policy_for_component(component, base)
+            .map(Some)
+            .context("Failed to synthesize policy")
+    }
 
-        // We need to generate a predictable filename for the policy
-        // The lifecycle manager expects {component_id}.policy.yaml
-        // For now, we'll use a hash of the URI as a temporary name
-        // The lifecycle manager will rename it after loading
+    /// Compute the changes needed to make the installed components match the manifest. A
+    /// component whose state cannot be determined, e.g. because its policy cannot be fetched, is
+    /// recorded in the plan's errors and the remaining components are still planned.
+    pub async fn plan(&self) -> Result<ReconcilePlan> {
+        let known: HashSet<String> = self
+            .lifecycle_manager
+            .list_components_known()
+            .await
+            .into_iter()
+            .collect();
+
+        let mut plan = ReconcilePlan::default();
+        let mut declared = HashSet::new();
 
-        // Create a temporary policy file that will be discovered by the loader
-        let temp_policy_name = format!("temp_{}.policy.yaml", hash_string(&component.uri));
-        let policy_path = self.plugin_dir.join(temp_policy_name);
+        for component in &self.manifest.components {
+            let result = match component_id_from_uri(&component.uri) {
+                Ok(component_id) => {
+                    declared.insert(component_id.clone());
+                    self.plan_component(component, component_id, &known).await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(action) => plan.actions.push(action),
+                Err(e) => {
+                    let component_name = component.name.as_deref().unwrap_or(&component.uri);
+                    tracing::error!("Failed to plan component {}: {:#}", component_name, e);
+                    plan.errors
+                        .push((component_name.to_string(), format!("{e:#}")));
+                }
+            }
+        }
 
-        std::fs::write(&policy_path, policy_yaml).with_context(|| {
-            format!("Failed to write policy file to: {}", policy_path.display())
-        })?;
+        let mut extras: Vec<&String> = known.difference(&declared).collect();
+        extras.sort();
+        for component_id in extras {
+            plan.actions.push(PlanAction::Unload {
+                component_id: component_id.clone(),
+            });
+        }
 
-        Ok(policy_path)
+        Ok(plan)
     }
 
-    /// Verify component digest (SHA-256)
-    fn verify_digest(&self, component: &ComponentDeclaration, expected_digest: &str) -> Result<()> {
-        // Digest verification is deferred to post-MVP for simplicity
-        // The digest format was validated during manifest validation,
-        // but actual verification requires reading the downloaded component bytes
+    /// The action that brings one declared component in line with the manifest
+    async fn plan_component(
+        &self,
+        component: &ComponentDeclaration,
+        component_id: String,
+        known: &HashSet<String>,
+    ) -> Result<PlanAction> {
+        if !known.contains(&component_id) {
+            return Ok(PlanAction::Install {
+                component_id,
+                uri: component.uri.clone(),
+            });
+        }
 
-        tracing::warn!(
-            "Digest verification is not yet implemented for component: {}. Expected: {}",
-            component.name.as_deref().unwrap_or(&component.uri),
-            expected_digest
-        );
+        if let Some(expected) = self.expected_digest(component) {
+            if !self
+                .installed_version(&component_id)
+                .await?
+                .matches_digest(expected)
+            {
+                return Ok(PlanAction::Reinstall {
+                    component_id,
+                    uri: component.uri.clone(),
+                });
+            }
+        }
+
+        let desired = self.desired_policy(component).await?;
+        if self.current_policy(&component_id).await? == desired {
+            Ok(PlanAction::Keep { component_id })
+        } else {
+            Ok(PlanAction::UpdatePolicy { component_id })
+        }
+    }
+
+    /// Make the installed components match the manifest: install missing or outdated
+    /// components, rewrite drifted policies and then unload components it does not list.
+    /// Components that already match the manifest are left alone.
+    pub async fn reconcile(&self) -> Result<Lockfile> {
+        let plan = self.plan().await?;
+        if plan.has_changes() {
+            tracing::info!("Reconciling components:\n{}", plan);
+        }
+
+        let declarations: HashMap<String, &ComponentDeclaration> = self
+            .manifest
+            .components
+            .iter()
+            .filter_map(|component| {
+                component_id_from_uri(&component.uri)
+                    .ok()
+                    .map(|component_id| (component_id, component))
+            })
+            .collect();
+
+        let mut errors: Vec<(String, anyhow::Error)> = plan
+            .errors
+            .iter()
+            .map(|(name, error)| (name.clone(), anyhow::anyhow!("{error}")))
+            .collect();
+        let mut lockfile = Lockfile::default();
+        for action in &plan.actions {
+            if matches!(action, PlanAction::Unload { .. }) {
+                continue;
+            }
+            let component_id = action.component_id();
+            let component = declarations.get(component_id).copied().with_context(|| {
+                format!(
+                    "Plan refers to component {component_id}, which the manifest does not declare"
+                )
+            })?;
+            let component_name = component.name.as_deref().unwrap_or(&component.uri);
+            let result = match action {
+                PlanAction::Keep { .. } => self.lock_installed(component, component_id).await,
+                PlanAction::UpdatePolicy { .. } => {
+                    self.update_component_policy(component, component_id).await
+                }
+                // Install or Reinstall, unloads were skipped above
+                _ => self.provision_component(component).await,
+            };
+            match result {
+                Ok(locked) => lockfile.components.push(locked),
+                Err(e) => {
+                    tracing::error!("Failed to reconcile component {}: {}", component_name, e);
+                    errors.push((component_name.to_string(), e));
+                }
+            }
+        }
+
+        // Extras are unloaded once the declared components are in place
+        for action in &plan.actions {
+            if let PlanAction::Unload { component_id } = action {
+                if let Err(e) = self.lifecycle_manager.unload_component(component_id).await {
+                    tracing::error!("Failed to unload component {}: {}", component_id, e);
+                    errors.push((
+                        component_id.clone(),
+                        e.context("Failed to unload component"),
+                    ));
+                }
+            }
+        }
+
+        Self::finish(lockfile, errors)
+    }
 
-        // TODO: Implement digest verification
-        // 1. Get the component bytes from the downloaded artifact
-        // 2. Compute SHA-256 hash
-        // 3. Compare with expected_digest (strip "sha256:" prefix)
+    /// The policy currently stored for a component, if any
+    async fn current_policy(&self, component_id: &str) -> Result<Option<PolicyDocument>> {
+        let Some(info) = self.lifecycle_manager.get_policy_info(component_id).await else {
+            return Ok(None);
+        };
+        if !info.local_path.exists() {
+            return Ok(None);
+        }
+        // A policy that no longer parses has drifted as well
+        Ok(PolicyParser::parse_file(&info.local_path).ok())
+    }
+}
 
+/// A single change needed to reconcile installed components with a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanAction {
+    /// The component is not installed yet
+    Install { component_id: String, uri: String },
+    /// The component is installed from a different artifact than the manifest or lockfile pins
+    Reinstall { component_id: String, uri: String },
+    /// The component is installed but its policy differs from the manifest
+    UpdatePolicy { component_id: String },
+    /// The component is installed but not listed in the manifest
+    Unload { component_id: String },
+    /// The component is installed with the policy the manifest declares
+    Keep { component_id: String },
+}
+
+impl PlanAction {
+    /// The component the action applies to
+    pub fn component_id(&self) -> &str {
+        match self {
+            PlanAction::Install { component_id, .. }
+            | PlanAction::Reinstall { component_id, .. }
+            | PlanAction::UpdatePolicy { component_id }
+            | PlanAction::Unload { component_id }
+            | PlanAction::Keep { component_id } => component_id,
+        }
+    }
+}
+
+/// The changes needed to reconcile installed components with a manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconcilePlan {
+    pub actions: Vec<PlanAction>,
+    /// Components that could not be planned, by name, with the reason
+    pub errors: Vec<(String, String)>,
+}
+
+impl ReconcilePlan {
+    /// Whether applying the plan would change anything
+    pub fn has_changes(&self) -> bool {
+        self.actions
+            .iter()
+            .any(|action| !matches!(action, PlanAction::Keep { .. }))
+    }
+}
+
+impl fmt::Display for ReconcilePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mut install, mut update, mut unload) = (0, 0, 0);
+        for action in &self.actions {
+            match action {
+                PlanAction::Install { component_id, uri } => {
+                    install += 1;
+                    writeln!(f, "  + install {component_id} ({uri})")?;
+                }
+                PlanAction::Reinstall { component_id, uri } => {
+                    update += 1;
+                    writeln!(f, "  ~ reinstall {component_id} ({uri})")?;
+                }
+                PlanAction::UpdatePolicy { component_id } => {
+                    update += 1;
+                    writeln!(f, "  ~ update policy {component_id}")?;
+                }
+                PlanAction::Unload { component_id } => {
+                    unload += 1;
+                    writeln!(f, "  - unload {component_id}")?;
+                }
+                PlanAction::Keep { component_id } => {
+                    writeln!(f, "    keep {component_id}")?;
+                }
+            }
+        }
+        for (component_name, error) in &self.errors {
+            writeln!(f, "  ! {component_name}: {error}")?;
+        }
+        write!(
+            f,
+            "Plan: {install} to install, {update} to update, {unload} to unload."
+        )?;
+        if !self.errors.is_empty() {
+            write!(f, " {} could not be planned.", self.errors.len())?;
+        }
         Ok(())
     }
 }
 
-/// Hash a string to create a temporary filename
-fn hash_string(s: &str) -> String {
-    // Simple hash for temporary filenames
-    // In production, we'd use a proper hash function
-    let hash = s
-        .bytes()
-        .fold(0u64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u64));
-    format!("{:016x}", hash)
+/// Provision the manifest at `manifest_path`, pinning components to the digests in its
+/// lockfile and rewriting the lockfile when they change. In reconcile mode components that
+/// the manifest does not list are unloaded first.
+pub async fn apply_manifest(
+    manifest: &ProvisioningManifest,
+    manifest_path: &Path,
+    lifecycle_manager: &LifecycleManager,
+    reconcile: bool,
+) -> Result<()> {
+    let lockfile_path = Lockfile::path_for_manifest(manifest_path);
+    let existing_lockfile = Lockfile::load(&lockfile_path)?;
+
+    let provisioner = ProvisioningController::new(
+        manifest,
+        lifecycle_manager,
+        lifecycle_manager.secrets_manager(),
+    )
+    .with_lockfile(existing_lockfile.clone());
+
+    let resolved_lockfile = if reconcile {
+        provisioner.reconcile().await
+    } else {
+        provisioner.provision().await
+    }
+    .context("Component provisioning failed")?;
+
+    if existing_lockfile.as_ref() != Some(&resolved_lockfile) {
+        resolved_lockfile.save(&lockfile_path)?;
+        tracing::info!("Wrote lockfile to {}", lockfile_path.display());
+    }
+
+    Ok(())
+}
+
+/// The installed components captured as a provisioning manifest
+#[derive(Debug)]
+pub struct ManifestExport {
+    /// Manifest declaring every component that could be expressed
+    pub manifest: ProvisioningManifest,
+    /// Components left out of the manifest, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Capture the components in the component directory and their stored policies as a manifest
+/// that provisions the same install
+pub async fn export_manifest(lifecycle_manager: &LifecycleManager) -> ManifestExport {
+    let mut components = Vec::new();
+    let mut skipped = Vec::new();
+
+    for component_id in lifecycle_manager.list_components_known().await {
+        match export_component(lifecycle_manager, &component_id).await {
+            Ok(component) => components.push(component),
+            Err(e) => skipped.push((component_id, format!("{e:#}"))),
+        }
+    }
+
+    ManifestExport {
+        manifest: ProvisioningManifest {
+            version: 1,
+            components,
+        },
+        skipped,
+    }
+}
+
+async fn export_component(
+    lifecycle_manager: &LifecycleManager,
+    component_id: &str,
+) -> Result<ComponentDeclaration> {
+    // The copy in the component directory is wassette's own storage, not something a manifest
+    // can provision from
+    let uri = lifecycle_manager
+        .get_component_source_uri(component_id)
+        .await
+        .context("The URI the component was installed from is not recorded")?;
+
+    // Components without a policy are exported without permissions and provision without one
+    let (permissions, args) = match lifecycle_manager.get_policy_info(component_id).await {
+        Some(info) => {
+            let policy = PolicyParser::parse_file(&info.local_path)
+                .context("Failed to parse stored policy")?;
+            (
+                permission_synthesis::inline_permissions_from_policy(&policy)?,
+                policy.args,
+            )
+        }
+        None => (InlinePermissions::default(), None),
+    };
+
+    Ok(ComponentDeclaration {
+        uri,
+        name: None,
+        digest: None,
+        policy: None,
+        permissions,
+        args,
+        retry_policy: None,
+        source: None,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls;
+
     use super::*;
     use crate::manifest::{
-        EnvironmentPermissions, EnvironmentRule, InlinePermissions, NetworkPermissions, NetworkRule,
+        BackoffStrategy, EnvironmentPermissions, EnvironmentRule, InlinePermissions,
+        NetworkPermissions, NetworkRule, PolicyReference,
     };
 
-    #[test]
-    fn test_hash_string() {
-        let hash1 = hash_string("oci://example.com/component:latest");
-        let hash2 = hash_string("oci://example.com/component:v1.0.0");
+    fn declaration(uri: &str) -> ComponentDeclaration {
+        ComponentDeclaration {
+            uri: uri.to_string(),
+            name: None,
+            digest: None,
+            policy: None,
+            permissions: InlinePermissions::default(),
+            args: None,
+            retry_policy: None,
+            source: None,
+        }
+    }
+
+    async fn plan_for(
+        manifest: &ProvisioningManifest,
+        lifecycle_manager: &LifecycleManager,
+    ) -> ReconcilePlan {
+        ProvisioningController::new(
+            manifest,
+            lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        )
+        .plan()
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plan_installs_missing_and_unloads_extras() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("stale.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        let manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![declaration("oci://ghcr.io/example/weather:1.0.0")],
+        };
+
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+
+        assert_eq!(
+            plan.actions,
+            vec![
+                PlanAction::Install {
+                    component_id: "example_weather".to_string(),
+                    uri: "oci://ghcr.io/example/weather:1.0.0".to_string(),
+                },
+                PlanAction::Unload {
+                    component_id: "stale".to_string(),
+                },
+            ]
+        );
+        assert!(plan.has_changes());
+        assert!(plan
+            .to_string()
+            .ends_with("Plan: 1 to install, 0 to update, 1 to unload."));
+    }
+
+    #[tokio::test]
+    async fn test_plan_continues_past_components_that_fail() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("weather.wasm"), b"").unwrap();
+        std::fs::write(component_dir.path().join("stale.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        let mut broken = declaration("file:///opt/components/weather.wasm");
+        broken.policy = Some(PolicyReference {
+            uri: "file:///nonexistent/weather.policy.yaml".to_string(),
+        });
+        let manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![broken, declaration("file:///nonexistent/time.wasm")],
+        };
+
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert_eq!(
+            plan.actions,
+            vec![
+                PlanAction::Install {
+                    component_id: "time".to_string(),
+                    uri: "file:///nonexistent/time.wasm".to_string(),
+                },
+                PlanAction::Unload {
+                    component_id: "stale".to_string(),
+                },
+            ]
+        );
+        assert_eq!(plan.errors.len(), 1);
+        assert_eq!(plan.errors[0].0, "file:///opt/components/weather.wasm");
+        assert!(plan.errors[0].1.contains("Failed to fetch policy"));
+        assert!(plan.to_string().ends_with("1 could not be planned."));
 
-        // Hashes should be deterministic
-        assert_eq!(hash1, hash_string("oci://example.com/component:latest"));
-        assert_eq!(hash2, hash_string("oci://example.com/component:v1.0.0"));
+        // The failures are reported, and the declared but broken component is not unloaded
+        let err = ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        )
+        .reconcile()
+        .await
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Failed to provision 2 component(s)"));
+        assert!(component_dir.path().join("weather.wasm").exists());
+    }
+
+    #[tokio::test]
+    async fn test_plan_detects_policy_drift() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("weather.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        let mut manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![declaration("file:///opt/components/weather.wasm")],
+        };
+        // No policy stored yet, so the synthesized one has to be written
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert_eq!(
+            plan.actions,
+            vec![PlanAction::UpdatePolicy {
+                component_id: "weather".to_string(),
+            }]
+        );
+
+        let policy =
+            permission_synthesis::synthesize_policy_for_component(&manifest.components[0], None)
+                .unwrap();
+        let policy = serde_yaml::to_string(&policy).unwrap();
+        std::fs::write(component_dir.path().join("weather.policy.yaml"), policy).unwrap();
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert!(!plan.has_changes());
 
-        // Different strings should have different hashes
-        assert_ne!(hash1, hash2);
+        manifest.components[0].permissions.network = Some(NetworkPermissions {
+            allow: vec![NetworkRule {
+                host: "api.example.com".to_string(),
+            }],
+        });
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert!(plan.has_changes());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_leaves_unchanged_components_alone() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("weather.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        // The declared URI does not exist, so reloading the component would fail
+        let manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![declaration("file:///nonexistent/weather.wasm")],
+        };
+        let policy =
+            permission_synthesis::synthesize_policy_for_component(&manifest.components[0], None)
+                .unwrap();
+        std::fs::write(
+            component_dir.path().join("weather.policy.yaml"),
+            serde_yaml::to_string(&policy).unwrap(),
+        )
+        .unwrap();
+
+        let lockfile = ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        )
+        .reconcile()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            lockfile.components,
+            vec![LockedComponent {
+                uri: "file:///nonexistent/weather.wasm".to_string(),
+                digest: wassette::sha256_digest(b""),
+            }]
+        );
+        assert!(lifecycle_manager.list_components().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plan_reinstalls_components_pinned_to_another_digest() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("weather.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        let mut manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![declaration("file:///opt/components/weather.wasm")],
+        };
+        manifest.components[0].digest = Some(format!("sha256:{}", "0".repeat(64)));
+
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert_eq!(
+            plan.actions,
+            vec![PlanAction::Reinstall {
+                component_id: "weather".to_string(),
+                uri: "file:///opt/components/weather.wasm".to_string(),
+            }]
+        );
+
+        manifest.components[0].digest = Some(wassette::sha256_digest(b""));
+        let plan = plan_for(&manifest, &lifecycle_manager).await;
+        assert!(!plan
+            .actions
+            .iter()
+            .any(|action| matches!(action, PlanAction::Reinstall { .. })));
+    }
+
+    /// Serves `body` over HTTPS with a self-signed certificate, answering the first `failures`
+    /// requests with `status`
+    async fn flaky_https_server(
+        failures: usize,
+        status: u16,
+        body: &'static [u8],
+    ) -> (String, Arc<AtomicUsize>) {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let cert = rcgen::generate_simple_self_signed(vec!["127.0.0.1".into()]).unwrap();
+        let key =
+            rustls::pki_types::PrivateKeyDer::try_from(cert.signing_key.serialize_der()).unwrap();
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.cert.der().clone()], key)
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    continue;
+                };
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let (status, body) = if n < failures {
+                    (status, &b"unavailable"[..])
+                } else {
+                    (200, body)
+                };
+                let head = format!(
+                    "HTTP/1.1 {status} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (format!("https://{addr}/weather.wasm"), requests)
+    }
+
+    async fn provision_with_retries(url: &str) -> Result<Lockfile> {
+        let component_dir = tempfile::tempdir().unwrap();
+        let secrets_dir = tempfile::tempdir().unwrap();
+        let lifecycle_manager = LifecycleManager::builder(component_dir.path())
+            .with_secrets_dir(secrets_dir.path())
+            .with_http_client(
+                reqwest::Client::builder()
+                    .danger_accept_invalid_certs(true)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .await
+            .unwrap();
+        let mut component = declaration(url);
+        component.retry_policy = Some(RetryPolicy {
+            attempts: 3,
+            backoff: BackoffStrategy::Linear { increment_ms: 10 },
+        });
+        let manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![component],
+        };
+
+        ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        )
+        .provision()
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_provision_retries_transient_download_failures() {
+        // The download succeeds on the third attempt. The bytes are not a component, and that
+        // failure is not retried.
+        let (url, requests) = flaky_https_server(2, 503, b"not a component").await;
+        let err = provision_with_retries(&url).await.unwrap_err();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert!(!format!("{err:#}").contains("503"), "{err:#}");
+
+        let (url, requests) = flaky_https_server(5, 404, b"not a component").await;
+        let err = provision_with_retries(&url).await.unwrap_err();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(format!("{err:#}").contains("404"), "{err:#}");
+    }
+
+    /// Write the metadata wassette records when it loads a component from `source_uri`
+    fn record_source_uri(component_dir: &Path, component_id: &str, source_uri: &str) {
+        let metadata = serde_json::json!({
+            "component_id": component_id,
+            "tool_schemas": [],
+            "function_identifiers": [],
+            "tool_names": [],
+            "validation_stamp": { "file_size": 0, "mtime": 0, "content_hash": null },
+            "created_at": 0,
+            "source_uri": source_uri,
+        });
+        std::fs::write(
+            component_dir.join(format!("{component_id}.metadata.json")),
+            metadata.to_string(),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_manifest() {
+        let component_dir = tempfile::tempdir().unwrap();
+        std::fs::write(component_dir.path().join("weather.wasm"), b"").unwrap();
+        std::fs::write(
+            component_dir.path().join("weather.policy.yaml"),
+            "version: \"1.0\"\nargs: [weather, --metric]\npermissions:\n  network:\n    allow:\n      - host: api.example.com\n",
+        )
+        .unwrap();
+        record_source_uri(component_dir.path(), "weather", "oci://ghcr.io/weather:1.0");
+        std::fs::write(component_dir.path().join("unrestricted.wasm"), b"").unwrap();
+        record_source_uri(
+            component_dir.path(),
+            "unrestricted",
+            "https://example.com/unrestricted.wasm",
+        );
+        std::fs::write(component_dir.path().join("unknown.wasm"), b"").unwrap();
+
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
+        let export = export_manifest(&lifecycle_manager).await;
+
+        // Without a recorded source there is nothing a manifest could provision from
+        assert_eq!(export.skipped.len(), 1);
+        assert_eq!(export.skipped[0].0, "unknown");
+
+        let mut components = export.manifest.components.clone();
+        components.sort_by(|a, b| a.uri.cmp(&b.uri));
+        let [unrestricted, weather] = components.as_slice() else {
+            panic!("expected two exported components, got {components:?}");
+        };
+        assert_eq!(unrestricted.uri, "https://example.com/unrestricted.wasm");
+        assert!(unrestricted.permissions.is_empty());
+        assert!(unrestricted.args.is_none());
+        assert_eq!(weather.uri, "oci://ghcr.io/weather:1.0");
+        assert_eq!(
+            weather.args,
+            Some(vec!["weather".to_string(), "--metric".to_string()])
+        );
+
+        // The exported manifest provisions the same policies it was captured from
+        let yaml = serde_yaml::to_string(&export.manifest).unwrap();
+        assert!(!yaml.contains("permissions: {}"));
+        let manifest = ProvisioningManifest::from_yaml(&yaml, &HashMap::new()).unwrap();
+        manifest.validate().unwrap();
+        let controller = ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        );
+        for component in &manifest.components {
+            let component_id = component_id_from_uri(&component.uri).unwrap();
+            let provisioned = controller.desired_policy(component).await.unwrap();
+            let stored = controller.current_policy(&component_id).await.unwrap();
+            assert_eq!(
+                provisioned.map(|policy| (policy.permissions, policy.args)),
+                stored.map(|policy| (policy.permissions, policy.args))
+            );
+        }
     }
 
     #[test]
@@ -244,6 +1074,7 @@ mod tests {
             uri: "oci://example.com/test:latest".to_string(),
             name: Some("test".to_string()),
             digest: None,
+            policy: None,
             permissions: InlinePermissions {
                 environment: Some(EnvironmentPermissions {
                     allow: vec![EnvironmentRule {
@@ -255,7 +1086,9 @@ mod tests {
                 storage: None,
                 resources: None,
             },
+            args: None,
             retry_policy: None,
+            source: None,
         };
 
         let _temp_dir = tempfile::tempdir().unwrap();
@@ -272,14 +1105,18 @@ mod tests {
         std::env::remove_var("TEST_API_KEY");
     }
 
-    #[test]
-    fn test_synthesize_policy() {
-        let _temp_dir = tempfile::tempdir().unwrap();
+    #[tokio::test]
+    async fn test_synthesize_policy() {
+        let component_dir = tempfile::tempdir().unwrap();
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
 
         let component = ComponentDeclaration {
             uri: "oci://example.com/test:latest".to_string(),
             name: Some("test".to_string()),
             digest: None,
+            policy: None,
             permissions: InlinePermissions {
                 network: Some(NetworkPermissions {
                     allow: vec![NetworkRule {
@@ -290,19 +1127,65 @@ mod tests {
                 environment: None,
                 resources: None,
             },
+            args: None,
             retry_policy: None,
+            source: None,
         };
 
-        let _manifest = ProvisioningManifest {
+        let manifest = ProvisioningManifest {
             version: 1,
             components: vec![component.clone()],
         };
+        let controller = ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        );
 
-        // Create a mock provisioning controller
-        // (We can't fully initialize it without mocks, but we can test the helper)
+        let policy = controller
+            .desired_policy(&component)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(policy.permissions.network.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_policy_from_reference() {
+        let component_dir = tempfile::tempdir().unwrap();
+        let lifecycle_manager = LifecycleManager::new_unloaded(component_dir.path())
+            .await
+            .unwrap();
 
-        // Verify hash is deterministic
-        let hash = hash_string(&component.uri);
-        assert_eq!(hash, hash_string(&component.uri));
+        let shared_policy = component_dir.path().join("shared.yaml");
+        std::fs::write(
+            &shared_policy,
+            "version: \"1.0\"\npermissions:\n  environment:\n    allow:\n      - key: SHARED_KEY\n",
+        )
+        .unwrap();
+
+        let mut component = declaration("oci://example.com/test:latest");
+        component.policy = Some(PolicyReference {
+            uri: format!("file://{}", shared_policy.display()),
+        });
+        let manifest = ProvisioningManifest {
+            version: 1,
+            components: vec![component.clone()],
+        };
+        let controller = ProvisioningController::new(
+            &manifest,
+            &lifecycle_manager,
+            lifecycle_manager.secrets_manager(),
+        );
+
+        let policy = controller
+            .desired_policy(&component)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            policy.permissions.environment.unwrap().allow.unwrap()[0].key,
+            "SHARED_KEY"
+        );
     }
 }
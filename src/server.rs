@@ -9,7 +9,7 @@ use std::sync::{Arc, Mutex};
 
 use mcp_server::{
     handle_prompts_list, handle_resources_list, handle_tools_call, handle_tools_list,
-    LifecycleManager,
+    ComponentRegistry, LifecycleManager,
 };
 use rmcp::model::{
     CallToolRequestParam, CallToolResult, ErrorData, ListPromptsResult, ListResourcesResult,
@@ -22,6 +22,7 @@ use rmcp::ServerHandler;
 #[derive(Clone)]
 pub struct McpServer {
     lifecycle_manager: LifecycleManager,
+    registry: ComponentRegistry,
     peer: Arc<Mutex<Option<rmcp::Peer<rmcp::RoleServer>>>>,
     disable_builtin_tools: bool,
 }
@@ -31,10 +32,16 @@ impl McpServer {
     ///
     /// # Arguments
     /// * `lifecycle_manager` - The lifecycle manager for handling component operations
+    /// * `registry` - The component registry searched by the `search-components` tool
     /// * `disable_builtin_tools` - Whether to disable built-in tools
-    pub fn new(lifecycle_manager: LifecycleManager, disable_builtin_tools: bool) -> Self {
+    pub fn new(
+        lifecycle_manager: LifecycleManager,
+        registry: ComponentRegistry,
+        disable_builtin_tools: bool,
+    ) -> Self {
         Self {
             lifecycle_manager,
+            registry,
             peer: Arc::new(Mutex::new(None)),
             disable_builtin_tools,
         }
@@ -95,6 +102,7 @@ Key points:
             let result = handle_tools_call(
                 params,
                 &self.lifecycle_manager,
+                &self.registry,
                 peer_clone,
                 disable_builtin_tools,
             )
@@ -8,12 +8,25 @@ use std::path::PathBuf;
 
 use anyhow::{bail, Context, Result};
 
-use crate::registry;
-
 mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
+/// Format a byte count with a binary unit, e.g. `1.5 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
 /// Parse environment variable in KEY=VALUE format
 pub fn parse_env_var(s: &str) -> Result<(String, String), String> {
     match s.split_once('=') {
@@ -79,12 +92,6 @@ pub fn load_env_file(path: &PathBuf) -> Result<HashMap<String, String>, anyhow::
     Ok(env_vars)
 }
 
-/// Load and parse the component registry JSON
-pub fn load_component_registry() -> Result<Vec<registry::RegistryComponent>> {
-    const COMPONENT_REGISTRY: &str = include_str!("../component-registry.json");
-    registry::parse_registry(COMPONENT_REGISTRY).context("Failed to parse component registry")
-}
-
 /// Formats build information similar to agentgateway's version output
 pub fn format_build_info() -> String {
     // Parse Rust version more robustly by looking for version pattern
@@ -127,6 +134,13 @@ pub fn format_build_info() -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+
     #[test]
     fn test_version_format_contains_required_fields() {
         let version_info = format_build_info();
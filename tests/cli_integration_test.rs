@@ -184,6 +184,38 @@ async fn test_cli_component_load_and_list() -> Result<()> {
     Ok(())
 }
 
+#[test(tokio::test)]
+async fn test_cli_tool_search() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let component_path = build_fetch_component().await?;
+
+    let (_, stderr, exit_code) = ctx
+        .run_command(&[
+            "component",
+            "load",
+            &format!("file://{}", component_path.display()),
+        ])
+        .await?;
+    assert_eq!(exit_code, 0, "Load command failed with stderr: {stderr}");
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["tool", "search", "fetch url"]).await?;
+    assert_eq!(exit_code, 0, "Search command failed with stderr: {stderr}");
+
+    let output: Value = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["status"], "success");
+    assert_eq!(output["tools"][0]["name"], "fetch");
+    assert!(output["tools"][0]["component_id"].is_string());
+
+    let (stdout, _, exit_code) = ctx
+        .run_command(&["tool", "search", "xyz123notfound"])
+        .await?;
+    assert_eq!(exit_code, 0);
+    let output: Value = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["count"], 0);
+
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_cli_component_load_unload() -> Result<()> {
     let ctx = CliTestContext::new().await?;
@@ -229,6 +261,168 @@ async fn test_cli_component_load_unload() -> Result<()> {
     Ok(())
 }
 
+#[test(tokio::test)]
+async fn test_cli_component_versions_and_rollback() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let component_path = build_fetch_component().await?;
+
+    // A second version of the component that only differs by a trailing custom section
+    let v2_dir = tempfile::tempdir()?;
+    let v2_path = v2_dir.path().join(component_path.file_name().unwrap());
+    let mut v2_bytes = tokio::fs::read(&component_path).await?;
+    v2_bytes.extend_from_slice(&[0x00, 0x03, 0x02, b'v', b'2']);
+    tokio::fs::write(&v2_path, &v2_bytes).await?;
+
+    for path in [&component_path, &v2_path] {
+        let (_, stderr, exit_code) = ctx
+            .run_command(&["component", "load", &format!("file://{}", path.display())])
+            .await?;
+        assert_eq!(exit_code, 0, "Load command failed with stderr: {stderr}");
+    }
+    let component_id = component_path.file_stem().unwrap().to_str().unwrap();
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&["component", "versions", component_id])
+        .await?;
+    assert_eq!(
+        exit_code, 0,
+        "Versions command failed with stderr: {stderr}"
+    );
+    let output: Value = ctx.parse_json_output(&stdout)?;
+    let versions = output["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["active"], true);
+    assert_eq!(versions[1]["active"], false);
+    let previous_digest = versions[1]["digest"].as_str().unwrap().to_string();
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&["component", "rollback", component_id])
+        .await?;
+    assert_eq!(
+        exit_code, 0,
+        "Rollback command failed with stderr: {stderr}"
+    );
+    let output: Value = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["status"], "component rolled back successfully");
+    assert_eq!(output["digest"], previous_digest.as_str());
+
+    let (stdout, _, _) = ctx
+        .run_command(&["component", "versions", component_id])
+        .await?;
+    let output: Value = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["versions"][0]["digest"], previous_digest.as_str());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_cli_component_update_skips_local_components() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let component_path = build_fetch_component().await?;
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&[
+            "component",
+            "load",
+            &format!("file://{}", component_path.display()),
+        ])
+        .await?;
+    assert_eq!(exit_code, 0, "Load command failed with stderr: {stderr}");
+    let component_id = ctx.parse_json_output(&stdout)?["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["component", "outdated"]).await?;
+    assert_eq!(
+        exit_code, 0,
+        "Outdated command failed with stderr: {stderr}"
+    );
+    assert_eq!(ctx.parse_json_output(&stdout)?["count"], 0);
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["component", "update", "--all"]).await?;
+    assert_eq!(exit_code, 0, "Update command failed with stderr: {stderr}");
+    let output = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["status"], "success");
+    assert_eq!(output["updated"].as_array().unwrap().len(), 0);
+
+    let (_, stderr, exit_code) = ctx
+        .run_command(&["component", "update", &component_id])
+        .await?;
+    assert_ne!(exit_code, 0);
+    assert!(stderr.contains("not installed from an OCI tag"), "{stderr}");
+
+    let (_, _, exit_code) = ctx.run_command(&["component", "update"]).await?;
+    assert_ne!(exit_code, 0, "update needs a component ID or --all");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_cli_gc_removes_orphaned_artifacts() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let orphan = ctx.component_dir.join("unloaded.cwasm");
+    tokio::fs::write(&orphan, "precompiled").await?;
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["gc", "--dry-run"]).await?;
+    assert_eq!(exit_code, 0, "Gc command failed with stderr: {stderr}");
+    let output = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["count"], 1);
+    assert_eq!(output["reclaimed_bytes"], 11);
+    assert!(orphan.exists(), "dry run must not remove anything");
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["gc"]).await?;
+    assert_eq!(exit_code, 0, "Gc command failed with stderr: {stderr}");
+    assert_eq!(ctx.parse_json_output(&stdout)?["count"], 1);
+    assert!(!orphan.exists());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_cli_component_verify_reports_external_changes() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let component_path = build_fetch_component().await?;
+    let (_, stderr, exit_code) = ctx
+        .run_command(&[
+            "component",
+            "load",
+            &format!("file://{}", component_path.display()),
+        ])
+        .await?;
+    assert_eq!(exit_code, 0, "Load command failed with stderr: {stderr}");
+    let component_id = component_path.file_stem().unwrap().to_str().unwrap();
+
+    let (stdout, stderr, exit_code) = ctx.run_command(&["component", "verify"]).await?;
+    assert_eq!(exit_code, 0, "Verify command failed with stderr: {stderr}");
+    assert_eq!(ctx.parse_json_output(&stdout)?["status"], "verified");
+
+    // A policy written by hand is not trusted
+    let policy_path = ctx
+        .component_dir
+        .join(format!("{component_id}.policy.yaml"));
+    tokio::fs::write(&policy_path, "version: \"1.0\"\n").await?;
+    let (stdout, _, exit_code) = ctx.run_command(&["component", "verify"]).await?;
+    assert_eq!(exit_code, 1);
+    let output = ctx.parse_json_output(&stdout)?;
+    assert_eq!(output["status"], "modified");
+    assert_eq!(output["violations"][0]["component_id"], component_id);
+    assert_eq!(output["violations"][0]["artifact"], "policy");
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&["component", "verify", "--accept"])
+        .await?;
+    assert_eq!(exit_code, 0, "Verify command failed with stderr: {stderr}");
+    assert_eq!(ctx.parse_json_output(&stdout)?["status"], "accepted");
+
+    let (stdout, _, exit_code) = ctx.run_command(&["component", "verify"]).await?;
+    assert_eq!(exit_code, 0);
+    assert_eq!(ctx.parse_json_output(&stdout)?["status"], "verified");
+
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_cli_component_load_invalid_path() -> Result<()> {
     let ctx = CliTestContext::new().await?;
@@ -245,6 +439,31 @@ async fn test_cli_component_load_invalid_path() -> Result<()> {
     Ok(())
 }
 
+#[test(tokio::test)]
+async fn test_cli_run_manifest_failure_keeps_stdout_clean() -> Result<()> {
+    let ctx = CliTestContext::new().await?;
+    let manifest_path = ctx.temp_dir.path().join("manifest.yaml");
+    tokio::fs::write(
+        &manifest_path,
+        "version: 1\ncomponents:\n  - uri: file:///nonexistent/missing.wasm\n",
+    )
+    .await?;
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&["run", "--manifest", manifest_path.to_str().unwrap()])
+        .await?;
+
+    // Stdout carries the MCP transport, so provisioning errors may only appear on stderr
+    assert_ne!(exit_code, 0, "Command should have failed");
+    assert!(stdout.is_empty(), "Unexpected output on stdout: {stdout}");
+    assert!(
+        stderr.contains("Component provisioning failed"),
+        "Missing provisioning error on stderr: {stderr}"
+    );
+
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_cli_component_unload_invalid_id() -> Result<()> {
     let ctx = CliTestContext::new().await?;
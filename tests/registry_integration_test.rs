@@ -198,6 +198,40 @@ async fn test_registry_search_matches_description() -> Result<()> {
     Ok(())
 }
 
+#[test(tokio::test)]
+async fn test_registry_search_extra_source() -> Result<()> {
+    let ctx = RegistryTestContext::new().await?;
+
+    let index = ctx.temp_dir.path().join("index.json");
+    tokio::fs::write(
+        &index,
+        r#"[{"name": "Internal Weather", "description": "Weather from the internal mirror", "uri": "oci://registry.internal/weather:1.0.0"}]"#,
+    )
+    .await?;
+
+    let (stdout, stderr, exit_code) = ctx
+        .run_command(&[
+            "registry",
+            "search",
+            "weather",
+            "--registry",
+            index.to_str().unwrap(),
+        ])
+        .await?;
+
+    assert_eq!(exit_code, 0, "Command failed: {}", stderr);
+
+    let json = ctx.parse_json_output(&stdout)?;
+    assert_eq!(json["count"], 3); // The internal entry plus the two built-in weather components
+    assert!(json["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|c| c["name"] == "Internal Weather"));
+
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_registry_get_nonexistent() -> Result<()> {
     let ctx = RegistryTestContext::new().await?;